@@ -0,0 +1,20 @@
+use std::process::Command;
+
+fn main() {
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        .filter(|commit| !commit.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=CLIO_GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=CLIO_BUILD_DATE={}", chrono::Utc::now().format("%Y-%m-%d"));
+    // A missing path always counts as changed, which would rerun this on every build of a source tarball
+    if std::path::Path::new(".git").exists() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        println!("cargo:rerun-if-changed=.git/refs");
+    }
+}
@@ -0,0 +1,45 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+pub const FILE_NAME: &str = ".clioignore";
+
+// Parsed once per project root for the whole session
+fn matcher(root: &Path) -> Arc<Gitignore> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, Arc<Gitignore>>>> = OnceLock::new();
+    let root = root.canonicalize().unwrap_or(root.to_path_buf());
+    let mut cache = CACHE.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .entry(root.clone())
+        .or_insert_with(|| {
+            let mut builder = GitignoreBuilder::new(&root);
+            let path = root.join(FILE_NAME);
+            if path.is_file() {
+                if let Some(e) = builder.add(&path) {
+                    warn!("Warning: {}: {}", path.display(), e);
+                }
+            }
+            Arc::new(builder.build().unwrap_or_else(|e| {
+                warn!("Warning: ignoring {}: {}", path.display(), e);
+                Gitignore::empty()
+            }))
+        })
+        .clone()
+}
+
+// True when `path` (absolute, under `root`) or one of its parents matches .clioignore
+pub fn is_ignored(root: &Path, path: &Path, is_dir: bool) -> bool {
+    let matcher = matcher(root);
+    if matcher.is_empty() {
+        return false;
+    }
+    let path = path.canonicalize().unwrap_or(path.to_path_buf());
+    // The matcher panics on paths outside its root
+    path.starts_with(matcher.path()) && matcher.matched_path_or_any_parents(&path, is_dir).is_ignore()
+}
+
+// Number of patterns, or None when the project has no .clioignore
+pub fn pattern_count(root: &Path) -> Option<u64> {
+    root.join(FILE_NAME).is_file().then(|| matcher(root).num_ignores() + matcher(root).num_whitelists())
+}
@@ -0,0 +1,1642 @@
+use crate::config::{self, Config};
+use crate::keys;
+use crate::llm::{self, ChatMeta};
+use crate::models;
+use crate::policy::Decision;
+use crate::project;
+use crate::rate_limit::RateLimiter;
+use crate::tools::{self, resolve_path, ToolResponse};
+use crate::undo::{Change, Snapshot};
+use crate::stats::PromptStats;
+use crate::{
+    check_command_write, clioignore, config_file, confirm, dir_models, gather_repo_context, git, output, paths, policy, print_stats_line, readiness, search, set_auto_approve, style, submit_prompt,
+    transcript, version, Session,
+};
+use std::env;
+use std::future::Future;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::time::Instant;
+
+// Single source of truth for slash-commands: drives dispatch, /help and tab completion
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub arg: ArgKind,
+    pub handler: Handler,
+}
+
+pub enum ArgKind {
+    None,
+    Model,
+    Path,
+    Branch,
+    Profile,
+    // Setting names for /set and /get
+    Setting,
+    Words(&'static [&'static str]),
+}
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+// Handlers get the text after the command name ("" when there is none)
+pub enum Handler {
+    Sync(fn(&mut Session, &str) -> CommandResult),
+    Async(for<'a> fn(&'a mut Session, &'a str) -> BoxFuture<'a, CommandResult>),
+}
+
+pub enum CommandResult {
+    Handled,
+    Quit,
+    Prompt(String),
+    NotCommand,
+}
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "/help",
+        usage: "/help",
+        help: "Show this help",
+        arg: ArgKind::None,
+        handler: Handler::Sync(cmd_help),
+    },
+    CommandSpec {
+        name: "/models",
+        usage: "/models [refresh]",
+        help: "List available models, or ask the providers for their current lists",
+        arg: ArgKind::Words(&["refresh"]),
+        handler: Handler::Async(cmd_models),
+    },
+    CommandSpec {
+        name: "/model",
+        usage: "/model [--global] <name>",
+        help: "Switch model (--global: not remembered for this directory with PERSIST_MODEL_PER_DIR)",
+        arg: ArgKind::Model,
+        handler: Handler::Sync(cmd_model),
+    },
+    CommandSpec {
+        name: "/config",
+        usage: "/config [show|path|diff|edit|save]",
+        help: "Show effective settings and their sources, config file locations, only changed values, edit the file, or save session changes to it",
+        arg: ArgKind::Words(&["show", "path", "diff", "edit", "save"]),
+        handler: Handler::Sync(cmd_config),
+    },
+    CommandSpec {
+        name: "/cd",
+        usage: "/cd [dir]",
+        help: "Change the working directory, applying its .env and .clio.toml (no argument prints it)",
+        arg: ArgKind::Path,
+        handler: Handler::Sync(cmd_cd),
+    },
+    CommandSpec {
+        name: "/reload",
+        usage: "/reload",
+        help: "Re-read .env and config files without restarting (asks before switching provider or model)",
+        arg: ArgKind::None,
+        handler: Handler::Sync(cmd_reload),
+    },
+    CommandSpec {
+        name: "/profile",
+        usage: "/profile [name|none]",
+        help: "List config profiles, or switch to one (reloads provider, model, keys and limits)",
+        arg: ArgKind::Profile,
+        handler: Handler::Sync(cmd_profile),
+    },
+    CommandSpec {
+        name: "/keys",
+        usage: "/keys [set <provider> [--keyring] | migrate]",
+        help: "Show where each API key is stored, set one (in .env or the OS keyring), or move .env keys to the keyring",
+        arg: ArgKind::Words(&["set", "migrate"]),
+        handler: Handler::Sync(cmd_keys),
+    },
+    CommandSpec {
+        name: "/tools",
+        usage: "/tools",
+        help: "Show the resolved tool policy: allow, confirm or deny for each action, and denied paths",
+        arg: ArgKind::None,
+        handler: Handler::Sync(cmd_tools),
+    },
+    CommandSpec {
+        name: "/mode",
+        usage: "/mode [standard|careful|read-only|auto]",
+        help: "Show or switch the tool permission mode for this session",
+        arg: ArgKind::Words(&["standard", "careful", "read-only", "auto"]),
+        handler: Handler::Sync(cmd_mode),
+    },
+    CommandSpec {
+        name: "/set",
+        usage: "/set <setting> <value> | /set limit [<name> <n>]",
+        help: "Change a setting for this session (model, provider, temperature, max_tokens, stream, limits, ...); /set limit lists the limits",
+        arg: ArgKind::Setting,
+        handler: Handler::Sync(cmd_set),
+    },
+    CommandSpec {
+        name: "/get",
+        usage: "/get <setting>",
+        help: "Show a setting's current value and where it came from",
+        arg: ArgKind::Setting,
+        handler: Handler::Sync(cmd_get),
+    },
+    CommandSpec {
+        name: "/usage",
+        usage: "/usage",
+        help: "Show token usage and time spent this session",
+        arg: ArgKind::None,
+        handler: Handler::Sync(cmd_usage),
+    },
+    CommandSpec {
+        name: "/stats",
+        usage: "/stats",
+        help: "Same as /usage, with the time spent per tool action",
+        arg: ArgKind::None,
+        handler: Handler::Sync(cmd_usage),
+    },
+    CommandSpec {
+        name: "/clear",
+        usage: "/clear",
+        help: "Clear the conversation and reset the tool rate limiter",
+        arg: ArgKind::None,
+        handler: Handler::Sync(cmd_clear),
+    },
+    CommandSpec {
+        name: "/branch",
+        usage: "/branch <name>",
+        help: "Save the transcript (for /export and /copy) as a named branch; the model is not sent it",
+        arg: ArgKind::Branch,
+        handler: Handler::Sync(cmd_branch),
+    },
+    CommandSpec {
+        name: "/checkout",
+        usage: "/checkout <name>",
+        help: "Switch to a saved transcript branch",
+        arg: ArgKind::Branch,
+        handler: Handler::Sync(cmd_checkout),
+    },
+    CommandSpec {
+        name: "/branches",
+        usage: "/branches",
+        help: "List saved transcript branches",
+        arg: ArgKind::None,
+        handler: Handler::Sync(cmd_branches),
+    },
+    CommandSpec {
+        name: "/undo",
+        usage: "/undo",
+        help: "Revert the last batch of file changes",
+        arg: ArgKind::None,
+        handler: Handler::Sync(cmd_undo),
+    },
+    CommandSpec {
+        name: "/redo",
+        usage: "/redo",
+        help: "Reapply the last undone batch",
+        arg: ArgKind::None,
+        handler: Handler::Sync(cmd_redo),
+    },
+    CommandSpec {
+        name: "/copy",
+        usage: "/copy [code [n]]",
+        help: "Copy the last response (or its n-th code block) to the clipboard",
+        arg: ArgKind::Words(&["code"]),
+        handler: Handler::Sync(cmd_copy),
+    },
+    CommandSpec {
+        name: "/export",
+        usage: "/export [path] [--full]",
+        help: "Save the session transcript as Markdown",
+        arg: ArgKind::Path,
+        handler: Handler::Sync(cmd_export),
+    },
+    CommandSpec {
+        name: "/gen-tests",
+        usage: "/gen-tests <file>",
+        help: "Generate unit tests for a source file",
+        arg: ArgKind::Path,
+        handler: Handler::Async(cmd_gen_tests),
+    },
+    CommandSpec {
+        name: "/gen-readme",
+        usage: "/gen-readme",
+        help: "Draft README.md from the repository structure",
+        arg: ArgKind::None,
+        handler: Handler::Async(cmd_gen_readme),
+    },
+    CommandSpec {
+        name: "/gen-ci",
+        usage: "/gen-ci <github-actions|gitlab-ci|circleci|drone>",
+        help: "Generate a CI config with dependency caching for the detected project type",
+        arg: ArgKind::Words(&["github-actions", "gitlab-ci", "circleci", "drone"]),
+        handler: Handler::Async(cmd_gen_ci),
+    },
+    CommandSpec {
+        name: "/open",
+        usage: "/open <file>[:a-b]",
+        help: "Print a file (or a line range) with line numbers",
+        arg: ArgKind::Path,
+        handler: Handler::Sync(cmd_open),
+    },
+    CommandSpec {
+        name: "/grep",
+        usage: "/grep [-i] [--glob G] [--attach] <pattern> [path]",
+        help: "Search project files",
+        arg: ArgKind::None,
+        handler: Handler::Sync(cmd_grep),
+    },
+    CommandSpec {
+        name: "/transcribe",
+        usage: "/transcribe <path>",
+        help: "Transcribe audio (Groq Whisper) and send it as a prompt",
+        arg: ArgKind::Path,
+        handler: Handler::Async(cmd_transcribe),
+    },
+    CommandSpec {
+        name: "/quiet",
+        usage: "/quiet [on|off]",
+        help: "Hide tool action lines and progress, show only results",
+        arg: ArgKind::Words(&["on", "off"]),
+        handler: Handler::Sync(cmd_quiet),
+    },
+    CommandSpec {
+        name: "/version",
+        usage: "/version",
+        help: "Show version, commit and build date",
+        arg: ArgKind::None,
+        handler: Handler::Sync(cmd_version),
+    },
+    CommandSpec {
+        name: "/quit",
+        usage: "/quit",
+        help: "Exit",
+        arg: ArgKind::None,
+        handler: Handler::Sync(cmd_quit),
+    },
+    CommandSpec {
+        name: "/exit",
+        usage: "/exit",
+        help: "Exit (same as /quit)",
+        arg: ArgKind::None,
+        handler: Handler::Sync(cmd_quit),
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|c| c.name == name)
+}
+
+pub async fn handle(input: &str, session: &mut Session) -> CommandResult {
+    let (name, args) = input.split_once(' ').unwrap_or((input, ""));
+    let Some(command) = find(name) else {
+        return CommandResult::NotCommand;
+    };
+    match command.handler {
+        Handler::Sync(run) => run(session, args),
+        Handler::Async(run) => run(session, args).await,
+    }
+}
+
+fn cmd_help(_: &mut Session, _: &str) -> CommandResult {
+    println!("\nCommands:");
+    for line in help_lines() {
+        println!("{}", line);
+    }
+    println!();
+    CommandResult::Handled
+}
+
+fn help_lines() -> Vec<String> {
+    COMMANDS
+        .iter()
+        .map(|command| {
+            let state = match command.name {
+                "/quiet" => format!(" (now {})", if output::is_quiet() { "on" } else { "off" }),
+                _ => String::new(),
+            };
+            format!("  {:<24} - {}{}", command.usage, command.help, state)
+        })
+        .collect()
+}
+
+fn cmd_models<'a>(session: &'a mut Session, args: &'a str) -> BoxFuture<'a, CommandResult> {
+    Box::pin(async move {
+        match args.trim() {
+            "" => list_models(session),
+            "refresh" => refresh_models(session).await,
+            _ => println!("Usage: /models [refresh]"),
+        }
+        CommandResult::Handled
+    })
+}
+
+async fn refresh_models(session: &mut Session) {
+    info!("{}", style::dim("Asking providers for their models..."));
+    let results = models::fetch_all(session.llm.config()).await;
+    let registry = &mut session.llm.config_mut().models;
+    for (provider, result) in results {
+        let name = provider_name(provider);
+        match result {
+            Ok(ids) => {
+                let added: Vec<String> = ids.iter().filter(|id| registry.discover(id, provider)).cloned().collect();
+                if added.is_empty() {
+                    println!("{}: no new models ({} listed)", name, ids.len());
+                    continue;
+                }
+                println!("Found {} new {} model{}:", added.len(), name, if added.len() == 1 { "" } else { "s" });
+                for id in added {
+                    println!("  {}", id);
+                }
+            }
+            Err(e) => warn!("{}: {}", name, style::yellow(&e)),
+        }
+    }
+}
+
+fn provider_name(provider: &str) -> &str {
+    match provider {
+        "ollama" => "Ollama",
+        "groq" => "Groq",
+        "gemini" => "Gemini",
+        "deepseek" => "DeepSeek",
+        other => other,
+    }
+}
+
+fn list_models(session: &Session) {
+    let config = session.llm.config();
+    println!("\nAvailable models:");
+    for model in config.models.all() {
+        let is_default = config.default_model(&model.provider).as_deref() == Some(model.id.as_str());
+        let mut extra = Vec::new();
+        if let Some(window) = model.context_window {
+            extra.push(format!("{}k context", window / 1000));
+        }
+        if model.custom {
+            extra.push("custom".to_string());
+        }
+        if model.discovered {
+            extra.push("from refresh".to_string());
+        }
+        let extra = if extra.is_empty() { String::new() } else { style::dim(&format!(" [{}]", extra.join(", "))) };
+        println!("  {} {} - {} ({}){}", if is_default { "*" } else { " " }, model.id, model.name, model.provider, extra);
+    }
+    // Configured defaults that are not in the registry
+    for (provider, id) in &config.default_models {
+        if config.models.get(id).is_none() {
+            println!("  * {} ({})", id, provider);
+        }
+    }
+    println!("{}\n", style::dim("  * default for its provider (/set provider <name>)"));
+}
+
+fn cmd_model(session: &mut Session, args: &str) -> CommandResult {
+    let args: Vec<&str> = args.split_whitespace().collect();
+    let (global, model) = match args.as_slice() {
+        ["--global", model] => (true, *model),
+        [model] if *model != "--global" => (false, *model),
+        _ => {
+            println!("Usage: /model [--global] <model_name>");
+            return CommandResult::Handled;
+        }
+    };
+    session.llm.set_model(model);
+    session.llm.config_mut().set_source("model", config::Source::Runtime("/model"));
+    session.llm.config_mut().set_source("provider", config::Source::Runtime("/model"));
+    println!("Switched to: {}", model);
+    if !global {
+        remember_model(session);
+    }
+    CommandResult::Handled
+}
+
+// PERSIST_MODEL_PER_DIR: an explicit switch becomes this project's model
+fn remember_model(session: &Session) {
+    let config = session.llm.config();
+    if !config.persist_model_per_dir {
+        return;
+    }
+    if let Err(e) = dir_models::remember(&session.cwd, &config.provider, &config.model) {
+        warn!("{} cannot remember the model for this directory: {}", style::yellow("Warning:"), e);
+    }
+}
+
+// Switches to the model last chosen in this project root; a --model flag still wins
+pub fn recall_dir_model(session: &mut Session) {
+    let config = session.llm.config();
+    if !config.persist_model_per_dir || matches!(config.source("model"), config::Source::Cli(_)) {
+        return;
+    }
+    let Some((root, saved)) = dir_models::recall(&session.cwd) else {
+        return;
+    };
+    if saved.model == config.model && saved.provider == config.provider {
+        return;
+    }
+    let config = session.llm.config_mut();
+    config.provider = saved.provider;
+    config.model = saved.model;
+    config.set_source("provider", config::Source::Remembered(root.clone()));
+    config.set_source("model", config::Source::Remembered(root.clone()));
+    info!(
+        "{} {} ({}), last chosen in {}",
+        style::dim("Using"),
+        config.model,
+        config.provider,
+        root.display()
+    );
+}
+
+fn cmd_config(session: &mut Session, args: &str) -> CommandResult {
+    let config = session.llm.config();
+    match args.trim() {
+        "" | "show" | "diff" => {
+            let only_changed = args.trim() == "diff";
+            println!();
+            for (name, value) in config.settings() {
+                let source = config.source(name);
+                if only_changed && *source == config::Source::Default {
+                    continue;
+                }
+                let mut origin = style::dim(&source.to_string());
+                if let Some(var) = config.bare_env_var(name) {
+                    origin.push_str(&style::yellow(&format!(" (generic name; CLIO_{} takes precedence)", var)));
+                }
+                println!("  {:<24} {:<32} {}", name, value, origin);
+            }
+            let ignore = match clioignore::pattern_count(&session.cwd) {
+                Some(n) => format!("{} pattern(s)", n),
+                None => "(none)".into(),
+            };
+            println!("  {:<24} {}", clioignore::FILE_NAME, ignore);
+            println!();
+        }
+        "path" => {
+            let mark = |path: &Path, active: bool| {
+                format!("  {}{}", path.display(), if active { style::green(" (loaded)") } else { String::new() })
+            };
+            println!(".env files (first found wins):");
+            let cwd_env = session.cwd.join(".env");
+            println!("{}", mark(&cwd_env, config.env_file.as_deref() == Some(cwd_env.as_path())));
+            for path in Config::env_paths() {
+                println!("{}", mark(&path, config.env_file.as_deref() == Some(path.as_path())));
+            }
+            println!("Config files (first found wins):");
+            for path in config_file::paths() {
+                println!("{}", mark(&path, config.config_file.as_deref() == Some(path.as_path())));
+            }
+            println!("Project file (nearest {} up to the git root):", config_file::PROJECT_FILES.join(" or "));
+            match &config.project_file {
+                Some(path) => println!("{}", mark(path, true)),
+                None => println!("  (none)"),
+            }
+            println!("Data:");
+            let dir = |dir: Option<PathBuf>| dir.map(|d| d.display().to_string()).unwrap_or("(unavailable)".into());
+            println!("  history, sessions: {}", dir(paths::state_dir()));
+            println!("  cache:             {}", dir(paths::cache_dir()));
+            println!("Ignore file:");
+            let ignore = session.cwd.join(clioignore::FILE_NAME);
+            println!("{}", mark(&ignore, ignore.is_file()));
+        }
+        "edit" => edit_config(session),
+        "save" => match session.llm.config_mut().save() {
+            Ok((_, saved)) if saved.is_empty() => println!("No settings changed this session"),
+            Ok((path, saved)) => println!("Saved {} to {}", saved.join(", "), path.display()),
+            Err(e) => warn!("{} {}", style::red("Error:"), e),
+        },
+        _ => println!("Usage: /config [show|path|diff|edit|save]"),
+    }
+    CommandResult::Handled
+}
+
+fn cmd_profile(session: &mut Session, args: &str) -> CommandResult {
+    let name = args.trim();
+    let config = session.llm.config();
+    if name.is_empty() {
+        if config.profiles.is_empty() {
+            let path = config.config_file.clone().or_else(|| config_file::paths().into_iter().next());
+            let path = path.map(|p| p.display().to_string()).unwrap_or("config.toml".into());
+            println!("No profiles. Add [profile.<name>] sections to {}", path);
+        }
+        for profile in &config.profiles {
+            let active = config.profile.as_deref() == Some(profile.as_str());
+            println!("{} {}", if active { style::green("*") } else { " ".into() }, profile);
+        }
+        return CommandResult::Handled;
+    }
+    if name != "none" && !config.profiles.iter().any(|p| p == name) {
+        println!("No profile '{}' (see /profile)", name);
+        return CommandResult::Handled;
+    }
+
+    let name = if name == "none" { "" } else { name };
+    let mut config = Config::load(Some(name), session.interactive);
+    config.set_source("profile", config::Source::Runtime("/profile"));
+    // --flags, /set and /mode outrank the profile, as on /cd and /reload
+    config.carry_overrides(session.llm.config());
+    replace_config(session, config);
+    let config = session.llm.config();
+    println!(
+        "Profile: {} | Model: {} ({})",
+        config.profile.as_deref().unwrap_or("(none)"),
+        config.model,
+        config.provider
+    );
+    readiness::print_problems(config, &config.validate());
+    CommandResult::Handled
+}
+
+// Swaps in a freshly loaded config along with the session state derived from it
+fn replace_config(session: &mut Session, config: Config) {
+    output::set_quiet(config.quiet);
+    set_auto_approve(config.auto_approve);
+    if config.tool_rate_limit != session.llm.config().tool_rate_limit {
+        session.rate_limiter = RateLimiter::new(config.tool_rate_limit);
+    }
+    *session.llm.config_mut() = config;
+}
+
+fn cmd_keys(session: &mut Session, args: &str) -> CommandResult {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    match words.as_slice() {
+        [] => {
+            let config = session.llm.config();
+            for provider in config::PROVIDERS {
+                for setting in Config::key_settings(provider) {
+                    let storage = match config.source(setting) {
+                        config::Source::Env(_) => "environment".to_string(),
+                        config::Source::EnvFile(path, _) => path.display().to_string(),
+                        config::Source::Keyring(_) => "keyring".to_string(),
+                        config::Source::File(path) => format!("{} (plaintext)", path.display()),
+                        config::Source::Runtime(_) => "this session".to_string(),
+                        _ => style::dim("not set"),
+                    };
+                    println!("  {:<12} {:<24} {}", provider, config.setting_var(setting), storage);
+                }
+            }
+        }
+        ["set", provider, flags @ ..] => set_key(session, provider, flags.contains(&"--keyring")),
+        ["migrate"] => migrate_keys(session),
+        _ => println!("Usage: /keys [set <provider> [--keyring] | migrate]"),
+    }
+    CommandResult::Handled
+}
+
+fn set_key(session: &mut Session, provider: &str, keyring: bool) {
+    let settings = Config::key_settings(provider);
+    if settings.is_empty() {
+        println!("{} needs no key (providers with keys: gemini, groq, deepseek, huggingface, bedrock)", provider);
+        return;
+    }
+    for setting in settings {
+        let var = session.llm.config().setting_var(setting);
+        let secret = match keys::read_secret(&format!("{}: ", var)) {
+            Ok(secret) if !secret.is_empty() => secret,
+            Ok(_) => {
+                println!("Cancelled");
+                return;
+            }
+            Err(e) => {
+                warn!("{} {}", style::red("Error:"), e);
+                return;
+            }
+        };
+
+        let mut source = None;
+        if keyring {
+            match keys::set(&var, &secret) {
+                Ok(()) => source = Some(config::Source::Keyring(var.clone())),
+                Err(e) => warn!("{} keyring unavailable ({}); storing in a .env file instead", style::yellow("Warning:"), e),
+            }
+        }
+        let source = match source {
+            Some(source) => source,
+            None => {
+                let config = session.llm.config();
+                let Some(path) = config.env_file.clone().or_else(|| Config::env_paths().into_iter().next()) else {
+                    warn!("{} no .env location (no home directory)", style::red("Error:"));
+                    return;
+                };
+                if let Err(e) = keys::write_env(&path, &var, &secret) {
+                    warn!("{} {}", style::red("Error:"), e);
+                    return;
+                }
+                config::Source::EnvFile(path, var.clone())
+            }
+        };
+        println!("Stored {} in {}", var, source);
+        let current = session.llm.config().source(setting);
+        if keyring && matches!(current, config::Source::Env(_) | config::Source::EnvFile(..)) {
+            warn!("{} {} is also set in {}, which takes precedence", style::yellow("Note:"), var, current);
+        }
+        let config = session.llm.config_mut();
+        if let Some(key) = config.key_mut(setting) {
+            *key = Some(secret);
+        }
+        config.set_source(setting, source);
+    }
+}
+
+// Moves keys read from .env files into the keyring, then removes them from those files
+fn migrate_keys(session: &mut Session) {
+    let config = session.llm.config();
+    let mut found = Vec::new();
+    for provider in config::PROVIDERS {
+        for setting in Config::key_settings(provider) {
+            if let (config::Source::EnvFile(path, var), Some(key)) = (config.source(setting), config.key(setting)) {
+                found.push((*setting, path.clone(), var.clone(), key.to_string()));
+            }
+        }
+    }
+    if found.is_empty() {
+        println!("No keys come from .env files; nothing to migrate");
+        return;
+    }
+    for (_, path, var, _) in &found {
+        println!("  {} in {}", var, path.display());
+    }
+    if !confirm(&format!("Move {} key(s) into the OS keyring and remove them from the file?", found.len())) {
+        return;
+    }
+
+    let mut moved: Vec<(&'static str, PathBuf, String)> = Vec::new();
+    for (setting, path, var, key) in found {
+        if let Err(e) = keys::set(&var, &key) {
+            warn!("{} keyring unavailable ({}); keeping the keys in their .env files", style::yellow("Warning:"), e);
+            break;
+        }
+        // A platform without a usable backend accepts the write into memory only; never scrub on its word
+        if keys::get(&var).as_deref() != Some(key.as_str()) {
+            warn!("{} the keyring did not return {} after storing it; keeping the keys in their .env files", style::yellow("Warning:"), var);
+            break;
+        }
+        moved.push((setting, path, var));
+    }
+    let mut files: Vec<&PathBuf> = moved.iter().map(|(_, path, _)| path).collect();
+    files.dedup();
+    for path in files {
+        let vars: Vec<String> = moved.iter().filter(|(_, p, _)| p == path).map(|(_, _, var)| var.clone()).collect();
+        match keys::scrub_env(path, &vars) {
+            Ok(()) => println!("Moved {} from {} to the keyring", vars.join(", "), path.display()),
+            Err(e) => warn!("{} stored in the keyring but could not update {}: {}", style::red("Error:"), path.display(), e),
+        }
+    }
+    for (setting, _, var) in moved {
+        session.llm.config_mut().set_source(setting, config::Source::Keyring(var));
+    }
+}
+
+fn cmd_tools(session: &mut Session, _: &str) -> CommandResult {
+    let config = session.llm.config();
+    println!("\nTool policy (mode: {}):", config.mode.as_deref().unwrap_or("none"));
+    for (name, _) in tools::TOOLS {
+        let decision = policy::for_action(config, name);
+        let (status, from) = match decision {
+            Decision::Deny(_) => (style::red(&format!("{:<8}", "deny")), config.source("blocked_actions").to_string()),
+            Decision::Confirm => (style::yellow(&format!("{:<8}", "confirm")), config.source("confirm_actions").to_string()),
+            Decision::Allow => (style::green(&format!("{:<8}", "allow")), String::new()),
+        };
+        println!("  {:<18} {} {}", name, status, style::dim(&from));
+    }
+    if !config.protected_paths.is_empty() {
+        println!("\nDenied paths ({}):", config.source("protected_paths"));
+        for pattern in &config.protected_paths {
+            println!("  {}", pattern);
+        }
+    }
+    println!();
+    CommandResult::Handled
+}
+
+fn cmd_mode(session: &mut Session, args: &str) -> CommandResult {
+    let mode = args.trim();
+    if mode.is_empty() {
+        let config = session.llm.config();
+        println!("Mode: {} ({})", config.mode.as_deref().unwrap_or("none"), config.source("mode"));
+        return CommandResult::Handled;
+    }
+    let config = session.llm.config_mut();
+    match config.apply_mode(mode) {
+        Ok(changed) => {
+            for name in changed {
+                config.set_source(name, config::Source::Runtime("/mode"));
+            }
+            set_auto_approve(config.auto_approve);
+            println!("Mode set to {}", mode);
+        }
+        Err(e) => warn!("Error: {}", e),
+    }
+    CommandResult::Handled
+}
+
+fn cmd_transcribe<'a>(session: &'a mut Session, args: &'a str) -> BoxFuture<'a, CommandResult> {
+    Box::pin(async move {
+        if args.trim().is_empty() {
+            println!("Usage: /transcribe <audio_file>");
+            return CommandResult::Handled;
+        }
+        let path = Path::new(args.trim());
+        info!("Transcribing {}...", path.display());
+        match session.llm.transcribe_audio(path).await {
+            Ok(transcript) => {
+                info!("Transcript: {}", transcript);
+                return CommandResult::Prompt(transcript);
+            }
+            Err(e) => warn!("Error: {}", e),
+        }
+        CommandResult::Handled
+    })
+}
+
+fn cmd_set(session: &mut Session, args: &str) -> CommandResult {
+    let args: Vec<&str> = args.split_whitespace().collect();
+    match args.as_slice() {
+        ["limit"] => print_limits(session.llm.config()),
+        ["limit", name, value] => set_setting(session, name, value),
+        ["provider", provider] => match session.llm.set_provider(provider) {
+            Ok(model) => {
+                let source = config::Source::Runtime("/set");
+                session.llm.config_mut().set_source("provider", source.clone());
+                session.llm.config_mut().set_source("model", source);
+                println!("Switched to: {} ({})", model, provider);
+                remember_model(session);
+            }
+            Err(e) => warn!("{}", e),
+        },
+        ["model", model] => {
+            session.llm.set_model(model);
+            let source = config::Source::Runtime("/set");
+            session.llm.config_mut().set_source("model", source.clone());
+            session.llm.config_mut().set_source("provider", source);
+            println!("Switched to: {} ({})", model, session.llm.config().provider);
+            remember_model(session);
+        }
+        // The rest of the line is the value, so "/set confirm_actions delete, create_file" works
+        [name, value @ ..] if !value.is_empty() => set_setting(session, name, &value.join(" ")),
+        _ => {
+            println!("Usage: /set <setting> <value> | /set limit [<name> <n>]");
+            println!("Settings: provider, model, {}", config::SETTABLE.join(", "));
+        }
+    }
+    CommandResult::Handled
+}
+
+// Applied to a copy so session state derived from the config (quiet output, rate limiter) follows it
+fn set_setting(session: &mut Session, name: &str, value: &str) {
+    let mut config = session.llm.config().clone();
+    match config.set(name, value) {
+        Ok(name) => {
+            config.set_source(name, config::Source::Runtime("/set"));
+            let value = config.settings().into_iter().find(|(n, _)| *n == name).map(|(_, v)| v).unwrap_or_default();
+            println!("{} = {}", name, value);
+            replace_config(session, config);
+        }
+        Err(e) => warn!("{}", e),
+    }
+}
+
+fn print_limits(config: &Config) {
+    for spec in config::LIMITS {
+        let value = config.limits.get(spec.name).unwrap_or_default();
+        let range = format!("{}-{}", spec.min, spec.max);
+        println!("  {:<24} {:<10} {:<16} {}", spec.name, value, range, style::dim(&config.source(spec.name).to_string()));
+    }
+}
+
+fn cmd_get(session: &mut Session, args: &str) -> CommandResult {
+    let config = session.llm.config();
+    match args.trim() {
+        "" => println!("Usage: /get <setting> (/config lists every setting)"),
+        "limit" => print_limits(config),
+        name => match config.settings().into_iter().find(|(n, _)| *n == name) {
+            Some((name, value)) => println!("{} = {} {}", name, value, style::dim(&format!("(from {})", config.source(name)))),
+            None => println!("Unknown setting '{}' (/config lists every setting)", name),
+        },
+    }
+    CommandResult::Handled
+}
+
+fn cmd_open(session: &mut Session, args: &str) -> CommandResult {
+    let arg = args.trim();
+    if arg.is_empty() {
+        println!("Usage: /open <file>[:start-end]");
+        return CommandResult::Handled;
+    }
+    let (path, range) = parse_line_range(arg);
+    let expanded = match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().to_string(),
+        _ => path.to_string(),
+    };
+    let Some(full_path) = resolve_path(&session.cwd, &expanded) else {
+        warn!("Access denied: path outside current directory");
+        return CommandResult::Handled;
+    };
+    match tools::read_text(&full_path) {
+        Ok(content) => {
+            let lines: Vec<&str> = content.lines().collect();
+            let (start, end) = range.unwrap_or((1, lines.len()));
+            let start = start.max(1);
+            let end = end.min(lines.len());
+            if start > end {
+                println!("{} has {} lines", path, lines.len());
+                return CommandResult::Handled;
+            }
+            let width = end.to_string().len();
+            let numbered: Vec<String> = (start..=end)
+                .map(|n| format!("{} {}", style::dim(&format!("{:>width$} │", n)), lines[n - 1]))
+                .collect();
+            show_paged(&numbered.join("\n"));
+
+            session.recent_files.retain(|p| p != path);
+            session.recent_files.insert(0, path.to_string());
+            session.recent_files.truncate(20);
+        }
+        Err(e) => warn!("{}: {}", path, e),
+    }
+    CommandResult::Handled
+}
+
+fn cmd_grep(session: &mut Session, args: &str) -> CommandResult {
+    let mut opts = search::SearchOptions {
+        pattern: String::new(),
+        case_insensitive: false,
+        glob: None,
+        max_results: tools::SEARCH_MAX_RESULTS,
+    };
+    let mut attach = false;
+    let mut positional = Vec::new();
+    let mut args = split_args(args).into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-i" => opts.case_insensitive = true,
+            "--attach" => attach = true,
+            "--glob" => opts.glob = args.next(),
+            _ => positional.push(arg),
+        }
+    }
+    let mut positional = positional.into_iter();
+    let Some(pattern) = positional.next() else {
+        println!("Usage: /grep [-i] [--glob <glob>] [--attach] <pattern> [path]");
+        return CommandResult::Handled;
+    };
+    opts.pattern = pattern;
+    let path = positional.next().unwrap_or(".".into());
+    let Some(root) = resolve_path(&session.cwd, &path) else {
+        warn!("Access denied: path outside current directory");
+        return CommandResult::Handled;
+    };
+
+    match search::search(&root, &session.cwd, &opts) {
+        Ok(found) => {
+            for m in &found.matches {
+                let text = found
+                    .regex
+                    .replace_all(&m.text, |caps: &regex::Captures| style::bold(&style::red(&caps[0])));
+                println!("{}:{}: {}", style::cyan(&m.path), style::dim(&m.line.to_string()), text);
+            }
+            println!("{}", style::dim(&found.summary()));
+            if attach && !found.matches.is_empty() {
+                session.attachment = Some(format!("Search results for /{}/:\n{}", opts.pattern, found.to_text()));
+                println!("Results will be attached to your next prompt.");
+            }
+        }
+        Err(e) => warn!("{}", e),
+    }
+    CommandResult::Handled
+}
+
+fn cmd_quiet(session: &mut Session, args: &str) -> CommandResult {
+    let quiet = match args.trim() {
+        "on" => true,
+        "off" => false,
+        "" => !output::is_quiet(),
+        _ => {
+            println!("Usage: /quiet [on|off]");
+            return CommandResult::Handled;
+        }
+    };
+    output::set_quiet(quiet);
+    session.llm.config_mut().quiet = quiet;
+    session.llm.config_mut().set_source("quiet", config::Source::Runtime("/quiet"));
+    println!("Quiet mode {}", if quiet { "on" } else { "off" });
+    CommandResult::Handled
+}
+
+fn cmd_version(_: &mut Session, _: &str) -> CommandResult {
+    println!("clio-ai {}", version::LONG);
+    if let Some(latest) = version::cached_update() {
+        println!("{}", style::dim(&format!("clio-ai {} is available", latest)));
+    }
+    CommandResult::Handled
+}
+
+fn cmd_usage(session: &mut Session, _: &str) -> CommandResult {
+    let stats = &session.stats;
+    println!("\nSession usage:");
+    println!("  Prompts:     {}", stats.prompts);
+    println!("  Iterations:  {}", stats.iterations);
+    println!("  Tokens:      {} in / {} out", stats.usage.input_tokens, stats.usage.output_tokens);
+    println!("  Total time:  {:.1}s", stats.elapsed.as_secs_f64());
+    println!("    LLM:       {:.1}s", stats.llm_time.as_secs_f64());
+    println!("    Tools:     {:.1}s", stats.tool_time.as_secs_f64());
+    if let Some(cost) = stats.cost {
+        println!("  Est. cost:   ${:.4}", cost);
+    }
+    if !stats.actions.is_empty() {
+        println!("  Tool actions:");
+        for (action, (count, time)) in &stats.actions {
+            println!(
+                "    {:<16} {:>4} × {:>6}ms total, {}ms avg",
+                action,
+                count,
+                time.as_millis(),
+                time.as_millis() / u128::from((*count).max(1))
+            );
+        }
+    }
+    println!();
+    CommandResult::Handled
+}
+
+fn cmd_clear(session: &mut Session, _: &str) -> CommandResult {
+    session.transcript.clear();
+    session.last_response = None;
+    // Saved branches survive; the fresh conversation is not on any of them
+    session.branch = None;
+    session.rate_limiter.reset();
+    println!("Conversation cleared.");
+    CommandResult::Handled
+}
+
+fn cmd_branch(session: &mut Session, args: &str) -> CommandResult {
+    let name = args.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        println!("Usage: /branch <name>");
+        return CommandResult::Handled;
+    }
+    // Only the transcript is kept: every prompt goes to the model on its own, so there is no model-side
+    // history to fork, and a checkout changes what /export and /copy see
+    session.branches.insert(name.to_string(), session.transcript.clone());
+    session.branch = Some(name.to_string());
+    println!("Saved {} turn(s) of the transcript as branch '{}'", session.transcript.len(), name);
+    CommandResult::Handled
+}
+
+fn cmd_checkout(session: &mut Session, args: &str) -> CommandResult {
+    let name = args.trim();
+    if name.is_empty() {
+        println!("Usage: /checkout <name>");
+        return CommandResult::Handled;
+    }
+    let Some(turns) = session.branches.get(name).cloned() else {
+        println!("No branch '{}' (see /branches)", name);
+        return CommandResult::Handled;
+    };
+    // Keep what was added on the branch we are leaving; an unnamed conversation is dropped
+    match session.branch.take() {
+        Some(current) => {
+            session.branches.insert(current, session.transcript.clone());
+        }
+        None if !session.transcript.is_empty() => {
+            warn!("{}", style::yellow("Discarding the unsaved conversation (use /branch to keep it)."));
+        }
+        None => {}
+    }
+    session.last_response = turns.last().and_then(|t| t.response.clone());
+    session.transcript = turns;
+    session.branch = Some(name.to_string());
+    println!("Switched to branch '{}' ({} turn(s))", name, session.transcript.len());
+    CommandResult::Handled
+}
+
+fn cmd_branches(session: &mut Session, _: &str) -> CommandResult {
+    if session.branches.is_empty() {
+        println!("No branches yet. Use /branch <name> to save the transcript.");
+        return CommandResult::Handled;
+    }
+    let mut names: Vec<&String> = session.branches.keys().collect();
+    names.sort();
+    println!("\nBranches:");
+    for name in names {
+        let current = session.branch.as_ref() == Some(name);
+        let turns = if current { session.transcript.len() } else { session.branches[name].len() };
+        let line = format!("{} {:<20} {} turn(s)", if current { "*" } else { " " }, name, turns);
+        println!("  {}", if current { style::green(&line) } else { line });
+    }
+    println!();
+    CommandResult::Handled
+}
+
+fn cmd_undo(session: &mut Session, _: &str) -> CommandResult {
+    match session.undo.undo() {
+        Some(Ok(paths)) => println!("Undone: {}", paths.join(", ")),
+        Some(Err(e)) => warn!("Undo failed: {}", e),
+        None => println!("Nothing to undo."),
+    }
+    CommandResult::Handled
+}
+
+fn cmd_redo(session: &mut Session, _: &str) -> CommandResult {
+    match session.undo.redo() {
+        Some(Ok(paths)) => println!("Redone: {}", paths.join(", ")),
+        Some(Err(e)) => warn!("Redo failed: {}", e),
+        None => println!("Nothing to redo."),
+    }
+    CommandResult::Handled
+}
+
+fn cmd_copy(session: &mut Session, args: &str) -> CommandResult {
+    let Some(response) = session.last_response.as_deref() else {
+        println!("Nothing to copy yet.");
+        return CommandResult::Handled;
+    };
+    let args: Vec<&str> = args.split_whitespace().collect();
+    let text = match args.as_slice() {
+        [] => response.to_string(),
+        ["code"] | ["code", _] => {
+            let n = match args.get(1).map(|n| n.parse::<usize>()) {
+                None => 1,
+                Some(Ok(n)) if n > 0 => n,
+                Some(_) => {
+                    println!("Usage: /copy code [n]");
+                    return CommandResult::Handled;
+                }
+            };
+            let blocks = extract_code_blocks(response);
+            match blocks.into_iter().nth(n - 1) {
+                Some(block) => block,
+                None => {
+                    println!("No code block #{} in the last response.", n);
+                    return CommandResult::Handled;
+                }
+            }
+        }
+        _ => {
+            println!("Usage: /copy [code [n]]");
+            return CommandResult::Handled;
+        }
+    };
+    match copy_to_clipboard(session, &text) {
+        Ok(()) => println!("Copied {} bytes to clipboard.", text.len()),
+        Err(e) => {
+            match write_copy_fallback(&text) {
+                Ok(path) => println!("Clipboard unavailable ({}); wrote {}", e, path.display()),
+                Err(write_err) => warn!("Clipboard unavailable ({}) and fallback failed: {}", e, write_err),
+            }
+        }
+    }
+    CommandResult::Handled
+}
+
+fn cmd_export(session: &mut Session, args: &str) -> CommandResult {
+    let args: Vec<&str> = args.split_whitespace().collect();
+    let full = args.contains(&"--full");
+    let path = args
+        .iter()
+        .find(|a| !a.starts_with("--"))
+        .map(|p| p.to_string())
+        .unwrap_or_else(|| format!("clio-session-{}.md", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    if session.transcript.is_empty() {
+        println!("Nothing to export yet.");
+        return CommandResult::Handled;
+    }
+    let markdown = transcript::render_markdown(&session.transcript, &session.cwd.to_string_lossy(), full, &session.llm.config().models);
+    // Inside the project the export is written like the model's files: tool policy, protected paths and
+    // the rewrite question for an existing file
+    let target = match resolve_path(&session.cwd, &path) {
+        Some(target) => {
+            if let Some(reason) = check_command_write(session, &path, &markdown) {
+                warn!("{}", style::yellow(&reason));
+                return CommandResult::Handled;
+            }
+            target
+        }
+        None if Path::new(&path).is_absolute() => {
+            if !confirm(&format!("{} is outside the current directory. Write anyway?", path)) {
+                return CommandResult::Handled;
+            }
+            if Path::new(&path).exists() && !confirm(&format!("{} already exists. Overwrite?", path)) {
+                return CommandResult::Handled;
+            }
+            PathBuf::from(&path)
+        }
+        None => {
+            println!("Access denied: path outside current directory");
+            return CommandResult::Handled;
+        }
+    };
+    match std::fs::write(&target, markdown) {
+        Ok(()) => println!("Exported {} prompt(s) to {}", session.transcript.len(), path),
+        Err(e) => warn!("Export failed: {}", e),
+    }
+    CommandResult::Handled
+}
+
+fn cmd_gen_tests<'a>(session: &'a mut Session, args: &'a str) -> BoxFuture<'a, CommandResult> {
+    Box::pin(async move {
+        let file = args.trim();
+        if file.is_empty() {
+            println!("Usage: /gen-tests <file>");
+            return CommandResult::Handled;
+        }
+        let Some(source_path) = resolve_path(&session.cwd, file) else {
+            println!("Access denied: path outside current directory");
+            return CommandResult::Handled;
+        };
+        let source = match std::fs::read_to_string(&source_path) {
+            Ok(source) => source,
+            Err(e) => {
+                warn!("Cannot read {}: {}", file, e);
+                return CommandResult::Handled;
+            }
+        };
+        let plan = test_plan(file);
+        let prompt = format!(
+            "Generate comprehensive unit tests for the code in {file}. Create a test file alongside it at {test}. \
+             Language: {lang}. {hint}\n\n--- {file} ---\n{source}",
+            file = file,
+            test = plan.test_path,
+            lang = plan.language,
+            hint = plan.hint,
+            source = source
+        );
+        submit_prompt(session, &prompt).await;
+
+        let wrote_test_file = resolve_path(&session.cwd, &plan.test_path).is_some_and(|p| p.exists());
+        let added_inline_module = plan.language == "Rust"
+            && std::fs::read_to_string(&source_path).is_ok_and(|s| s.contains("#[cfg(test)]"));
+        if !wrote_test_file && !added_inline_module {
+            warn!("{}", style::yellow(&format!("No test file was written at {}.", plan.test_path)));
+        }
+        CommandResult::Handled
+    })
+}
+
+fn cmd_gen_readme<'a>(session: &'a mut Session, _: &'a str) -> BoxFuture<'a, CommandResult> {
+    Box::pin(async move {
+        if let Err(e) = gen_readme(session).await {
+            warn!("{} {}", style::red("Error:"), e);
+        }
+        CommandResult::Handled
+    })
+}
+
+fn cmd_gen_ci<'a>(session: &'a mut Session, args: &'a str) -> BoxFuture<'a, CommandResult> {
+    Box::pin(async move {
+        let Some((platform, path, shape)) = CI_PLATFORMS.iter().find(|(name, _, _)| *name == args.trim()) else {
+            println!("Usage: /gen-ci <github-actions|gitlab-ci|circleci|drone>");
+            return CommandResult::Handled;
+        };
+        if let Err(e) = gen_ci(session, platform, path, shape).await {
+            warn!("{} {}", style::red("Error:"), e);
+        }
+        CommandResult::Handled
+    })
+}
+
+fn cmd_quit(_: &mut Session, _: &str) -> CommandResult {
+    CommandResult::Quit
+}
+
+// "file.rs:10-20" or "file.rs:10" → (path, Some((start, end))); anything else is a plain path
+fn parse_line_range(arg: &str) -> (&str, Option<(usize, usize)>) {
+    let Some((path, range)) = arg.rsplit_once(':') else {
+        return (arg, None);
+    };
+    let parsed = match range.split_once('-') {
+        Some((a, b)) => a.parse().ok().zip(b.parse().ok()),
+        None => range.parse().ok().map(|n| (n, n)),
+    };
+    match parsed {
+        Some(range) if !path.is_empty() => (path, Some(range)),
+        _ => (arg, None),
+    }
+}
+
+// Opens the loaded (or first) config file in $VISUAL/$EDITOR, then reloads it into the live session
+fn edit_config(session: &mut Session) {
+    let loaded = session.llm.config().config_file.clone();
+    let Some(path) = loaded.or_else(|| config_file::paths().into_iter().next()) else {
+        warn!("Error: no home directory for the config file");
+        return;
+    };
+    if !path.exists() {
+        if let Err(e) = config_file::write_example(&path) {
+            warn!("Error: {}", e);
+            return;
+        }
+        println!("Created {}", path.display());
+    }
+
+    let default_editor = if cfg!(windows) { "notepad" } else { "vi" };
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or(default_editor.into());
+    let mut words = editor.split_whitespace();
+    match std::process::Command::new(words.next().unwrap_or(default_editor)).args(words).arg(&path).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            println!("{} exited with {}; configuration not reloaded", editor, status);
+            return;
+        }
+        Err(e) => {
+            warn!("Error: cannot run {}: {}", editor, e);
+            return;
+        }
+    }
+
+    if let Err(e) = config_file::check(&path) {
+        warn!("{} {}: {}", style::red("Error:"), path.display(), e);
+        println!("Keeping the previous configuration");
+        return;
+    }
+    reload(session, &path.display().to_string());
+}
+
+fn cmd_cd(session: &mut Session, args: &str) -> CommandResult {
+    let arg = args.trim();
+    if arg.is_empty() {
+        println!("{}", session.cwd.display());
+        return CommandResult::Handled;
+    }
+    let dir = match (arg.strip_prefix("~/").or((arg == "~").then_some("")), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => session.cwd.join(arg),
+    };
+    if !dir.is_dir() {
+        println!("Not a directory: {}", arg);
+        return CommandResult::Handled;
+    }
+    match change_dir(session, &dir) {
+        Ok(()) => {
+            println!("{}", session.cwd.display());
+            let config = session.llm.config();
+            readiness::print_problems(config, &config.validate());
+        }
+        Err(e) => warn!("{} {}", style::red("Error:"), e),
+    }
+    CommandResult::Handled
+}
+
+// Moves the session and the process to `dir`, then reloads the configuration so the .env and .clio.toml
+// that apply there take effect. Command-line and /set changes are kept
+pub fn change_dir(session: &mut Session, dir: &Path) -> Result<(), String> {
+    env::set_current_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    session.cwd = env::current_dir().unwrap_or(dir.to_path_buf());
+    session.git_root = git::toplevel(&session.cwd);
+    let current = session.llm.config();
+    let previous = current.project_file.clone();
+    let mut config = Config::load(Some(current.profile.as_deref().unwrap_or("")), session.interactive);
+    config.set_source("profile", current.source("profile").clone());
+    config.carry_overrides(current);
+    replace_config(session, config);
+    recall_dir_model(session);
+    let config = session.llm.config();
+    match (&previous, &config.project_file) {
+        (old, new) if old == new => {}
+        (Some(old), None) => info!("{} {}", style::dim("No longer using project settings from"), old.display()),
+        _ => print_project_notice(config),
+    }
+    Ok(())
+}
+
+// Names what a .clio.toml changed, since a cloned repo's file can also loosen confirmations
+pub fn print_project_notice(config: &Config) {
+    let Some(path) = &config.project_file else {
+        return;
+    };
+    let names: Vec<&str> = config
+        .sources
+        .iter()
+        .filter(|(_, source)| **source == config::Source::Project(path.clone()))
+        .map(|(name, _)| *name)
+        .collect();
+    info!("{} {} ({})", style::dim("Project settings:"), path.display(), names.join(", "));
+    if config.auto_approve {
+        warn!("{}", style::yellow("  mode = \"auto\": every confirmation is approved without asking"));
+    }
+}
+
+fn cmd_reload(session: &mut Session, _args: &str) -> CommandResult {
+    if let Some(path) = session.llm.config().config_file.clone() {
+        if let Err(e) = config_file::check(&path) {
+            warn!("{} {}: {}", style::red("Error:"), path.display(), e);
+            println!("Keeping the previous configuration");
+            return CommandResult::Handled;
+        }
+    }
+    reload(session, "configuration");
+    CommandResult::Handled
+}
+
+// Re-reads the .env and config files into the live session and reports what changed. Keys, limits and
+// UI settings apply at once; a different provider or model changes who answers, so it is asked about first
+fn reload(session: &mut Session, what: &str) {
+    let current = session.llm.config();
+    let before = current.settings();
+    let mut config = Config::load(Some(current.profile.as_deref().unwrap_or("")), session.interactive);
+    config.set_source("profile", current.source("profile").clone());
+    // --flags, /set and /mode outrank the files, as on /cd
+    config.carry_overrides(current);
+    if (&config.provider, &config.model) != (&current.provider, &current.model) {
+        let question = format!(
+            "Switch from {} ({}) to {} ({})?",
+            current.model, current.provider, config.model, config.provider
+        );
+        if !confirm(&question) {
+            config.provider.clone_from(&current.provider);
+            config.model.clone_from(&current.model);
+            config.set_source("provider", current.source("provider").clone());
+            config.set_source("model", current.source("model").clone());
+        }
+    }
+    replace_config(session, config);
+    let after = session.llm.config().settings();
+
+    let changed: Vec<_> = before.iter().zip(&after).filter(|(old, new)| old.1 != new.1).collect();
+    if changed.is_empty() {
+        println!("Reloaded {}; no settings changed", what);
+    } else {
+        println!("Reloaded {}:", what);
+        for ((name, old), (_, new)) in changed {
+            println!("  {:<24} {} -> {}", name, style::dim(old), new);
+        }
+    }
+    let config = session.llm.config();
+    readiness::print_problems(config, &config.validate());
+}
+
+// Pipe through $PAGER (default less) when the text is taller than the terminal
+fn show_paged(text: &str) {
+    let height = terminal_size::terminal_size().map(|(_, h)| h.0 as usize);
+    let fits = height.is_none_or(|h| text.lines().count() < h.saturating_sub(1));
+    if fits || !io::stdout().is_terminal() {
+        println!("{}", text);
+        return;
+    }
+
+    let default_pager = if cfg!(windows) { "more" } else { "less -R" };
+    let pager = env::var("PAGER").ok().filter(|p| !p.trim().is_empty()).unwrap_or(default_pager.into());
+    let mut words = pager.split_whitespace();
+    let spawned = std::process::Command::new(words.next().unwrap_or(default_pager))
+        .args(words)
+        .stdin(std::process::Stdio::piped())
+        .spawn();
+    match spawned {
+        Ok(mut child) => {
+            if let Some(mut stdin) = child.stdin.take() {
+                // The user quitting the pager early closes the pipe; that is not an error
+                let _ = writeln!(stdin, "{}", text);
+            }
+            let _ = child.wait();
+        }
+        Err(_) => println!("{}", text),
+    }
+}
+
+// Whitespace-separated arguments, with "double quotes" grouping words
+fn split_args(input: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        args.push(current);
+    }
+    args
+}
+
+const README_PROMPT: &str = "Based on this repository context, generate a professional README.md with: title, description, \
+installation, usage, and license sections. Do not call any tools: reply with {\"response\": \"<the complete README.md as markdown>\"}.";
+
+async fn gen_readme(session: &mut Session) -> Result<(), String> {
+    let context = gather_repo_context(&session.cwd, session.llm.config());
+    let cwd = session.cwd.to_string_lossy().to_string();
+    info!("{}", style::dim("Drafting README.md..."));
+
+    let started = Instant::now();
+    let (response, meta) = session.llm.chat(README_PROMPT, &cwd, None, Some(&context)).await?;
+    record_chat_stats(session, started, &meta);
+    let readme = generated_content(response, "README.md").ok_or("The model did not return README content")?;
+
+    if write_generated(session, "README.md", &readme)? {
+        println!("Wrote README.md ({} lines). Use /undo to revert.", readme.lines().count());
+        print_stats_line(session);
+    }
+    Ok(())
+}
+
+// Platform, the file its config lives in, and what that config should look like
+const CI_PLATFORMS: &[(&str, &str, &str)] = &[
+    (
+        "github-actions",
+        ".github/workflows/ci.yml",
+        "a GitHub Actions workflow that runs on push and pull_request; cache with actions/cache or the setup action's built-in cache",
+    ),
+    ("gitlab-ci", ".gitlab-ci.yml", "a GitLab CI pipeline; cache with the cache: keyword and a key: files: entry for the lockfile"),
+    ("circleci", ".circleci/config.yml", "a CircleCI 2.1 config with a workflow; cache with restore_cache/save_cache and a {{ checksum }} key on the lockfile"),
+    (
+        "drone",
+        ".drone.yml",
+        "a Drone docker pipeline (kind: pipeline, type: docker); Drone has no built-in cache, so use the drone-cache plugin or a host volume",
+    ),
+];
+
+async fn gen_ci(session: &mut Session, platform: &'static str, path: &str, shape: &str) -> Result<(), String> {
+    let project = project::detect_project_type(&session.cwd);
+    let build = match project {
+        Some(kind) => format!("This is a {} project. {}", kind.name(), kind.ci_hint()),
+        None => "Infer the build and test steps from the repository context, and cache the dependency downloads.".to_string(),
+    };
+    let prompt = format!(
+        "Based on this repository context, write the CI config for this project, to be saved as {}: {}. {} \
+         Do not call any tools: reply with {{\"response\": \"<the complete YAML file>\"}}.",
+        path, shape, build
+    );
+
+    let context = gather_repo_context(&session.cwd, session.llm.config());
+    let cwd = session.cwd.to_string_lossy().to_string();
+    info!(
+        "{}",
+        style::dim(&format!("Generating {} config for {} project...", platform, project.map_or("an unrecognized".into(), |k| format!("a {}", k.name()))))
+    );
+
+    let started = Instant::now();
+    let (response, meta) = session.llm.chat(&prompt, &cwd, None, Some(&context)).await?;
+    record_chat_stats(session, started, &meta);
+    let content = generated_content(response, path).ok_or("The model did not return a CI config")?;
+    let yaml = llm::strip_fences(&content);
+    serde_yaml::from_str::<serde_yaml::Value>(yaml)
+        .map_err(|e| format!("The model returned invalid YAML ({}); nothing was written", e))?;
+
+    if write_generated(session, path, yaml)? {
+        println!("Wrote {} ({} lines). Use /undo to revert.", path, yaml.lines().count());
+        print_stats_line(session);
+    }
+    Ok(())
+}
+
+// A one-call command's usage goes into /usage and the stats line like a prompt's
+fn record_chat_stats(session: &mut Session, started: Instant, meta: &ChatMeta) {
+    let stats = PromptStats {
+        elapsed: started.elapsed(),
+        iterations: 1,
+        usage: meta.usage,
+        llm_time: started.elapsed(),
+        cost: session.llm.config().models.get(&meta.model).and_then(|m| m.cost(meta.usage)),
+        answered_by: Some(format!("{}/{}", meta.provider, meta.model)),
+        ..Default::default()
+    };
+    session.stats.add(&stats);
+    session.last_stats = Some(stats);
+}
+
+// Accept a create_file for the target path too; models often answer that way despite the instructions
+fn generated_content(response: ToolResponse, path: &str) -> Option<String> {
+    response
+        .response
+        .or_else(|| {
+            response.tools.unwrap_or_default().into_iter().find_map(|t| {
+                (t.action == "create_file" && t.path.as_deref().is_some_and(|p| p.trim_start_matches("./").eq_ignore_ascii_case(path)))
+                    .then_some(t.content)
+                    .flatten()
+            })
+        })
+        .filter(|content| !content.trim().is_empty())
+}
+
+// Returns false when the tool policy or the user kept the file from being written
+fn write_generated(session: &mut Session, display: &str, content: &str) -> Result<bool, String> {
+    let path = resolve_path(&session.cwd, display).ok_or_else(|| format!("{}: path outside current directory", display))?;
+    let content = format!("{}\n", content.trim_end());
+    if let Some(reason) = check_command_write(session, display, &content) {
+        warn!("{}", style::yellow(&reason));
+        return Ok(false);
+    }
+    let before = Snapshot::capture(&path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+    }
+    std::fs::write(&path, content).map_err(|e| format!("{}: {}", display, e))?;
+    let after = Snapshot::capture(&path);
+    session.undo.record(vec![Change { path, display: display.into(), before, after }]);
+    Ok(true)
+}
+
+struct TestPlan {
+    language: &'static str,
+    test_path: String,
+    hint: &'static str,
+}
+
+fn test_plan(file: &str) -> TestPlan {
+    let path = Path::new(file);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = path.extension().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let sibling = |name: String| match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(parent) => parent.join(name).to_string_lossy().to_string(),
+        None => name,
+    };
+
+    match ext.as_str() {
+        "rs" => TestPlan {
+            language: "Rust",
+            test_path: sibling(format!("{}_test.rs", stem)),
+            hint: "Either create that file or add a #[cfg(test)] mod tests to the source file itself.",
+        },
+        "py" => TestPlan {
+            language: "Python",
+            test_path: sibling(format!("test_{}.py", stem)),
+            hint: "Use pytest.",
+        },
+        "js" | "jsx" | "ts" | "tsx" | "mjs" => TestPlan {
+            language: if ext.starts_with('t') { "TypeScript" } else { "JavaScript" },
+            test_path: sibling(format!("{}.test.{}", stem, ext)),
+            hint: "Use the test runner the project already uses (Jest or Vitest).",
+        },
+        "go" => TestPlan {
+            language: "Go",
+            test_path: sibling(format!("{}_test.go", stem)),
+            hint: "Use the standard testing package in the same package.",
+        },
+        _ => TestPlan {
+            language: "the file's language",
+            test_path: sibling(format!("{}_test.{}", stem, ext)),
+            hint: "Use the idiomatic test framework for the language.",
+        },
+    }
+}
+
+fn copy_to_clipboard(session: &mut Session, text: &str) -> Result<(), String> {
+    let clipboard = match &mut session.clipboard {
+        Some(clipboard) => clipboard,
+        None => session.clipboard.insert(arboard::Clipboard::new().map_err(|e| e.to_string())?),
+    };
+    clipboard.set_text(text.to_string()).map_err(|e| e.to_string())
+}
+
+// A new owner-only file with a unique name: the temp dir is shared, so a fixed name could be a planted
+// symlink, and the copied text is nobody else's business
+fn write_copy_fallback(text: &str) -> io::Result<PathBuf> {
+    let name = format!("clio-ai-copy-{}-{}.txt", std::process::id(), chrono::Local::now().format("%Y%m%d-%H%M%S%3f"));
+    let path = env::temp_dir().join(name);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(&path)?.write_all(text.as_bytes())?;
+    Ok(path)
+}
+
+fn extract_code_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<Vec<&str>> = None;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            match current.take() {
+                Some(lines) => blocks.push(lines.join("\n")),
+                None => current = Some(Vec::new()),
+            }
+        } else if let Some(lines) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_command_is_listed_in_help_and_every_help_line_dispatches() {
+        let lines = help_lines();
+        assert_eq!(lines.len(), COMMANDS.len());
+        for (command, line) in COMMANDS.iter().zip(&lines) {
+            assert!(!command.help.trim().is_empty(), "{} has no help text", command.name);
+            assert!(command.usage.starts_with(command.name), "{} usage is {:?}", command.name, command.usage);
+            assert!(line.contains(command.help));
+        }
+        for line in &lines {
+            let name = line.split_whitespace().next().unwrap();
+            assert!(find(name).is_some(), "/help lists {} but nothing handles it", name);
+        }
+        let mut names: Vec<&str> = COMMANDS.iter().map(|c| c.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), COMMANDS.len(), "a command is registered twice");
+        assert!(find("/exit").is_some());
+    }
+}
+
@@ -0,0 +1,79 @@
+use crate::commands::{self, ArgKind, COMMANDS};
+use crate::config;
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+
+// Completes slash-commands and their arguments as described by the command table
+#[derive(Default)]
+pub struct ClioHelper {
+    pub recent_files: Vec<String>,
+    pub branches: Vec<String>,
+    pub models: Vec<String>,
+    pub profiles: Vec<String>,
+    files: FilenameCompleter,
+}
+
+fn candidates<'a>(prefix: &str, options: impl Iterator<Item = &'a str>) -> Vec<Pair> {
+    options
+        .filter(|option| option.starts_with(prefix))
+        .map(|option| Pair { display: option.to_string(), replacement: option.to_string() })
+        .collect()
+}
+
+impl Completer for ClioHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let typed = &line[..pos];
+        if !typed.starts_with('/') {
+            return Ok((0, Vec::new()));
+        }
+
+        let Some((name, arg)) = typed.split_once(' ') else {
+            return Ok((0, candidates(typed, COMMANDS.iter().map(|c| c.name))));
+        };
+        let start = pos - arg.len();
+        match commands::find(name).map(|c| &c.arg) {
+            Some(ArgKind::Model) => Ok((start, candidates(arg, self.models.iter().map(String::as_str)))),
+            Some(ArgKind::Branch) => Ok((start, candidates(arg, self.branches.iter().map(String::as_str)))),
+            Some(ArgKind::Profile) => {
+                let profiles = self.profiles.iter().map(String::as_str).chain(["none"]);
+                Ok((start, candidates(arg, profiles)))
+            }
+            Some(ArgKind::Setting) if !arg.contains(' ') => {
+                let limits = config::LIMITS.iter().map(|spec| spec.name);
+                let names = ["limit", "provider", "model"].into_iter().chain(config::SETTABLE.iter().copied()).chain(limits);
+                Ok((start, candidates(arg, names)))
+            }
+            Some(ArgKind::Words(words)) if !arg.contains(' ') => Ok((start, candidates(arg, words.iter().copied()))),
+            Some(ArgKind::Path) => {
+                let (file_start, files) = self.files.complete(line, pos, ctx)?;
+                if file_start != start {
+                    return Ok((file_start, files));
+                }
+                // Recently opened files first, then whatever is on disk
+                let mut pairs = candidates(arg, self.recent_files.iter().map(String::as_str));
+                for file in files {
+                    if !pairs.iter().any(|p| p.replacement == file.replacement) {
+                        pairs.push(file);
+                    }
+                }
+                Ok((start, pairs))
+            }
+            _ => Ok((0, Vec::new())),
+        }
+    }
+}
+
+impl Hinter for ClioHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ClioHelper {}
+
+impl Validator for ClioHelper {}
+
+impl Helper for ClioHelper {}
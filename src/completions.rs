@@ -0,0 +1,16 @@
+use crate::config::MODELS;
+use crate::Cli;
+use clap::builder::PossibleValuesParser;
+use clap::CommandFactory;
+use clap_complete::{generate, Shell};
+use std::io;
+
+pub fn print(shell: Shell) {
+    let mut cmd = Cli::command();
+    // --model accepts any id at runtime; only the completion script offers the known ones
+    if matches!(shell, Shell::Zsh | Shell::Fish) {
+        let ids: Vec<&str> = MODELS.iter().map(|(id, _, _)| *id).collect();
+        cmd = cmd.mut_arg("model", |arg| arg.value_parser(PossibleValuesParser::new(ids)));
+    }
+    generate(shell, &mut cmd, "clio-ai", &mut io::stdout());
+}
@@ -1,5 +1,16 @@
+use crate::config_file::{self, ConfigFile, ProjectFile};
+use crate::keys;
+use crate::models::ModelRegistry;
+use crate::paths;
+use crate::style;
+use crate::trust;
+use ignore::gitignore::GitignoreBuilder;
+use ignore::Match;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
-use std::path::PathBuf;
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -7,41 +18,1358 @@ pub struct Config {
     pub model: String,
     pub gemini_api_key: Option<String>,
     pub groq_api_key: Option<String>,
+    pub deepseek_api_key: Option<String>,
+    pub hf_api_key: Option<String>,
     pub ollama_url: Option<String>,
+    // ask | always | never: pulling an Ollama model that a request reports missing
+    pub ollama_auto_pull: String,
+    pub aws_access_key_id: Option<String>,
+    pub aws_secret_access_key: Option<String>,
+    pub aws_session_token: Option<String>,
+    pub aws_region: String,
+    pub limits: Limits,
+    pub max_files: usize,
+    // --lite: no repo context or prompt examples, fewer iterations, short tool results
+    pub lite: bool,
+    pub watch_prompt: String,
+    // Submitted once when the REPL starts, as if typed at the first prompt
+    pub default_prompt: Option<String>,
+    // Remember the model chosen with /model or /set per project root and switch back to it there
+    pub persist_model_per_dir: bool,
+    pub tool_rate_limit: usize,
+    pub stats_line: bool,
+    pub quiet: bool,
+    pub nerd_fonts: bool,
+    pub allow_watch: bool,
+    pub allow_net_read: bool,
+    // Only JSON replies become tool calls; no YAML or **file.ext** + code block fallbacks
+    pub strict_json: bool,
+    // read_file notes CRLF endings, a BOM or a missing final newline ahead of the content
+    pub file_metadata: bool,
+    // A batch whose file-changing action fails is rolled back as a whole
+    pub atomic_batch: bool,
+    // Tool paths are relative to the git root found at startup (or /cd) instead of the working directory
+    pub paths_from_git_root: bool,
+    pub blocked_actions: Vec<String>,
+    // Actions that need an interactive y/N before they run
+    pub confirm_actions: Vec<String>,
+    // Gitignore-style patterns no action may change, relative to protected_root (else the working directory)
+    pub protected_paths: Vec<String>,
+    pub protected_root: Option<PathBuf>,
+    // From a project file or /mode: standard, careful, read-only or auto
+    pub mode: Option<String>,
+    // What read-only added to blocked_actions, removed again when the mode changes
+    pub mode_blocked: Vec<String>,
+    // mode = "auto": every confirmation is approved, as with --yes
+    pub auto_approve: bool,
+    pub update_check: bool,
+    pub stream: bool,
+    pub notify: bool,
+    pub notify_after_secs: u64,
+    // Write /model, /set and /quiet changes back to config.toml on exit
+    pub persist_settings: bool,
+    // Send a tiny prompt to the provider at startup (CLIO_TEST_CONNECTION)
+    pub test_connection: bool,
+    // REPL history file; None when disabled with "none"
+    pub history_file: Option<PathBuf>,
+    // Replaces the built-in system prompt when CLIO_SYSTEM_PROMPT_FILE is readable
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f32>,
+    // Cap on reply length; None leaves it to the provider
+    pub max_tokens: Option<u32>,
+    // Model picked when switching to a provider without naming one, keyed by provider
+    pub default_models: BTreeMap<String, String>,
+    // Built-in models plus [[models]] from the config file
+    pub models: ModelRegistry,
+    // The .env file that was loaded, if any (None means process environment only)
+    pub env_file: Option<PathBuf>,
+    pub config_file: Option<PathBuf>,
+    // The .clio.toml overlaid for the working directory
+    pub project_file: Option<PathBuf>,
+    // Active [profile.<name>] and every profile the config file defines
+    pub profile: Option<String>,
+    pub profiles: Vec<String>,
+    // Key settings read from a non-default env var (gemini_api_key_env etc.), by setting name
+    key_env: BTreeMap<&'static str, String>,
+    // Where each setting came from, keyed by setting name
+    pub sources: BTreeMap<&'static str, Source>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Default,
+    Env(String),
+    EnvFile(PathBuf, String),
+    File(PathBuf),
+    Cli(&'static str),
+    // Changed during the session, e.g. by /model or /set
+    Runtime(&'static str),
+    // OS keyring entry named after the env var
+    Keyring(String),
+    // A .clio.toml found from the working directory up to the git root
+    Project(PathBuf),
+    // PERSIST_MODEL_PER_DIR: the model last chosen in this project root
+    Remembered(PathBuf),
+}
+
+// Size and count limits; LIMITS has each one's env var, default and allowed range
+#[derive(Debug, Clone)]
+pub struct Limits {
+    pub max_iterations: usize,
+    pub max_read_bytes: usize,
+    pub max_result_bytes: usize,
+    // Lines of each key file (README.md, Cargo.toml, ...) put in the repo context, capped at max_context_chars
+    pub context_lines: usize,
+    pub max_context_chars: usize,
+    pub max_tools_per_iteration: usize,
+    // Seconds one LLM request may take, retries included
+    pub request_timeout: usize,
+}
+
+pub struct LimitSpec {
+    pub name: &'static str,
+    pub var: &'static str,
+    pub default: usize,
+    pub min: usize,
+    pub max: usize,
+}
+
+pub const LIMITS: &[LimitSpec] = &[
+    LimitSpec { name: "max_iterations", var: "CLIO_MAX_ITERATIONS", default: 10, min: 1, max: 1000 },
+    LimitSpec { name: "max_read_bytes", var: "CLIO_MAX_READ_BYTES", default: 1_048_576, min: 1024, max: 104_857_600 },
+    LimitSpec { name: "max_result_bytes", var: "CLIO_MAX_RESULT_BYTES", default: 65_536, min: 256, max: 10_485_760 },
+    LimitSpec { name: "context_lines", var: "CLIO_CONTEXT_LINES", default: 60, min: 0, max: 10_000 },
+    LimitSpec { name: "max_context_chars", var: "CLIO_MAX_CONTEXT_CHARS", default: 8000, min: 0, max: 100_000 },
+    LimitSpec { name: "max_tools_per_iteration", var: "CLIO_MAX_TOOLS_PER_ITERATION", default: 50, min: 1, max: 500 },
+    LimitSpec { name: "request_timeout", var: "CLIO_REQUEST_TIMEOUT", default: 300, min: 5, max: 3600 },
+];
+
+impl Limits {
+    fn field(&mut self, name: &str) -> Option<&mut usize> {
+        match name {
+            "max_iterations" => Some(&mut self.max_iterations),
+            "max_read_bytes" => Some(&mut self.max_read_bytes),
+            "max_result_bytes" => Some(&mut self.max_result_bytes),
+            "context_lines" => Some(&mut self.context_lines),
+            "max_context_chars" => Some(&mut self.max_context_chars),
+            "max_tools_per_iteration" => Some(&mut self.max_tools_per_iteration),
+            "request_timeout" => Some(&mut self.request_timeout),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        match name {
+            "max_iterations" => Some(self.max_iterations),
+            "max_read_bytes" => Some(self.max_read_bytes),
+            "max_result_bytes" => Some(self.max_result_bytes),
+            "context_lines" => Some(self.context_lines),
+            "max_context_chars" => Some(self.max_context_chars),
+            "max_tools_per_iteration" => Some(self.max_tools_per_iteration),
+            "request_timeout" => Some(self.request_timeout),
+            _ => None,
+        }
+    }
+
+    // Returns the limit's setting name, or why the value was refused
+    pub fn set(&mut self, name: &str, value: usize) -> Result<&'static str, String> {
+        let Some(spec) = LIMITS.iter().find(|spec| spec.name == name) else {
+            let names: Vec<&str> = LIMITS.iter().map(|spec| spec.name).collect();
+            return Err(format!("Unknown limit '{}' (one of {})", name, names.join(", ")));
+        };
+        if !(spec.min..=spec.max).contains(&value) {
+            return Err(format!("{} must be from {} to {}", spec.name, spec.min, spec.max));
+        }
+        if let Some(field) = self.field(spec.name) {
+            *field = value;
+        }
+        Ok(spec.name)
+    }
+}
+
+// A setting that is wrong (error) or suspicious (warning), with how to fix it
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub error: bool,
+    pub setting: &'static str,
+    pub message: String,
+    pub fix: String,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::Env(var) => write!(f, "env {}", var),
+            Source::EnvFile(path, var) => write!(f, "{} ({})", path.display(), var),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Cli(flag) => write!(f, "cli {}", flag),
+            Source::Runtime(command) => write!(f, "session {}", command),
+            Source::Keyring(var) => write!(f, "keyring {}", var),
+            Source::Project(path) => write!(f, "project {}", path.display()),
+            Source::Remembered(root) => write!(f, "last chosen in {}", root.display()),
+        }
+    }
+}
+
+// Resolves each setting as env var > config file > default, recording the winner
+struct Loader {
+    file: Option<PathBuf>,
+    env_file: Option<PathBuf>,
+    // What env_file sets; only read for variables the real environment lacks
+    env_file_vars: HashMap<String, String>,
+    // env_file was found from the working directory rather than in the user's config dirs, so it may have
+    // come with the repository and is not read for SAFETY_SETTINGS
+    env_file_local: bool,
+    // Config::defaults: neither the environment nor the keyring is read
+    isolated: bool,
+    sources: BTreeMap<&'static str, Source>,
+}
+
+// Settings a project .clio.toml may only loosen once trusted; a repository's .env cannot set them at all
+const SAFETY_SETTINGS: &[&str] = &[
+    "blocked_actions",
+    "confirm_actions",
+    "protected_paths",
+    "allow_net_read",
+    "allow_watch",
+    "paths_from_git_root",
+    "tool_rate_limit",
+    "max_files",
+    "max_iterations",
+    "max_read_bytes",
+];
+
+impl Loader {
+    fn get<T>(&mut self, name: &'static str, var: &str, file_value: Option<T>, parse: impl Fn(&str) -> Option<T>) -> Option<T> {
+        let use_env_file = !(self.env_file_local && SAFETY_SETTINGS.contains(&name));
+        if let Some((var, value, from_file)) = self.env_value(var, use_env_file).and_then(|(var, v, f)| parse(&v).map(|value| (var, value, f))) {
+            let source = match &self.env_file {
+                Some(path) if from_file => Source::EnvFile(path.clone(), var),
+                _ => Source::Env(var),
+            };
+            self.sources.insert(name, source);
+            return Some(value);
+        }
+        if let (Some(value), Some(path)) = (file_value, &self.file) {
+            self.sources.insert(name, Source::File(path.clone()));
+            return Some(value);
+        }
+        self.sources.insert(name, Source::Default);
+        None
+    }
+
+    // Every variable is also read as CLIO_<NAME>, which wins over the bare name other tools may set too.
+    // The real environment wins over the .env for the same name; the flag says the value came from the file
+    fn env_value(&self, var: &str, use_env_file: bool) -> Option<(String, String, bool)> {
+        if self.isolated {
+            return None;
+        }
+        let prefixed = (!var.starts_with("CLIO_")).then(|| format!("CLIO_{}", var));
+        prefixed.into_iter().chain(Some(var.to_string())).find_map(|name| match env::var(&name) {
+            Ok(value) => Some((name, value, false)),
+            Err(_) if use_env_file => self.env_file_vars.get(&name).map(|value| (name.clone(), value.clone(), true)),
+            Err(_) => {
+                if let (Some(path), true) = (&self.env_file, self.env_file_vars.contains_key(&name)) {
+                    warn!(
+                        "Warning: ignoring {} in {}; safety settings are read from the environment or {}",
+                        name,
+                        path.display(),
+                        Config::env_paths().first().map(|p| p.display().to_string()).unwrap_or("the user .env".into())
+                    );
+                }
+                None
+            }
+        })
+    }
+
+    // Out-of-range values are clamped so the session still works, with a warning
+    fn limit(&mut self, name: &'static str, file_value: Option<usize>) -> usize {
+        let Some(spec) = LIMITS.iter().find(|spec| spec.name == name) else {
+            return file_value.unwrap_or_default();
+        };
+        let value = self.get(name, spec.var, file_value, |v| v.trim().parse().ok()).unwrap_or(spec.default);
+        let clamped = value.clamp(spec.min, spec.max);
+        if clamped != value {
+            warn!("Warning: {} {} is outside {}-{}; using {}", name, value, spec.min, spec.max, clamped);
+        }
+        clamped
+    }
+
+    // Keys: env var (or .env) > OS keyring > config file
+    fn get_key(&mut self, name: &'static str, var: &str, file_value: Option<String>) -> Option<String> {
+        if let Some(value) = self.get(name, var, None, parse_string) {
+            return Some(value);
+        }
+        if let Some(value) = (!self.isolated).then(|| keys::get(var)).flatten() {
+            self.sources.insert(name, Source::Keyring(var.to_string()));
+            return Some(value);
+        }
+        self.get(name, var, file_value, parse_string)
+    }
+}
+
+// Reads the first readable .env into a map rather than the process environment, so a reload sees edited and
+// removed lines without touching variables other threads may be reading. The flag is set for a .env found
+// from the working directory rather than in the user's config dirs
+fn load_env_file() -> Option<(PathBuf, HashMap<String, String>, bool)> {
+    // Try the current dir and its parents first, then the config dir, ~/.clio-ai/.env and ~/.ai-cli/.env
+    let cwd = env::current_dir().ok();
+    let user = Config::env_paths();
+    let local = cwd.iter().flat_map(|dir| dir.ancestors()).map(|dir| dir.join(".env")).find(|path| path.is_file());
+    local.into_iter().chain(user.clone()).find_map(|path| {
+        let vars = dotenvy::from_path_iter(&path).ok()?.collect::<Result<HashMap<_, _>, _>>().ok()?;
+        let local = !user.contains(&path);
+        Some((path, vars, local))
+    })
+}
+
+// Probes the directory (or the nearest existing parent it would be created in) with a temp file
+fn check_writable(dir: &Path) -> Result<(), String> {
+    let existing = dir.ancestors().find(|d| d.is_dir()).ok_or("no existing parent directory")?;
+    let probe = existing.join(format!(".clio-write-test-{}", std::process::id()));
+    std::fs::File::create(&probe).map_err(|e| e.to_string())?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn mask(secret: Option<&str>) -> String {
+    match secret.map(str::trim).filter(|s| !s.is_empty()) {
+        None => "(not set)".into(),
+        Some(s) if s.chars().count() > 8 => format!("****{}", s.chars().skip(s.chars().count() - 4).collect::<String>()),
+        Some(_) => "****".into(),
+    }
+}
+
+fn parse_string(v: &str) -> Option<String> {
+    Some(v.to_string())
+}
+
+fn parse_auto_pull(v: &str) -> Option<String> {
+    let v = v.trim().to_lowercase();
+    ["ask", "always", "never"].contains(&v.as_str()).then_some(v)
+}
+
+fn parse_flag(v: &str) -> Option<bool> {
+    Some(v == "1" || v == "true")
+}
+
+fn parse_list(v: &str) -> Option<Vec<String>> {
+    if v.trim() == "none" {
+        return Some(Vec::new());
+    }
+    Some(v.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect())
+}
+
+// "groq=llama-3.3-70b-versatile,ollama=qwen2.5-coder"
+fn parse_model_map(v: &str) -> Option<BTreeMap<String, String>> {
+    v.split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| pair.split_once('=').map(|(p, m)| (p.trim().to_string(), m.trim().to_string())))
+        .collect()
 }
 
 impl Config {
-    pub fn load() -> Self {
-        // Try current dir first, then ~/.clio-ai/.env and ~/.ai-cli/.env
-        if !dotenvy::dotenv().is_ok() {
-            for path in Self::env_paths() {
-                if dotenvy::from_path(&path).is_ok() {
-                    break;
+    // `profile` (from --profile or /profile) wins over CLIO_PROFILE; Some("") selects no profile.
+    // Without `interactive` (server mode) a project file's trust question is never asked
+    pub fn load(profile: Option<&str>, interactive: bool) -> Self {
+        let (env_file, env_file_vars, env_file_local) = match load_env_file() {
+            Some((path, vars, local)) => (Some(path), vars, local),
+            None => (None, HashMap::new(), false),
+        };
+        let mut l = Loader { file: None, env_file, env_file_vars, env_file_local, isolated: false, sources: BTreeMap::new() };
+        let profile = match profile {
+            Some(name) => Some(name.to_string()).filter(|p| !p.is_empty()),
+            None => l.get("profile", "CLIO_PROFILE", None, parse_string).filter(|p| !p.trim().is_empty()),
+        };
+        let (config_file, file) = match config_file::load(profile.as_deref()) {
+            Some((path, file)) => (Some(path), file),
+            None => {
+                if let Some(name) = &profile {
+                    warn!("Warning: profile '{}' selected but no config file was found", name);
+                }
+                (None, ConfigFile::default())
+            }
+        };
+        let mut config = Self::build(l, profile, config_file, file);
+        if let Some((path, project, hash)) = env::current_dir().ok().and_then(|cwd| config_file::load_project(&cwd)) {
+            config.apply_project(path, project, &hash, interactive);
+        }
+        config
+    }
+
+    // Built-in defaults only: no environment, .env, config file, keyring or project file. Tests start here
+    // so the developer's own settings cannot change their outcome
+    #[cfg(test)]
+    pub fn defaults() -> Self {
+        let l = Loader {
+            file: None,
+            env_file: None,
+            env_file_vars: HashMap::new(),
+            env_file_local: false,
+            isolated: true,
+            sources: BTreeMap::new(),
+        };
+        Self::build(l, None, None, ConfigFile::default())
+    }
+
+    fn build(mut l: Loader, profile: Option<String>, config_file: Option<PathBuf>, file: ConfigFile) -> Self {
+        l.file.clone_from(&config_file);
+        let env_file = l.env_file.clone();
+
+        let providers = file.providers;
+        let mut key_env = BTreeMap::new();
+        let mut key_var = |name: &'static str, custom: Option<String>, default: &str| match custom {
+            Some(var) => {
+                key_env.insert(name, var.clone());
+                var
+            }
+            None => default.to_string(),
+        };
+        let gemini_var = key_var("gemini_api_key", providers.gemini_api_key_env, "GEMINI_API_KEY");
+        let groq_var = key_var("groq_api_key", providers.groq_api_key_env, "GROQ_API_KEY");
+        let deepseek_var = key_var("deepseek_api_key", providers.deepseek_api_key_env, "DEEPSEEK_API_KEY");
+        let hf_var = key_var("hf_api_key", providers.hf_api_key_env, "HF_API_KEY");
+        let aws_id_var = key_var("aws_access_key_id", providers.aws_access_key_id_env, "AWS_ACCESS_KEY_ID");
+        let aws_secret_var = key_var("aws_secret_access_key", providers.aws_secret_access_key_env, "AWS_SECRET_ACCESS_KEY");
+        let tools = file.tools;
+        let limits = file.limits;
+        // Per-action rules in [tools] edit the file's lists (or the defaults); CONFIRM and friends still win
+        let (file_blocked, file_confirm) = match &config_file {
+            Some(path) if !tools.actions.is_empty() => {
+                let mut blocked = tools.blocked.clone().unwrap_or_default();
+                let mut confirm = tools.confirm.clone().unwrap_or(vec!["delete".into()]);
+                crate::policy::apply_rules(path, &tools.actions, &mut blocked, &mut confirm);
+                (Some(blocked), Some(confirm))
+            }
+            _ => (tools.blocked.clone(), tools.confirm.clone()),
+        };
+        let ui = file.ui;
+
+        if !file.models.is_empty() {
+            if let Some(path) = &config_file {
+                l.sources.insert("models", Source::File(path.clone()));
+            }
+        }
+        let lite = l.get("lite", "CLIO_LITE", limits.lite, parse_flag).unwrap_or(false);
+        let mut config = Self {
+            provider: l.get("provider", "PROVIDER", file.provider, parse_string).unwrap_or("gemini".into()),
+            model: l.get("model", "MODEL", file.model, parse_string).unwrap_or("gemini-3-flash-preview".into()),
+            gemini_api_key: l.get_key("gemini_api_key", &gemini_var, providers.gemini_api_key),
+            groq_api_key: l.get_key("groq_api_key", &groq_var, providers.groq_api_key),
+            deepseek_api_key: l.get_key("deepseek_api_key", &deepseek_var, providers.deepseek_api_key),
+            hf_api_key: l.get_key("hf_api_key", &hf_var, providers.hf_api_key),
+            ollama_url: l
+                .get("ollama_url", "OLLAMA_URL", providers.ollama_url, parse_string)
+                .or(Some("http://localhost:11434".into())),
+            ollama_auto_pull: l
+                .get("ollama_auto_pull", "OLLAMA_AUTO_PULL", providers.ollama_auto_pull.as_deref().and_then(parse_auto_pull), parse_auto_pull)
+                .unwrap_or("ask".into()),
+            aws_access_key_id: l.get_key("aws_access_key_id", &aws_id_var, providers.aws_access_key_id),
+            aws_secret_access_key: l.get_key("aws_secret_access_key", &aws_secret_var, providers.aws_secret_access_key),
+            aws_session_token: l.get("aws_session_token", "AWS_SESSION_TOKEN", None, parse_string),
+            aws_region: l.get("aws_region", "AWS_REGION", providers.aws_region, parse_string).unwrap_or("us-east-1".into()),
+            limits: Limits {
+                max_iterations: l.limit("max_iterations", limits.max_iterations),
+                max_read_bytes: l.limit("max_read_bytes", limits.max_read_bytes),
+                max_result_bytes: l.limit("max_result_bytes", limits.max_result_bytes),
+                context_lines: l.limit("context_lines", limits.context_lines),
+                max_context_chars: l.limit("max_context_chars", limits.max_context_chars),
+                max_tools_per_iteration: l.limit("max_tools_per_iteration", limits.max_tools_per_iteration),
+                request_timeout: l.limit("request_timeout", limits.request_timeout),
+            },
+            max_files: l.get("max_files", "CLIO_MAX_FILES", limits.max_files, |v| v.parse().ok()).unwrap_or(50),
+            lite: false,
+            watch_prompt: l
+                .get("watch_prompt", "CLIO_WATCH_PROMPT", file.watch_prompt, parse_string)
+                .unwrap_or("These files changed: {files}. Review them and fix any problems you find.".into()),
+            default_prompt: l
+                .get("default_prompt", "CLIO_DEFAULT_PROMPT", file.default_prompt, parse_string)
+                .filter(|p| !p.trim().is_empty()),
+            persist_model_per_dir: l
+                .get("persist_model_per_dir", "PERSIST_MODEL_PER_DIR", file.persist_model_per_dir, parse_flag)
+                .unwrap_or(false),
+            tool_rate_limit: l
+                .get("tool_rate_limit", "CLIO_TOOL_RATE_LIMIT", tools.rate_limit, |v| v.parse().ok())
+                .unwrap_or(50),
+            stats_line: l
+                .get("stats_line", "STATS_LINE", ui.stats_line, |v| Some(v != "false" && v != "0"))
+                .unwrap_or(true),
+            quiet: l.get("quiet", "QUIET", ui.quiet, parse_flag).unwrap_or(false),
+            nerd_fonts: l.get("nerd_fonts", "CLIO_NERD_FONTS", ui.nerd_fonts, parse_flag).unwrap_or(false),
+            allow_watch: l.get("allow_watch", "CLIO_ALLOW_WATCH", tools.allow_watch, parse_flag).unwrap_or(false),
+            allow_net_read: l.get("allow_net_read", "ALLOW_NET_READ", tools.allow_net_read, parse_flag).unwrap_or(false),
+            strict_json: l.get("strict_json", "STRICT_JSON", tools.strict_json, parse_flag).unwrap_or(false),
+            file_metadata: l.get("file_metadata", "CLIO_FILE_METADATA", tools.file_metadata, parse_flag).unwrap_or(false),
+            atomic_batch: l.get("atomic_batch", "ATOMIC_BATCH", tools.atomic_batch, parse_flag).unwrap_or(false),
+            paths_from_git_root: l.get("paths_from_git_root", "PATHS_FROM_GIT_ROOT", tools.paths_from_git_root, parse_flag).unwrap_or(false),
+            blocked_actions: l.get("blocked_actions", "CLIO_BLOCKED_ACTIONS", file_blocked, parse_list).unwrap_or_default(),
+            confirm_actions: l
+                .get("confirm_actions", "CONFIRM", file_confirm, parse_list)
+                .unwrap_or(vec!["delete".into()]),
+            mode_blocked: Vec::new(),
+            protected_paths: l.get("protected_paths", "CLIO_PROTECTED_PATHS", tools.protected, parse_list).unwrap_or_default(),
+            protected_root: None,
+            mode: None,
+            auto_approve: false,
+            update_check: l.get("update_check", "UPDATE_CHECK", ui.update_check, parse_flag).unwrap_or(false),
+            stream: l.get("stream", "STREAM", ui.stream, parse_flag).unwrap_or(false),
+            notify: l.get("notify", "NOTIFY", ui.notify, parse_flag).unwrap_or(false),
+            notify_after_secs: l
+                .get("notify_after_secs", "NOTIFY_AFTER_SECS", ui.notify_after_secs, |v| v.parse().ok())
+                .unwrap_or(30),
+            persist_settings: l
+                .get("persist_settings", "PERSIST_SETTINGS", ui.persist_settings, parse_flag)
+                .unwrap_or(false),
+            test_connection: l
+                .get("test_connection", "CLIO_TEST_CONNECTION", ui.test_connection, parse_flag)
+                .unwrap_or(false),
+            history_file: match l.get("history_file", "CLIO_HISTORY_FILE", ui.history_file, parse_string) {
+                Some(path) if path.eq_ignore_ascii_case("none") => None,
+                Some(path) => Some(expand_home(&path)),
+                None => paths::state_file("history.txt"),
+            },
+            system_prompt: l
+                .get("system_prompt_file", "CLIO_SYSTEM_PROMPT_FILE", file.system_prompt_file, parse_string)
+                .and_then(|path| Self::load_system_prompt(&path)),
+            temperature: l.get("temperature", "CLIO_TEMPERATURE", file.temperature, |v| v.parse().ok()),
+            max_tokens: l.get("max_tokens", "CLIO_MAX_TOKENS", file.max_tokens, |v| v.parse().ok()),
+            default_models: l
+                .get("default_models", "CLIO_DEFAULT_MODELS", Some(file.default_model).filter(|m| !m.is_empty()), parse_model_map)
+                .unwrap_or_default(),
+            models: ModelRegistry::with_custom(file.models),
+            env_file,
+            config_file,
+            project_file: None,
+            profile,
+            profiles: file.profile.into_keys().collect(),
+            key_env,
+            sources: l.sources,
+        };
+
+        if lite {
+            let source = config.source("lite").clone();
+            config.set_lite(source);
+        }
+
+        // A provider chosen without a model starts on that provider's default model,
+        // and a model chosen without a provider implies its provider
+        match (*config.source("model") == Source::Default, *config.source("provider") == Source::Default) {
+            (true, false) => {
+                if let Some(model) = config.default_model(&config.provider) {
+                    config.model = model;
+                    config.set_source("model", config.source("provider").clone());
                 }
             }
+            (false, true) => {
+                config.provider = config.provider_for(&config.model);
+                config.set_source("provider", config.source("model").clone());
+            }
+            _ => {}
+        }
+        config
+    }
+
+    // A preset for blocked_actions and confirm_actions, from a project's `mode` or /mode; returns the
+    // settings it changed. Actions that read-only blocked are given back when another mode replaces it
+    pub fn apply_mode(&mut self, mode: &str) -> Result<Vec<&'static str>, String> {
+        if !MODES.contains(&mode) {
+            return Err(format!("unknown mode '{}' (standard, careful, read-only or auto)", mode));
+        }
+        let changing: Vec<String> = crate::tools::TOOLS
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .filter(|name| crate::tools::is_mutating_action(name))
+            .collect();
+        let mut set = vec!["mode"];
+        if !self.mode_blocked.is_empty() {
+            let given_back = std::mem::take(&mut self.mode_blocked);
+            self.blocked_actions.retain(|action| !given_back.contains(action));
+            set.push("blocked_actions");
+        }
+        match mode {
+            "standard" => self.confirm_actions = vec!["delete".into()],
+            "careful" => self.confirm_actions = changing,
+            "read-only" => {
+                self.mode_blocked = changing.into_iter().filter(|action| !self.blocked_actions.contains(action)).collect();
+                self.blocked_actions.extend(self.mode_blocked.clone());
+                set.push("blocked_actions");
+            }
+            _ => self.confirm_actions.clear(),
         }
-        
-        Self {
-            provider: env::var("PROVIDER").unwrap_or("gemini".into()),
-            model: env::var("MODEL").unwrap_or("gemini-3-flash-preview".into()),
-            gemini_api_key: env::var("GEMINI_API_KEY").ok(),
-            groq_api_key: env::var("GROQ_API_KEY").ok(),
-            ollama_url: env::var("OLLAMA_URL").ok().or(Some("http://localhost:11434".into())),
+        if mode != "read-only" {
+            set.push("confirm_actions");
         }
+        self.auto_approve = mode == "auto";
+        self.mode = Some(mode.to_string());
+        Ok(set)
     }
-    
-    pub fn env_paths() -> Vec<PathBuf> {
-        if let Some(home) = dirs::home_dir() {
-            vec![
-                home.join(".clio-ai").join(".env"),
-                home.join(".ai-cli").join(".env"),
-            ]
+
+    // A project file comes with the repository, so settings that loosen safety need a one-time yes for
+    // this exact content; the ones that only tighten apply either way
+    fn apply_project(&mut self, path: PathBuf, project: ProjectFile, hash: &str, interactive: bool) {
+        let before = self.clone();
+        self.overlay_project(path.clone(), project);
+        let mut safe = self.clone();
+        let loosened = safe.keep_safety_of(&before);
+        if loosened.is_empty() || trust::is_trusted(&path, hash) {
+            return;
+        }
+        warn!("{} {} loosens safety settings:", style::yellow("Project file"), path.display());
+        for change in &loosened {
+            warn!("  - {}", change);
+        }
+        if interactive && io::stdin().is_terminal() && matches!(crate::ask("  Trust this file and apply them? [y/N]").as_str(), "y" | "yes") {
+            if let Err(e) = trust::trust(&path, hash) {
+                warn!("Warning: could not remember trusting {}: {}", path.display(), e);
+            }
         } else {
-            Vec::new()
+            warn!("{}", style::dim("  Not applied; the file's other settings are. It asks again until trusted, or after it changes"));
+            *self = safe;
+        }
+    }
+
+    // Undoes what a project file loosened compared to `before`, returning a line for each
+    fn keep_safety_of(&mut self, before: &Config) -> Vec<String> {
+        let mut loosened = Vec::new();
+        if self.auto_approve && !before.auto_approve {
+            loosened.push("mode = \"auto\": every confirmation is approved without asking".to_string());
+            self.auto_approve = false;
+            self.mode.clone_from(&before.mode);
+            self.sources.insert("mode", before.source("mode").clone());
+        }
+        let unblocked: Vec<String> =
+            before.blocked_actions.iter().filter(|a| !self.blocked_actions.contains(a)).cloned().collect();
+        if !unblocked.is_empty() {
+            loosened.push(format!("allows blocked actions: {}", unblocked.join(", ")));
+            self.blocked_actions.extend(unblocked);
+        }
+        let unconfirmed: Vec<String> = before
+            .confirm_actions
+            .iter()
+            .filter(|a| !self.confirm_actions.contains(a) && !self.blocked_actions.contains(a))
+            .cloned()
+            .collect();
+        if !unconfirmed.is_empty() {
+            loosened.push(format!("stops asking before: {}", unconfirmed.join(", ")));
+            self.confirm_actions.extend(unconfirmed);
+        }
+        for (name, now, was) in [
+            ("blocked_actions", &self.blocked_actions, &before.blocked_actions),
+            ("confirm_actions", &self.confirm_actions, &before.confirm_actions),
+        ] {
+            if now.len() == was.len() && was.iter().all(|a| now.contains(a)) {
+                self.sources.insert(name, before.source(name).clone());
+            }
+        }
+        for (name, now, was) in [
+            ("allow_net_read", &mut self.allow_net_read, before.allow_net_read),
+            ("allow_watch", &mut self.allow_watch, before.allow_watch),
+            ("paths_from_git_root", &mut self.paths_from_git_root, before.paths_from_git_root),
+        ] {
+            if *now && !was {
+                loosened.push(format!("{} = true", name));
+                *now = false;
+                self.sources.insert(name, before.source(name).clone());
+            }
+        }
+        if self.max_files > before.max_files {
+            loosened.push(format!("max_files = {} (was {})", self.max_files, before.max_files));
+            self.max_files = before.max_files;
+            self.sources.insert("max_files", before.source("max_files").clone());
+        }
+        for name in ["max_iterations", "max_read_bytes"] {
+            let (now, was) = (self.limits.get(name).unwrap_or_default(), before.limits.get(name).unwrap_or_default());
+            if now > was {
+                loosened.push(format!("{} = {} (was {})", name, now, was));
+                let _ = self.limits.set(name, was);
+                self.sources.insert(name, before.source(name).clone());
+            }
+        }
+        let (rate, was) = (self.tool_rate_limit, before.tool_rate_limit);
+        if was != 0 && (rate == 0 || rate > was) {
+            loosened.push(format!("rate_limit = {} (was {})", rate, was));
+            self.tool_rate_limit = was;
+            self.sources.insert("tool_rate_limit", before.source("tool_rate_limit").clone());
+        }
+        let kept = self.protected_root == before.protected_root
+            && before.protected_paths.iter().all(|p| self.protected_paths.contains(p));
+        if !before.protected_paths.is_empty() && !kept {
+            loosened.push(format!("replaces protected paths {}", before.protected_paths.join(", ")));
+            self.protected_paths.clone_from(&before.protected_paths);
+            self.protected_root.clone_from(&before.protected_root);
+            self.sources.insert("protected_paths", before.source("protected_paths").clone());
         }
+        loosened
+    }
+
+    // A project file outranks the environment and the user's config file; --flags and /set still win
+    // (see carry_overrides). Explicit [tools] lists override what `mode` sets
+    fn overlay_project(&mut self, path: PathBuf, project: ProjectFile) {
+        let mut set: Vec<&'static str> = Vec::new();
+        if let Some(mode) = project.mode {
+            match self.apply_mode(&mode) {
+                Ok(changed) => set.extend(changed),
+                Err(e) => warn!("Warning: {} in {}", e, path.display()),
+            }
+        }
+
+        match (project.provider, project.model) {
+            (Some(provider), model) => {
+                self.model = model.or_else(|| self.default_model(&provider)).unwrap_or(self.model.clone());
+                self.provider = provider;
+                set.extend(["provider", "model"]);
+            }
+            (None, Some(model)) => {
+                self.provider = self.provider_for(&model);
+                self.model = model;
+                set.extend(["provider", "model"]);
+            }
+            (None, None) => {}
+        }
+        if let Some(temperature) = project.temperature {
+            self.temperature = Some(temperature);
+            set.push("temperature");
+        }
+        if let Some(max_tokens) = project.max_tokens {
+            self.max_tokens = Some(max_tokens);
+            set.push("max_tokens");
+        }
+
+        let tools = project.tools;
+        if let Some(blocked) = tools.blocked {
+            self.blocked_actions = blocked;
+            set.push("blocked_actions");
+        }
+        if let Some(confirm) = tools.confirm {
+            self.confirm_actions = confirm;
+            set.push("confirm_actions");
+        }
+        if !tools.actions.is_empty() {
+            crate::policy::apply_rules(&path, &tools.actions, &mut self.blocked_actions, &mut self.confirm_actions);
+            set.extend(["blocked_actions", "confirm_actions"]);
+        }
+        for (name, value, field) in [
+            ("allow_watch", tools.allow_watch, &mut self.allow_watch),
+            ("allow_net_read", tools.allow_net_read, &mut self.allow_net_read),
+            ("strict_json", tools.strict_json, &mut self.strict_json),
+            ("file_metadata", tools.file_metadata, &mut self.file_metadata),
+            ("atomic_batch", tools.atomic_batch, &mut self.atomic_batch),
+            ("paths_from_git_root", tools.paths_from_git_root, &mut self.paths_from_git_root),
+        ] {
+            if let Some(value) = value {
+                *field = value;
+                set.push(name);
+            }
+        }
+        if let Some(rate_limit) = tools.rate_limit {
+            self.tool_rate_limit = rate_limit;
+            set.push("tool_rate_limit");
+        }
+        if let Some(protected) = tools.protected {
+            // Patterns are relative to the project, which may be above the working directory
+            let dir = path.parent().unwrap_or(&path);
+            let root = if dir.ends_with(".clio") { dir.parent().unwrap_or(dir) } else { dir };
+            self.protected_root = Some(root.to_path_buf());
+            self.protected_paths = protected;
+            set.push("protected_paths");
+        }
+
+        let limits = project.limits;
+        for (name, value) in [
+            ("max_iterations", limits.max_iterations),
+            ("max_read_bytes", limits.max_read_bytes),
+            ("max_result_bytes", limits.max_result_bytes),
+            ("context_lines", limits.context_lines),
+            ("max_context_chars", limits.max_context_chars),
+            ("max_tools_per_iteration", limits.max_tools_per_iteration),
+            ("request_timeout", limits.request_timeout),
+        ] {
+            match value.map(|value| self.limits.set(name, value)) {
+                Some(Ok(name)) => set.push(name),
+                Some(Err(e)) => warn!("Warning: {}: {}", path.display(), e),
+                None => {}
+            }
+        }
+        if let Some(max_files) = limits.max_files {
+            self.max_files = max_files;
+            set.push("max_files");
+        }
+        if limits.lite == Some(true) {
+            self.set_lite(Source::Project(path.clone()));
+        }
+
+        for name in set {
+            self.set_source(name, Source::Project(path.clone()));
+        }
+        self.project_file = Some(path);
+    }
+
+    // Settings given on the command line or changed in this session outrank every file, so they survive
+    // the reload that /cd does
+    pub fn carry_overrides(&mut self, old: &Config) {
+        for (name, source) in &old.sources {
+            if !matches!(source, Source::Cli(_) | Source::Runtime(_)) {
+                continue;
+            }
+            match *name {
+                "provider" => self.provider.clone_from(&old.provider),
+                "model" => self.model.clone_from(&old.model),
+                "temperature" => self.temperature = old.temperature,
+                "max_tokens" => self.max_tokens = old.max_tokens,
+                "max_files" => self.max_files = old.max_files,
+                "lite" => self.lite = old.lite,
+                "tool_rate_limit" => self.tool_rate_limit = old.tool_rate_limit,
+                "blocked_actions" => self.blocked_actions.clone_from(&old.blocked_actions),
+                "confirm_actions" => self.confirm_actions.clone_from(&old.confirm_actions),
+                "mode" => {
+                    self.mode.clone_from(&old.mode);
+                    self.mode_blocked.clone_from(&old.mode_blocked);
+                    self.auto_approve = old.auto_approve;
+                }
+                "allow_watch" => self.allow_watch = old.allow_watch,
+                "allow_net_read" => self.allow_net_read = old.allow_net_read,
+                "strict_json" => self.strict_json = old.strict_json,
+                "file_metadata" => self.file_metadata = old.file_metadata,
+                "atomic_batch" => self.atomic_batch = old.atomic_batch,
+                "paths_from_git_root" => self.paths_from_git_root = old.paths_from_git_root,
+                "quiet" => self.quiet = old.quiet,
+                "stream" => self.stream = old.stream,
+                "stats_line" => self.stats_line = old.stats_line,
+                "notify" => self.notify = old.notify,
+                "notify_after_secs" => self.notify_after_secs = old.notify_after_secs,
+                name => match old.limits.get(name) {
+                    Some(value) => {
+                        let _ = self.limits.set(name, value);
+                    }
+                    None => continue,
+                },
+            }
+            self.sources.insert(name, source.clone());
+        }
+    }
+
+    // The protected pattern covering `path` (absolute), if any
+    pub fn protected_pattern(&self, cwd: &Path, path: &Path) -> Option<String> {
+        if self.protected_paths.is_empty() {
+            return None;
+        }
+        let root = self.protected_root.as_deref().unwrap_or(cwd);
+        // The matcher panics on paths outside its root
+        if !path.starts_with(root) {
+            return None;
+        }
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in &self.protected_paths {
+            builder.add_line(None, pattern).ok()?;
+        }
+        match builder.build().ok()?.matched_path_or_any_parents(path, path.is_dir()) {
+            Match::Ignore(glob) => Some(glob.original().to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn set_lite(&mut self, source: Source) {
+        self.lite = true;
+        self.limits.max_iterations = self.limits.max_iterations.min(LITE_MAX_ITERATIONS);
+        self.set_source("max_iterations", source.clone());
+        self.set_source("lite", source);
+    }
+
+    // Configured default for a provider, else the first model listed for it
+    pub fn default_model(&self, provider: &str) -> Option<String> {
+        self.default_models
+            .get(provider)
+            .cloned()
+            .or_else(|| self.models.first_for(provider).map(|m| m.id.clone()))
+    }
+
+    // Provider a model belongs to: the registry entry if known, else a guess from the id
+    pub fn provider_for(&self, model: &str) -> String {
+        match self.models.get(model) {
+            Some(info) => info.provider.clone(),
+            None => crate::llm::detect_provider(model).into(),
+        }
+    }
+
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        let mut problem = |error: bool, setting: &'static str, message: String, fix: String| {
+            problems.push(Problem { error, setting, message, fix })
+        };
+
+        let provider = self.provider.as_str();
+        if !PROVIDERS.contains(&provider) {
+            problem(
+                true,
+                "provider",
+                format!("unknown provider '{}'", provider),
+                format!("set PROVIDER (or provider in config.toml) to one of: {}", PROVIDERS.join(", ")),
+            );
+        } else if !self.has_key(provider) {
+            let var = self.key_var(provider).unwrap_or_default();
+            let env_file = Self::env_paths().first().map(|p| p.display().to_string()).unwrap_or(".env".into());
+            problem(
+                true,
+                "provider",
+                format!("{} is selected but {} is not set", provider, var),
+                format!("add {} to {} or [providers] in config.toml, or switch with /set provider", var, env_file),
+            );
+        }
+        if provider == "ollama" {
+            let url = self.ollama_url.as_deref().unwrap_or("http://localhost:11434");
+            if !(url.starts_with("http://") || url.starts_with("https://")) {
+                problem(
+                    true,
+                    "ollama_url",
+                    format!("'{}' is not an http(s) URL", url),
+                    "set OLLAMA_URL to something like http://localhost:11434".into(),
+                );
+            }
+        }
+
+        if PROVIDERS.contains(&provider) {
+            match self.models.get(&self.model) {
+                Some(info) if info.provider != provider => problem(
+                    true,
+                    "model",
+                    format!("{} belongs to {}, but the provider is {}", self.model, info.provider, provider),
+                    format!("use /model {} to switch both, or set CLIO_MODEL to a {} model (see /models)", self.model, provider),
+                ),
+                Some(_) => {}
+                None if crate::llm::detect_provider(&self.model) != provider => problem(
+                    false,
+                    "model",
+                    format!("'{}' is not a known {} model", self.model, provider),
+                    "check the name against /models, or add it under [[models]] in config.toml".into(),
+                ),
+                None => {}
+            }
+        }
+
+        if let Some(t) = self.temperature.filter(|t| !(0.0..=2.0).contains(t)) {
+            problem(
+                true,
+                "temperature",
+                format!("{} is out of range", t),
+                "set CLIO_TEMPERATURE to a value from 0.0 to 2.0".into(),
+            );
+        }
+        if let Some(n) = self.max_tokens.filter(|n| !(1..=MAX_TOKENS_LIMIT).contains(n)) {
+            problem(
+                true,
+                "max_tokens",
+                format!("{} is out of range", n),
+                format!("set CLIO_MAX_TOKENS to a value from 1 to {}", MAX_TOKENS_LIMIT),
+            );
+        }
+        if self.limits.max_iterations > 100 {
+            problem(
+                false,
+                "max_iterations",
+                format!("{} iterations per prompt can burn through a lot of tokens", self.limits.max_iterations),
+                "set CLIO_MAX_ITERATIONS to 100 or less".into(),
+            );
+        }
+        if self.max_files == 0 {
+            problem(
+                false,
+                "max_files",
+                "0 means no files can be created".into(),
+                "set CLIO_MAX_FILES to at least 1".into(),
+            );
+        }
+
+        if let Some(dir) = self.history_file.as_deref().and_then(|p| p.parent()) {
+            if let Err(e) = check_writable(dir) {
+                problem(
+                    false,
+                    "history_file",
+                    format!("{} is not writable ({}); history will not be saved", dir.display(), e),
+                    "set CLIO_HISTORY_FILE to a writable path, or to \"none\"".into(),
+                );
+            }
+        }
+        if let Some(dir) = paths::state_dir() {
+            if let Err(e) = check_writable(&dir) {
+                problem(
+                    false,
+                    "state dir",
+                    format!("{} is not writable ({}); sessions and update checks will not be saved", dir.display(), e),
+                    format!("fix the permissions on {}", dir.display()),
+                );
+            }
+        }
+        problems
+    }
+
+    // Writes settings changed during the session to the config file; returns the file and the names written
+    pub fn save(&mut self) -> Result<(PathBuf, Vec<&'static str>), String> {
+        let path = match &self.config_file {
+            Some(path) => path.clone(),
+            None => config_file::paths().into_iter().next().ok_or("No home directory")?,
+        };
+        let changed: Vec<&'static str> = self
+            .sources
+            .iter()
+            .filter(|(_, source)| matches!(source, Source::Runtime(_)))
+            .map(|(name, _)| *name)
+            .collect();
+        let entries: Vec<_> = changed.iter().filter_map(|name| self.file_entry(name)).collect();
+        if entries.is_empty() {
+            return Ok((path, Vec::new()));
+        }
+        config_file::update(&path, self.profile.as_deref(), &entries)?;
+
+        let saved: Vec<&'static str> = changed.into_iter().filter(|name| self.file_entry(name).is_some()).collect();
+        for name in &saved {
+            self.set_source(name, Source::File(path.clone()));
+        }
+        self.config_file = Some(path.clone());
+        Ok((path, saved))
+    }
+
+    // Table, key and current value of a setting in config.toml; None for secrets and settings kept elsewhere
+    fn file_entry(&self, name: &str) -> Option<(&'static str, &'static str, toml_edit::Value)> {
+        let int = |n: usize| toml_edit::Value::from(n as i64);
+        let entry = match name {
+            "provider" => ("", "provider", self.provider.as_str().into()),
+            "model" => ("", "model", self.model.as_str().into()),
+            // Through the decimal text so 0.2f32 is written as 0.2, not 0.200000003
+            "temperature" => ("", "temperature", self.temperature?.to_string().parse::<f64>().ok()?.into()),
+            "max_tokens" => ("", "max_tokens", i64::from(self.max_tokens?).into()),
+            "notify_after_secs" => ("ui", "notify_after_secs", (self.notify_after_secs as i64).into()),
+            "allow_watch" => ("tools", "allow_watch", self.allow_watch.into()),
+            "allow_net_read" => ("tools", "allow_net_read", self.allow_net_read.into()),
+            "strict_json" => ("tools", "strict_json", self.strict_json.into()),
+            "file_metadata" => ("tools", "file_metadata", self.file_metadata.into()),
+            "atomic_batch" => ("tools", "atomic_batch", self.atomic_batch.into()),
+            "paths_from_git_root" => ("tools", "paths_from_git_root", self.paths_from_git_root.into()),
+            "blocked_actions" => ("tools", "blocked", self.blocked_actions.iter().collect::<toml_edit::Array>().into()),
+            "confirm_actions" => ("tools", "confirm", self.confirm_actions.iter().collect::<toml_edit::Array>().into()),
+            "max_files" => ("limits", "max_files", int(self.max_files)),
+            "lite" => ("limits", "lite", self.lite.into()),
+            "tool_rate_limit" => ("tools", "rate_limit", int(self.tool_rate_limit)),
+            "quiet" => ("ui", "quiet", self.quiet.into()),
+            "stream" => ("ui", "stream", self.stream.into()),
+            "stats_line" => ("ui", "stats_line", self.stats_line.into()),
+            "notify" => ("ui", "notify", self.notify.into()),
+            _ => {
+                let spec = LIMITS.iter().find(|spec| spec.name == name)?;
+                ("limits", spec.name, int(self.limits.get(name)?))
+            }
+        };
+        Some(entry)
+    }
+
+    // Changes a setting in the running session (/set); returns its name, or why the value was refused.
+    // Provider and model are switched through LLM, which keeps the two consistent
+    pub fn set(&mut self, name: &str, value: &str) -> Result<&'static str, String> {
+        let flag = |name: &str| match value {
+            "true" | "on" | "1" | "yes" => Ok(true),
+            "false" | "off" | "0" | "no" => Ok(false),
+            _ => Err(format!("{} must be true or false", name)),
+        };
+        let number = |name: &str, min: u64, max: u64| {
+            value
+                .parse::<u64>()
+                .ok()
+                .filter(|n| (min..=max).contains(n))
+                .ok_or_else(|| format!("{} must be a whole number from {} to {}", name, min, max))
+        };
+        let actions = |name: &str| {
+            let list = parse_list(value).unwrap_or_default();
+            match list.iter().find(|action| !crate::tools::TOOLS.iter().any(|(tool, _)| tool == action)) {
+                Some(unknown) => Err(format!("Unknown action '{}' in {}", unknown, name)),
+                None => Ok(list),
+            }
+        };
+        let name = match name {
+            "temperature" => {
+                self.temperature = match value {
+                    "default" => None,
+                    _ => Some(
+                        value
+                            .parse::<f32>()
+                            .ok()
+                            .filter(|t| (0.0..=2.0).contains(t))
+                            .ok_or("temperature must be from 0.0 to 2.0, or default")?,
+                    ),
+                };
+                "temperature"
+            }
+            "max_tokens" => {
+                self.max_tokens = match value {
+                    "default" => None,
+                    _ => Some(number("max_tokens", 1, MAX_TOKENS_LIMIT.into())? as u32),
+                };
+                "max_tokens"
+            }
+            "max_files" => {
+                self.max_files = number("max_files", 1, 10_000)? as usize;
+                "max_files"
+            }
+            "tool_rate_limit" => {
+                self.tool_rate_limit = number("tool_rate_limit", 0, 10_000)? as usize;
+                "tool_rate_limit"
+            }
+            "notify_after_secs" => {
+                self.notify_after_secs = number("notify_after_secs", 0, 86_400)?;
+                "notify_after_secs"
+            }
+            "quiet" => {
+                self.quiet = flag("quiet")?;
+                "quiet"
+            }
+            "stream" => {
+                self.stream = flag("stream")?;
+                "stream"
+            }
+            "stats_line" => {
+                self.stats_line = flag("stats_line")?;
+                "stats_line"
+            }
+            "notify" => {
+                self.notify = flag("notify")?;
+                "notify"
+            }
+            "allow_watch" => {
+                self.allow_watch = flag("allow_watch")?;
+                "allow_watch"
+            }
+            "allow_net_read" => {
+                self.allow_net_read = flag("allow_net_read")?;
+                "allow_net_read"
+            }
+            "strict_json" => {
+                self.strict_json = flag("strict_json")?;
+                "strict_json"
+            }
+            "file_metadata" => {
+                self.file_metadata = flag("file_metadata")?;
+                "file_metadata"
+            }
+            "atomic_batch" => {
+                self.atomic_batch = flag("atomic_batch")?;
+                "atomic_batch"
+            }
+            "paths_from_git_root" => {
+                self.paths_from_git_root = flag("paths_from_git_root")?;
+                "paths_from_git_root"
+            }
+            "blocked_actions" => {
+                self.blocked_actions = actions("blocked_actions")?;
+                "blocked_actions"
+            }
+            "confirm_actions" => {
+                self.confirm_actions = actions("confirm_actions")?;
+                "confirm_actions"
+            }
+            _ if LIMITS.iter().any(|spec| spec.name == name) => {
+                let n = value.parse().map_err(|_| format!("{} must be a whole number", name))?;
+                self.limits.set(name, n)?
+            }
+            _ => {
+                return Err(format!("Unknown setting '{}' (settable: provider, model, {}, or a limit from /set limit)", name, SETTABLE.join(", ")));
+            }
+        };
+        Ok(name)
+    }
+
+    pub fn set_source(&mut self, name: &'static str, source: Source) {
+        self.sources.insert(name, source);
+    }
+
+    pub fn source(&self, name: &str) -> &Source {
+        self.sources.get(name).unwrap_or(&Source::Default)
+    }
+
+    // The generic variable (MODEL, QUIET, ...) a setting came from, which another tool may also set
+    pub fn bare_env_var(&self, name: &str) -> Option<&str> {
+        let var = match self.source(name) {
+            Source::Env(var) | Source::EnvFile(_, var) => var,
+            _ => return None,
+        };
+        // Credentials keep the names other tools share on purpose
+        let credential = var.starts_with("AWS_") || PROVIDERS.iter().any(|p| Self::key_settings(p).contains(&name));
+        (!var.starts_with("CLIO_") && !credential).then_some(var.as_str())
+    }
+
+    // Every effective setting in a stable order, secrets masked, for /config
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        let list = |items: &[String]| if items.is_empty() { "(none)".to_string() } else { items.join(",") };
+        vec![
+            ("profile", self.profile.clone().unwrap_or("(none)".into())),
+            ("provider", self.provider.clone()),
+            ("model", self.model.clone()),
+            ("default_models", {
+                let pairs: Vec<String> = self.default_models.iter().map(|(p, m)| format!("{}={}", p, m)).collect();
+                if pairs.is_empty() { "(first listed per provider)".into() } else { pairs.join(",") }
+            }),
+            ("models", {
+                let custom: Vec<&str> = self.models.custom().map(|m| m.id.as_str()).collect();
+                if custom.is_empty() { "(built-in)".into() } else { format!("built-in + {}", custom.join(",")) }
+            }),
+            ("temperature", self.temperature.map(|t| t.to_string()).unwrap_or("(provider default)".into())),
+            ("max_tokens", self.max_tokens.map(|n| n.to_string()).unwrap_or("(provider default)".into())),
+            ("gemini_api_key", mask(self.gemini_api_key.as_deref())),
+            ("groq_api_key", mask(self.groq_api_key.as_deref())),
+            ("deepseek_api_key", mask(self.deepseek_api_key.as_deref())),
+            ("hf_api_key", mask(self.hf_api_key.as_deref())),
+            ("ollama_url", self.ollama_url.clone().unwrap_or_default()),
+            ("ollama_auto_pull", self.ollama_auto_pull.clone()),
+            ("aws_access_key_id", mask(self.aws_access_key_id.as_deref())),
+            ("aws_secret_access_key", mask(self.aws_secret_access_key.as_deref())),
+            ("aws_region", self.aws_region.clone()),
+            ("max_iterations", self.limits.max_iterations.to_string()),
+            ("max_read_bytes", self.limits.max_read_bytes.to_string()),
+            ("max_result_bytes", self.limits.max_result_bytes.to_string()),
+            ("context_lines", self.limits.context_lines.to_string()),
+            ("max_context_chars", self.limits.max_context_chars.to_string()),
+            ("max_tools_per_iteration", self.limits.max_tools_per_iteration.to_string()),
+            ("request_timeout", format!("{}s", self.limits.request_timeout)),
+            ("max_files", self.max_files.to_string()),
+            ("lite", self.lite.to_string()),
+            ("tool_rate_limit", self.tool_rate_limit.to_string()),
+            ("blocked_actions", list(&self.blocked_actions)),
+            ("confirm_actions", list(&self.confirm_actions)),
+            ("protected_paths", list(&self.protected_paths)),
+            ("mode", self.mode.clone().unwrap_or("(none)".into())),
+            ("allow_watch", self.allow_watch.to_string()),
+            ("allow_net_read", self.allow_net_read.to_string()),
+            ("strict_json", self.strict_json.to_string()),
+            ("file_metadata", self.file_metadata.to_string()),
+            ("atomic_batch", self.atomic_batch.to_string()),
+            ("paths_from_git_root", self.paths_from_git_root.to_string()),
+            ("watch_prompt", self.watch_prompt.clone()),
+            ("default_prompt", self.default_prompt.clone().unwrap_or("(none)".into())),
+            ("persist_model_per_dir", self.persist_model_per_dir.to_string()),
+            ("system_prompt_file", if self.system_prompt.is_some() { "(custom)".into() } else { "(built-in)".into() }),
+            ("quiet", self.quiet.to_string()),
+            ("stats_line", self.stats_line.to_string()),
+            ("nerd_fonts", self.nerd_fonts.to_string()),
+            ("stream", self.stream.to_string()),
+            ("notify", self.notify.to_string()),
+            ("notify_after_secs", self.notify_after_secs.to_string()),
+            ("update_check", self.update_check.to_string()),
+            ("persist_settings", self.persist_settings.to_string()),
+            ("test_connection", self.test_connection.to_string()),
+            ("history_file", self.history_file.as_ref().map(|p| p.display().to_string()).unwrap_or("(off)".into())),
+        ]
+    }
+
+    fn load_system_prompt(path: &str) -> Option<String> {
+        if path.trim().is_empty() {
+            return None;
+        }
+        match std::fs::read_to_string(expand_home(path)) {
+            Ok(prompt) if !prompt.trim().is_empty() => Some(prompt),
+            Ok(_) => {
+                warn!("Warning: system prompt file {} is empty; using the built-in prompt", path);
+                None
+            }
+            Err(e) => {
+                warn!("Warning: cannot read system prompt file {}: {}; using the built-in prompt", path, e);
+                None
+            }
+        }
+    }
+    
+    // Settings holding a provider's credentials; empty for providers that need none
+    pub fn key_settings(provider: &str) -> &'static [&'static str] {
+        match provider {
+            "gemini" => &["gemini_api_key"],
+            "groq" => &["groq_api_key"],
+            "deepseek" => &["deepseek_api_key"],
+            "huggingface" => &["hf_api_key"],
+            "bedrock" => &["aws_access_key_id", "aws_secret_access_key"],
+            _ => &[],
+        }
+    }
+
+    // Env var a key setting is read from: the setting name in capitals unless a *_env setting renames it
+    pub fn setting_var(&self, setting: &str) -> String {
+        self.key_env.get(setting).cloned().unwrap_or(setting.to_uppercase())
+    }
+
+    // Env var holding the credential for a provider; None for providers that need no key
+    pub fn key_var(&self, provider: &str) -> Option<String> {
+        let vars: Vec<String> = Self::key_settings(provider).iter().map(|s| self.setting_var(s)).collect();
+        (!vars.is_empty()).then(|| vars.join(" and "))
+    }
+
+    pub fn key_mut(&mut self, setting: &str) -> Option<&mut Option<String>> {
+        match setting {
+            "gemini_api_key" => Some(&mut self.gemini_api_key),
+            "groq_api_key" => Some(&mut self.groq_api_key),
+            "deepseek_api_key" => Some(&mut self.deepseek_api_key),
+            "hf_api_key" => Some(&mut self.hf_api_key),
+            "aws_access_key_id" => Some(&mut self.aws_access_key_id),
+            "aws_secret_access_key" => Some(&mut self.aws_secret_access_key),
+            _ => None,
+        }
+    }
+
+    pub fn key(&self, setting: &str) -> Option<&str> {
+        let key = match setting {
+            "gemini_api_key" => &self.gemini_api_key,
+            "groq_api_key" => &self.groq_api_key,
+            "deepseek_api_key" => &self.deepseek_api_key,
+            "hf_api_key" => &self.hf_api_key,
+            "aws_access_key_id" => &self.aws_access_key_id,
+            "aws_secret_access_key" => &self.aws_secret_access_key,
+            _ => &None,
+        };
+        key.as_deref().filter(|k| !k.trim().is_empty())
+    }
+
+    pub fn has_key(&self, provider: &str) -> bool {
+        Self::key_settings(provider).iter().all(|setting| self.key(setting).is_some())
+    }
+
+    pub fn env_paths() -> Vec<PathBuf> {
+        let legacy = dirs::home_dir().map(|home| home.join(".ai-cli"));
+        [paths::config_dir(), paths::legacy_dir(), legacy].into_iter().flatten().map(|dir| dir.join(".env")).collect()
     }
 }
 
+const LITE_MAX_ITERATIONS: usize = 3;
+
+const MAX_TOKENS_LIMIT: u32 = 1_000_000;
+
+// Values of `mode` in a project file
+const MODES: &[&str] = &["read-only", "careful", "standard", "auto"];
+
+// Settings /set changes besides provider and model; the limits are listed in LIMITS
+pub const SETTABLE: &[&str] = &[
+    "temperature",
+    "max_tokens",
+    "max_files",
+    "tool_rate_limit",
+    "blocked_actions",
+    "confirm_actions",
+    "allow_watch",
+    "allow_net_read",
+    "strict_json",
+    "file_metadata",
+    "atomic_batch",
+    "paths_from_git_root",
+    "quiet",
+    "stream",
+    "stats_line",
+    "notify",
+    "notify_after_secs",
+];
+
+pub const PROVIDERS: &[&str] = &["gemini", "groq", "deepseek", "huggingface", "bedrock", "ollama"];
+
 pub const MODELS: &[(&str, &str, &str)] = &[
     ("gemini-3-flash-preview", "Gemini 3 Flash", "gemini"),
     ("gemini-2.5-flash-lite", "Gemini 2.5 Flash Lite", "gemini"),
@@ -49,5 +1377,75 @@ pub const MODELS: &[(&str, &str, &str)] = &[
     ("gemini-2.5-pro", "Gemini 2.5 Pro", "gemini"),
     ("compound-beta", "Groq Compound", "groq"),
     ("meta-llama/llama-4-scout-17b-16e-instruct", "Llama 4 Scout", "groq"),
+    ("deepseek-chat", "DeepSeek V3 Chat", "deepseek"),
+    ("deepseek-reasoner", "DeepSeek R1 Reasoner", "deepseek"),
+    ("anthropic.claude-3-5-sonnet-20241022-v2:0", "Claude 3.5 Sonnet v2", "bedrock"),
+    ("meta.llama3-1-70b-instruct-v1:0", "Llama 3.1 70B", "bedrock"),
+    ("Qwen/Qwen2.5-Coder-32B-Instruct", "Qwen 2.5 Coder 32B", "huggingface"),
+    ("meta-llama/Llama-3.3-70B-Instruct", "Llama 3.3 70B", "huggingface"),
+    ("deepseek-ai/DeepSeek-V3-0324", "DeepSeek V3", "huggingface"),
     ("llama3.2", "Llama 3.2 (Ollama)", "ollama"),
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_file_values_are_read_without_setting_process_vars() {
+        let path = PathBuf::from("/project/.env");
+        let vars = [("CLIO_TEST_ENV_FILE_ONLY", "from file"), ("PATH", "from file")];
+        let env_file_vars = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let mut l = Loader { file: None, env_file: Some(path.clone()), env_file_vars, env_file_local: false, isolated: false, sources: BTreeMap::new() };
+        assert_eq!(l.get("a", "TEST_ENV_FILE_ONLY", None, parse_string).as_deref(), Some("from file"));
+        assert_eq!(l.sources["a"], Source::EnvFile(path, "CLIO_TEST_ENV_FILE_ONLY".into()));
+        assert!(env::var_os("CLIO_TEST_ENV_FILE_ONLY").is_none());
+        assert_eq!(l.get("b", "PATH", None, parse_string), env::var("PATH").ok());
+        assert_eq!(l.sources["b"], Source::Env("PATH".into()));
+    }
+
+    #[test]
+    fn project_env_file_cannot_set_safety_settings() {
+        let vars = [("CLIO_TEST_LOCAL_CONFIRM", ""), ("CLIO_TEST_LOCAL_PROMPT", "hi")];
+        let env_file_vars = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let mut l = Loader { file: None, env_file: Some("/repo/.env".into()), env_file_vars, env_file_local: true, isolated: false, sources: BTreeMap::new() };
+        assert_eq!(l.get("confirm_actions", "TEST_LOCAL_CONFIRM", None, parse_list), None);
+        assert_eq!(l.sources["confirm_actions"], Source::Default);
+        assert_eq!(l.get("watch_prompt", "TEST_LOCAL_PROMPT", None, parse_string).as_deref(), Some("hi"));
+    }
+
+    #[test]
+    fn keep_safety_of_reverts_only_what_loosens() {
+        let mut before = Config::defaults();
+        before.blocked_actions = vec!["delete".into()];
+        before.confirm_actions = vec!["create_file".into()];
+        before.tool_rate_limit = 50;
+        before.allow_net_read = false;
+        before.protected_paths.clear();
+
+        let mut after = before.clone();
+        after.apply_mode("auto").unwrap();
+        after.blocked_actions = vec!["apply_diff".into()];
+        after.allow_net_read = true;
+        after.tool_rate_limit = 10;
+        after.protected_paths = vec!["infra/**".into()];
+        after.max_files = before.max_files + 100;
+        after.limits.max_iterations = 3;
+        let loosened = after.keep_safety_of(&before);
+
+        assert_eq!(loosened.len(), 5, "{:?}", loosened);
+        assert_eq!(after.max_files, before.max_files);
+        assert_eq!(after.limits.max_iterations, 3);
+        assert!(!after.auto_approve);
+        assert!(after.blocked_actions.contains(&"delete".to_string()));
+        assert!(after.blocked_actions.contains(&"apply_diff".to_string()));
+        assert_eq!(after.confirm_actions, vec!["create_file".to_string()]);
+        assert!(!after.allow_net_read);
+        assert_eq!(after.tool_rate_limit, 10);
+        assert_eq!(after.protected_paths, vec!["infra/**".to_string()]);
+
+        let mut tightened = before.clone();
+        tightened.apply_mode("read-only").unwrap();
+        assert!(tightened.keep_safety_of(&before).is_empty());
+    }
+}
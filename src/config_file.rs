@@ -0,0 +1,385 @@
+use crate::models::ModelInfo;
+use crate::paths;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Mirror of config.toml; every value is optional and environment variables win over it
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ConfigFile {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub system_prompt_file: Option<String>,
+    pub watch_prompt: Option<String>,
+    pub default_prompt: Option<String>,
+    pub persist_model_per_dir: Option<bool>,
+    pub default_model: BTreeMap<String, String>,
+    pub providers: ProvidersSection,
+    pub tools: ToolsSection,
+    pub limits: LimitsSection,
+    pub ui: UiSection,
+    pub models: Vec<ModelInfo>,
+    // [profile.<name>] overlays, merged over the rest of the file by load() when selected
+    pub profile: BTreeMap<String, toml::Table>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ProvidersSection {
+    pub gemini_api_key: Option<String>,
+    pub groq_api_key: Option<String>,
+    pub deepseek_api_key: Option<String>,
+    pub hf_api_key: Option<String>,
+    pub ollama_url: Option<String>,
+    pub ollama_auto_pull: Option<String>,
+    pub aws_access_key_id: Option<String>,
+    pub aws_secret_access_key: Option<String>,
+    pub aws_region: Option<String>,
+    // Env var to read a key from instead of the default name, so profiles can use different accounts
+    pub gemini_api_key_env: Option<String>,
+    pub groq_api_key_env: Option<String>,
+    pub deepseek_api_key_env: Option<String>,
+    pub hf_api_key_env: Option<String>,
+    pub aws_access_key_id_env: Option<String>,
+    pub aws_secret_access_key_env: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ToolsSection {
+    pub blocked: Option<Vec<String>>,
+    pub confirm: Option<Vec<String>>,
+    pub allow_watch: Option<bool>,
+    pub allow_net_read: Option<bool>,
+    pub strict_json: Option<bool>,
+    pub file_metadata: Option<bool>,
+    pub atomic_batch: Option<bool>,
+    pub paths_from_git_root: Option<bool>,
+    pub rate_limit: Option<usize>,
+    #[serde(alias = "deny_paths")]
+    pub protected: Option<Vec<String>>,
+    // delete = "confirm", create_file = "allow", ...: see policy::apply_rules
+    #[serde(flatten)]
+    pub actions: BTreeMap<String, toml::Value>,
+}
+
+// .clio.toml: a project's overrides for the settings that describe how to work in it. Keys, provider URLs
+// and UI preferences stay personal and are not read from it
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ProjectFile {
+    pub mode: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub tools: ToolsSection,
+    pub limits: LimitsSection,
+}
+
+pub const PROJECT_FILES: &[&str] = &[".clio.toml", ".clio/config.toml"];
+
+// The nearest project file from `cwd` up to the git root; without a git root only `cwd` is searched
+pub fn find_project(cwd: &Path) -> Option<PathBuf> {
+    let top = cwd.ancestors().find(|dir| dir.join(".git").exists()).unwrap_or(cwd);
+    for dir in cwd.ancestors() {
+        if let Some(path) = PROJECT_FILES.iter().map(|name| dir.join(name)).find(|path| path.is_file()) {
+            return Some(path);
+        }
+        if dir == top {
+            break;
+        }
+    }
+    None
+}
+
+// Also returns the content's hash, which decides whether the file is still trusted (see trust.rs)
+pub fn load_project(cwd: &Path) -> Option<(PathBuf, ProjectFile, String)> {
+    let path = find_project(cwd)?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => {
+            warn!("Warning: cannot read {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let parsed = toml::from_str::<toml::Table>(&text).map_err(|e| e.to_string()).and_then(|mut table| {
+        if table.remove("providers").is_some() {
+            warn!("Warning: ignoring [providers] in {}: keys and provider URLs are never read from a project file", path.display());
+        }
+        serde_ignored::deserialize(toml::Value::Table(table), |key| {
+            warn!("Warning: unknown or not per-project key '{}' in {}", key, path.display());
+        })
+        .map_err(|e| e.to_string())
+    });
+    match parsed {
+        Ok(project) => Some((path, project, crate::trust::hash(&text))),
+        Err(e) => {
+            warn!("Warning: ignoring {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LimitsSection {
+    pub max_iterations: Option<usize>,
+    pub max_read_bytes: Option<usize>,
+    pub max_result_bytes: Option<usize>,
+    pub context_lines: Option<usize>,
+    pub max_context_chars: Option<usize>,
+    pub max_tools_per_iteration: Option<usize>,
+    pub request_timeout: Option<usize>,
+    pub max_files: Option<usize>,
+    pub lite: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct UiSection {
+    pub quiet: Option<bool>,
+    pub stats_line: Option<bool>,
+    pub nerd_fonts: Option<bool>,
+    pub stream: Option<bool>,
+    pub notify: Option<bool>,
+    pub notify_after_secs: Option<u64>,
+    pub update_check: Option<bool>,
+    pub persist_settings: Option<bool>,
+    pub test_connection: Option<bool>,
+    pub history_file: Option<String>,
+}
+
+// Searched in order; the first file that exists is used. ~/.config is kept for macOS setups from before
+// the platform config dir was used
+pub fn paths() -> Vec<PathBuf> {
+    let dot_config = dirs::home_dir().map(|home| home.join(".config").join("clio-ai"));
+    let mut found: Vec<PathBuf> = Vec::new();
+    for dir in [paths::config_dir(), dot_config, paths::legacy_dir()].into_iter().flatten() {
+        let path = dir.join("config.toml");
+        if !found.contains(&path) {
+            found.push(path);
+        }
+    }
+    found
+}
+
+// Unknown keys and parse errors are reported as warnings; a broken file is ignored rather than fatal.
+// A selected profile is merged over the top-level settings
+pub fn load(profile: Option<&str>) -> Option<(PathBuf, ConfigFile)> {
+    let path = paths().into_iter().find(|p| p.is_file())?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => {
+            warn!("Warning: cannot read {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    match parse(&text, &path, profile) {
+        Ok(file) => Some((path, file)),
+        Err(e) => {
+            warn!("Warning: ignoring {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+// Strict re-parse used after /config edit so a broken file keeps the previous config
+pub fn check(path: &Path) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    toml::from_str::<ConfigFile>(&text).map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn parse(text: &str, path: &Path, profile: Option<&str>) -> Result<ConfigFile, String> {
+    let mut table: toml::Table = toml::from_str(text).map_err(|e| e.to_string())?;
+    if let Some(name) = profile {
+        let overlay = table.get("profile").and_then(|p| p.get(name)).and_then(|p| p.as_table()).cloned();
+        match overlay {
+            Some(overlay) => {
+                // A profile naming only a model (or only a provider) gets the matching other half, not the top-level one
+                if overlay.contains_key("model") != overlay.contains_key("provider") {
+                    table.remove(if overlay.contains_key("model") { "provider" } else { "model" });
+                }
+                merge(&mut table, overlay)
+            }
+            None => warn!("Warning: no [profile.{}] in {}; using the top-level settings", name, path.display()),
+        }
+    }
+    serde_ignored::deserialize(toml::Value::Table(table), |key| {
+        warn!("Warning: unknown key '{}' in {}", key, path.display());
+    })
+    .map_err(|e| e.to_string())
+}
+
+// Tables merge key by key; anything else in the overlay replaces the base value
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+pub const EXAMPLE: &str = r#"# clio-ai configuration. Environment variables (and .env files) override these values.
+
+# provider = "gemini"            # gemini | groq | deepseek | huggingface | bedrock | ollama
+# model = "gemini-3-flash-preview"
+# temperature = 0.2
+# max_tokens = 4096              # cap on reply length (default: the provider's)
+# system_prompt_file = "~/.clio-ai/system_prompt.txt"
+# watch_prompt = "These files changed: {files}. Review them and fix any problems you find."
+# default_prompt = "summarize what changed since the last commit"   # submitted once when the REPL starts
+# persist_model_per_dir = false  # switch back to the model last chosen with /model in each project
+
+# Model used when switching provider without naming a model (default: first listed in /models)
+# default_model.groq = "llama-3.3-70b-versatile"
+# default_model.ollama = "llama3.2"
+
+# Extra models for /models and provider detection; an id that is already listed replaces it
+# [[models]]
+# id = "my-finetune"
+# name = "My fine-tune"
+# provider = "ollama"            # gemini | groq | deepseek | huggingface | bedrock | ollama
+# context_window = 32768
+# input_price = 0.15             # USD per million tokens, for cost estimates
+# output_price = 0.60
+
+[providers]
+# gemini_api_key = ""
+# groq_api_key = ""
+# deepseek_api_key = ""
+# hf_api_key = ""
+# ollama_url = "http://localhost:11434"
+# ollama_auto_pull = "ask"       # ask | always | never: pull a model Ollama reports missing
+# aws_access_key_id = ""
+# aws_secret_access_key = ""
+# aws_region = "us-east-1"
+# gemini_api_key_env = "GEMINI_API_KEY"   # read a key from another env var (also *_env for the other keys)
+
+[tools]
+# blocked = ["delete"]           # actions the model may not use
+# confirm = ["delete"]           # actions that ask y/N first
+# allow_watch = false
+# allow_net_read = false         # let the model fetch public http(s) URLs with read_url
+# strict_json = false            # only JSON replies run tools; never guess files from markdown
+# file_metadata = false          # read_file notes CRLF line endings, a BOM or no final newline
+# atomic_batch = false           # roll back a batch's file changes when one of them fails
+# paths_from_git_root = false    # tool paths are relative to the repository root, not the working directory
+# rate_limit = 50                # file-changing actions per minute, 0 = unlimited
+# protected = ["infra/prod/", "*.lock"]   # gitignore-style patterns the model may not change (alias: deny_paths)
+# delete = "confirm"             # per-action rule: allow | confirm | deny (applied after blocked/confirm)
+
+[limits]
+# max_iterations = 10            # model round-trips per prompt (1-1000)
+# max_read_bytes = 1048576       # larger files are refused by read_file
+# max_result_bytes = 65536       # tool results are cut to this size
+# context_lines = 60             # lines of each key file in the repo context
+# max_context_chars = 8000       # ...and at most this many characters of it (minified files)
+# max_tools_per_iteration = 50   # actions run from one reply; the rest are sent back
+# request_timeout = 300          # seconds per LLM request, retries included
+# max_files = 50
+# lite = false                   # same as --lite
+
+[ui]
+# quiet = false
+# stats_line = true
+# nerd_fonts = false
+# stream = false
+# notify = false
+# notify_after_secs = 30
+# update_check = false
+# persist_settings = false       # write /model, /set and /quiet changes here on exit (/config save does it on demand)
+# test_connection = false        # send "respond with: ok" to the provider at startup
+# history_file = "~/.local/state/clio-ai/history.txt"   # "none" to keep no history
+
+# Profiles, selected with --profile, CLIO_PROFILE or /profile. Each can set anything above and is merged
+# over it; environment variables still take precedence, so keep secrets in .env under their own names.
+# [profile.work]
+# provider = "groq"
+# model = "llama-3.3-70b-versatile"
+# [profile.work.providers]
+# groq_api_key_env = "WORK_GROQ_API_KEY"
+"#;
+
+// Writes the commented example to the first config path; refuses to clobber an existing file
+pub fn init(force: bool) -> Result<PathBuf, String> {
+    let path = paths().into_iter().next().ok_or("No home directory")?;
+    if path.exists() && !force {
+        return Err(format!("{} already exists (use --force to overwrite)", path.display()));
+    }
+    write_example(&path)?;
+    Ok(path)
+}
+
+const SAVED_HEADER: &str = "# Written by clio-ai (/config save). Environment variables (and .env files) override these values.\n";
+
+// Sets each (table, key) to its value, under [profile.<name>] when a profile is given, keeping the rest of the
+// file and its comments. The new text goes to a temp file that is renamed over the old one, so a concurrent
+// reader or writer never sees a half-written file
+pub fn update(path: &Path, profile: Option<&str>, entries: &[(&str, &str, toml_edit::Value)]) -> Result<(), String> {
+    let (text, header) = match fs::read_to_string(path) {
+        Ok(text) => (text, ""),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => (String::new(), SAVED_HEADER),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    let mut doc: toml_edit::DocumentMut = text.parse().map_err(|e| format!("{}: {}", path.display(), e))?;
+    let not_table = |name: &str| format!("{} in {} is not a table", name, path.display());
+    let mut root = doc.as_table_mut();
+    if let Some(name) = profile {
+        let profiles = root.entry("profile").or_insert(toml_edit::table());
+        let profiles = profiles.as_table_mut().ok_or_else(|| not_table("profile"))?;
+        profiles.set_implicit(true);
+        root = profiles.entry(name).or_insert(toml_edit::table()).as_table_mut().ok_or_else(|| not_table(name))?;
+    }
+    for (table, key, value) in entries {
+        let item = toml_edit::Item::Value(value.clone());
+        if table.is_empty() {
+            root.insert(key, item);
+        } else {
+            let section = root.entry(table).or_insert(toml_edit::table());
+            let section = section.as_table_like_mut().ok_or_else(|| not_table(table))?;
+            section.insert(key, item);
+        }
+    }
+
+    // Owner-only when new, else the original file's mode, so a rewrite never widens who can read it
+    #[cfg(unix)]
+    let mode = fs::metadata(path).map(|meta| std::os::unix::fs::PermissionsExt::mode(&meta.permissions()) & 0o777).unwrap_or(0o600);
+    #[cfg(not(unix))]
+    let mode = 0o600;
+    paths::write_atomic(path, format!("{}{}", header, doc).as_bytes(), mode)
+}
+
+pub fn write_example(path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, EXAMPLE).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn update_keeps_the_file_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = crate::paths::test_dir("config-update-mode");
+        let path = dir.join("config.toml");
+        let value = |v: &str| toml_edit::Value::from(v);
+        update(&path, None, &[("", "model", value("a"))]).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        update(&path, None, &[("", "model", value("b"))]).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o640);
+        assert!(fs::read_to_string(&path).unwrap().contains("model = \"b\""));
+    }
+}
@@ -0,0 +1,44 @@
+use crate::paths;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// PERSIST_MODEL_PER_DIR: the model last picked with /model or /set in each project, keyed by the
+// canonical git root (or the directory itself outside a repository)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirModel {
+    pub provider: String,
+    pub model: String,
+}
+
+fn state_path() -> Option<PathBuf> {
+    paths::state_file("dir-models.json")
+}
+
+pub fn project_root(cwd: &Path) -> PathBuf {
+    let cwd = fs::canonicalize(cwd).unwrap_or(cwd.to_path_buf());
+    cwd.ancestors().find(|dir| dir.join(".git").exists()).unwrap_or(&cwd).to_path_buf()
+}
+
+fn load() -> BTreeMap<PathBuf, DirModel> {
+    state_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn recall(cwd: &Path) -> Option<(PathBuf, DirModel)> {
+    let root = project_root(cwd);
+    let entry = load().remove(&root)?;
+    Some((root, entry))
+}
+
+pub fn remember(cwd: &Path, provider: &str, model: &str) -> Result<(), String> {
+    let path = state_path().ok_or("No home directory")?;
+    let mut entries = load();
+    entries.retain(|dir, _| dir.is_dir());
+    entries.insert(project_root(cwd), DirModel { provider: provider.to_string(), model: model.to_string() });
+    let json = serde_json::to_vec_pretty(&entries).map_err(|e| e.to_string())?;
+    paths::write_atomic(&path, &json, 0o600)
+}
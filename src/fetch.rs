@@ -0,0 +1,156 @@
+use reqwest::{redirect, Client, Url};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+pub const MAX_BYTES: usize = 512 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+// Fetches a public http(s) URL as text. Redirects are reported, not followed, so every hop gets checked
+pub async fn read_url(url: &str) -> Result<String, String> {
+    let url = Url::parse(url.trim()).map_err(|e| format!("Invalid URL: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Only http and https URLs are allowed, not {}:", url.scheme()));
+    }
+    let host = url.host_str().ok_or("URL has no host")?.to_string();
+    let addr = public_addr(&url, &host).await?;
+
+    // Pin the connection to the address that was checked so a second DNS answer cannot differ, and go
+    // direct: a proxy would resolve the host itself and could reach what the check refused
+    let client = Client::builder()
+        .timeout(TIMEOUT)
+        .no_proxy()
+        .redirect(redirect::Policy::none())
+        .resolve(&host, addr)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut response = client.get(url.clone()).send().await.map_err(|e| e.to_string())?;
+
+    let status = response.status();
+    if status.is_redirection() {
+        let location = response.headers().get("location").and_then(|l| l.to_str().ok()).unwrap_or("(none)");
+        return Err(format!("{} redirects ({}) to {}; call read_url with that URL if it is what you want", url, status, location));
+    }
+    if !status.is_success() {
+        return Err(format!("{} returned {}", url, status));
+    }
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|c| c.to_str().ok())
+        .unwrap_or_default()
+        .to_lowercase();
+    if !is_text(&content_type) {
+        return Err(format!("{} is not text ({})", url, content_type));
+    }
+
+    let mut body = Vec::new();
+    let mut truncated = false;
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        body.extend_from_slice(&chunk);
+        if body.len() > MAX_BYTES {
+            body.truncate(MAX_BYTES);
+            truncated = true;
+            break;
+        }
+    }
+    let mut text = String::from_utf8_lossy(&body).to_string();
+    if truncated {
+        text.push_str(&format!("\n[truncated at {} KB]", MAX_BYTES / 1024));
+    }
+    Ok(text)
+}
+
+fn is_text(content_type: &str) -> bool {
+    content_type.is_empty()
+        || content_type.starts_with("text/")
+        || ["json", "xml", "javascript", "yaml", "toml"].iter().any(|t| content_type.contains(t))
+}
+
+async fn public_addr(url: &Url, host: &str) -> Result<SocketAddr, String> {
+    if host.eq_ignore_ascii_case("localhost") || host.to_lowercase().ends_with(".localhost") {
+        return Err("Access denied: localhost is not allowed".into());
+    }
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.trim_start_matches('[').trim_end_matches(']'), port))
+        .await
+        .map_err(|e| format!("Cannot resolve {}: {}", host, e))?
+        .collect();
+    // Every answer must be public, otherwise the pinned choice would not matter
+    if let Some(private) = addrs.iter().find(|a| !is_public(a.ip())) {
+        return Err(format!("Access denied: {} resolves to a private address ({})", host, private.ip()));
+    }
+    addrs.into_iter().next().ok_or_else(|| format!("Cannot resolve {}", host))
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_unspecified()
+                || v4.is_multicast()
+                || a == 0
+                // 100.64.0.0/10 carrier-grade NAT
+                || (a == 100 && (64..128).contains(&b))
+                // 198.18.0.0/15 benchmarking, 240.0.0.0/4 reserved
+                || (a == 198 && (18..20).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(v6) => match embedded_v4(v6) {
+            Some(v4) => is_public(IpAddr::V4(v4)),
+            None => {
+                !(v6.is_loopback()
+                    || v6.is_unspecified()
+                    || v6.is_multicast()
+                    || v6.is_unique_local()
+                    || v6.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+// IPv6 forms that reach an IPv4 address, which then decides: mapped (::ffff:a.b.c.d), the deprecated
+// compatible form (::a.b.c.d), NAT64 (64:ff9b::/96) and 6to4 (2002::/16)
+fn embedded_v4(v6: Ipv6Addr) -> Option<Ipv4Addr> {
+    let octets = v6.octets();
+    let tail = |at: usize| Ipv4Addr::new(octets[at], octets[at + 1], octets[at + 2], octets[at + 3]);
+    let segments = v6.segments();
+    if let Some(v4) = v6.to_ipv4_mapped() {
+        Some(v4)
+    } else if (segments[..6] == [0; 6] && !v6.is_loopback() && !v6.is_unspecified()) || segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        Some(tail(12))
+    } else if segments[0] == 0x2002 {
+        Some(tail(2))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn private_targets_behind_other_forms_are_refused() {
+        for ip in [
+            "198.18.0.1",
+            "198.19.255.255",
+            "240.0.0.1",
+            "::ffff:127.0.0.1",
+            "::10.0.0.1",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a9fe:a9fe",
+            "2002:7f00:1::",
+            "2002:c0a8:101::1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{} was allowed", ip);
+        }
+        for ip in ["93.184.216.34", "198.20.0.1", "64:ff9b::5db8:d822", "2002:5db8:d822::1", "2606:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{} was refused", ip);
+        }
+    }
+}
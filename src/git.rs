@@ -0,0 +1,24 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// The nearest directory up from `cwd` holding .git (a directory, or a file in worktrees and submodules)
+pub fn toplevel(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors().find(|dir| dir.join(".git").exists()).map(Path::to_path_buf)
+}
+
+// True when `path` is tracked by git and has uncommitted changes
+pub fn is_dirty_tracked(cwd: &Path, path: &Path) -> bool {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(cwd)
+        .args(["status", "--porcelain", "--"])
+        .arg(path)
+        .output();
+
+    match output {
+        Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .any(|line| !line.starts_with("??") && !line.starts_with("!!")),
+        _ => false,
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+// The one Ctrl+C listener of the session. tokio keeps its SIGINT handler for the rest of the process once
+// one is installed, so every wait goes through here: Ctrl+C wakes whatever is waiting in cancelled(), and
+// with nothing waiting it exits the way the default handler would
+#[derive(Clone)]
+pub struct Interrupt {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    waiting: AtomicUsize,
+    sender: broadcast::Sender<()>,
+}
+
+// Counts a waiter for as long as its cancelled() future lives
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Interrupt {
+    // Must be called inside the runtime
+    pub fn listen() -> Self {
+        let (sender, _) = broadcast::channel(1);
+        let inner = Arc::new(Inner { waiting: AtomicUsize::new(0), sender });
+        let listener = inner.clone();
+        tokio::spawn(async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if listener.waiting.load(Ordering::SeqCst) == 0 {
+                    println!();
+                    std::process::exit(130);
+                }
+                listener.sender.send(()).ok();
+            }
+        });
+        Interrupt { inner }
+    }
+
+    // Resolves on the next Ctrl+C; meant as a tokio::select! branch next to the work it cancels
+    pub async fn cancelled(&self) {
+        let mut receiver = self.inner.sender.subscribe();
+        self.inner.waiting.fetch_add(1, Ordering::SeqCst);
+        let _waiting = Waiting(&self.inner.waiting);
+        receiver.recv().await.ok();
+    }
+}
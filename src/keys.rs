@@ -0,0 +1,109 @@
+use crate::paths;
+use keyring::Entry;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+// Secrets are stored under this service with the env var name as the user, so profile-specific
+// variable names (gemini_api_key_env) get their own entries
+const SERVICE: &str = "clio-ai";
+
+// Names of the variables put in the keyring, so startup only asks the keyring for those
+// (an unused keyring can otherwise cost a D-Bus timeout or a keychain prompt)
+fn index_path() -> Option<PathBuf> {
+    paths::state_dir().map(|dir| dir.join("keyring-keys"))
+}
+
+fn stored() -> Vec<String> {
+    index_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|text| text.lines().map(str::trim).filter(|l| !l.is_empty()).map(String::from).collect())
+        .unwrap_or_default()
+}
+
+// The Secret Service backend blocks on its own runtime, which panics inside tokio's, so keyring
+// calls get a thread of their own
+fn off_runtime<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    thread::scope(|scope| scope.spawn(f).join().expect("keyring thread panicked"))
+}
+
+pub fn get(var: &str) -> Option<String> {
+    if !stored().iter().any(|v| v == var) {
+        return None;
+    }
+    let entry = Entry::new(SERVICE, var).ok()?;
+    off_runtime(|| entry.get_password()).ok().filter(|secret| !secret.trim().is_empty())
+}
+
+pub fn set(var: &str, secret: &str) -> Result<(), String> {
+    let entry = Entry::new(SERVICE, var).map_err(|e| e.to_string())?;
+    off_runtime(|| entry.set_password(secret)).map_err(|e| e.to_string())?;
+    let mut names = stored();
+    if !names.iter().any(|v| v == var) {
+        names.push(var.to_string());
+        let path = index_path().ok_or("No state directory")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(&path, names.join("\n") + "\n").map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// Sets VAR=value in a .env file, replacing an existing assignment
+pub fn write_env(path: &Path, var: &str, value: &str) -> Result<(), String> {
+    let text = fs::read_to_string(path).unwrap_or_default();
+    let mut lines: Vec<String> = text.lines().filter(|line| !assigns(line, var)).map(String::from).collect();
+    lines.push(format!("{}={}", var, value));
+    replace_file(path, &(lines.join("\n") + "\n"))
+}
+
+// Drops the assignments of `vars` from a .env file, keeping everything else
+pub fn scrub_env(path: &Path, vars: &[String]) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let kept: Vec<&str> = text.lines().filter(|line| !vars.iter().any(|var| assigns(line, var))).collect();
+    replace_file(path, &(kept.join("\n") + "\n"))
+}
+
+fn assigns(line: &str, var: &str) -> bool {
+    let line = line.trim_start();
+    let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+    line.strip_prefix(var).is_some_and(|rest| rest.trim_start().starts_with('='))
+}
+
+// Owner-only whatever the file's mode was: it holds API keys
+fn replace_file(path: &Path, text: &str) -> Result<(), String> {
+    paths::write_atomic(path, text.as_bytes(), 0o600)
+}
+
+// Reads a secret without echo on a terminal, or a plain line when stdin is piped
+pub fn read_secret(prompt: &str) -> Result<String, String> {
+    let secret = if io::stdin().is_terminal() {
+        rpassword::prompt_password(prompt).map_err(|e| e.to_string())?
+    } else {
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line).map_err(|e| e.to_string())?;
+        line
+    };
+    Ok(secret.trim().to_string())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn env_file_with_keys_is_owner_only_and_keeps_other_lines() {
+        let dir = paths::test_dir("write-env");
+        let path = dir.join(".env");
+        fs::write(&path, "PORT=8080\nGROQ_API_KEY=old\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_env(&path, "GROQ_API_KEY", "gsk_new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "PORT=8080\nGROQ_API_KEY=gsk_new\n");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1, "temp file left behind");
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,7 +1,47 @@
-use crate::config::Config;
-use crate::tools::{ToolCall, ToolResponse};
+use crate::config::{Config, PROVIDERS};
+use crate::tools::{tool_docs, ToolCall, ToolResponse};
+use crate::sigv4;
+use crate::style;
+use reqwest::multipart::{Form, Part};
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use regex::Regex;
 use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::OnceLock;
+
+const DEFAULT_TEMPERATURE: f32 = 0.7;
+
+// Retries on HTTP 429 and 503 before giving up
+const MAX_RETRIES: u32 = 3;
+
+// The parts of an OpenAI-compatible chat completions API that differ between providers
+pub struct OpenAiCompatible {
+    pub name: &'static str,
+    pub base_url: &'static str,
+    // response_format json_object. Groq rejects replies that fail its JSON check with HTTP 400,
+    // where the response parser could still have repaired them, so it stays off there
+    json_mode: bool,
+}
+
+pub const GROQ: OpenAiCompatible = OpenAiCompatible {
+    name: "Groq",
+    base_url: "https://api.groq.com/openai/v1",
+    json_mode: false,
+};
+
+pub const DEEPSEEK: OpenAiCompatible = OpenAiCompatible {
+    name: "DeepSeek",
+    base_url: "https://api.deepseek.com",
+    json_mode: true,
+};
+
+const HUGGINGFACE: OpenAiCompatible = OpenAiCompatible {
+    name: "Hugging Face",
+    base_url: "https://router.huggingface.co/v1",
+    json_mode: false,
+};
 
 const SYSTEM_PROMPT: &str = r#"You are an AI assistant that performs file system operations. You MUST respond with ONLY valid JSON.
 
@@ -16,13 +56,10 @@ CRITICAL RULES:
 8. Create ALL required files for complete projects; do NOT create unrelated files or scaffolding for other languages/frameworks. If a language or framework is specified, only create files for that stack.
 9. Return ONLY the JSON object, nothing else
 10. ONLY use the tool actions listed below. Never use actions like cd, run, exec, shell, or help.
+11. Each tool object MAY include an optional short "reason" field explaining why you are doing it, and an optional "note" (e.g. "created with Python 3.11 syntax") that is handed back to you with its result.
 
 TOOLS:
-- {"action": "create_file", "path": "file.txt", "content": "file content"}
-- {"action": "create_folder", "path": "folder"}
-- {"action": "read_file", "path": "file.txt"}
-- {"action": "delete", "path": "file.txt"}
-- {"action": "list_dir", "path": "."}
+{tools}
 
 EXAMPLES:
 
@@ -44,6 +81,43 @@ User: hi how are you
 Current directory: {cwd}
 RESPOND WITH ONLY JSON. NO MARKDOWN. NO EXPLANATIONS."#;
 
+// Drops the EXAMPLES section (up to the "Current directory" line) for --lite
+fn strip_examples(prompt: &str) -> String {
+    match (prompt.find("EXAMPLES:"), prompt.find("Current directory:")) {
+        (Some(start), Some(end)) if start < end => format!("{}{}", &prompt[..start], &prompt[end..]),
+        _ => prompt.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl Usage {
+    fn from_value(json: &Value, input: &str, output: &str) -> Self {
+        Self {
+            input_tokens: json.pointer(input).and_then(|v| v.as_u64()).unwrap_or(0),
+            output_tokens: json.pointer(output).and_then(|v| v.as_u64()).unwrap_or(0),
+        }
+    }
+
+    pub fn add(&mut self, other: Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+// Which provider/model actually produced a reply, and what it cost
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatMeta {
+    pub provider: String,
+    pub model: String,
+    pub usage: Usage,
+}
+
+#[allow(clippy::upper_case_acronyms)]
 pub struct LLM {
     client: Client,
     config: Config,
@@ -57,23 +131,42 @@ impl LLM {
         }
     }
 
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
     pub fn set_model(&mut self, model: &str) {
         self.config.model = model.to_string();
-        // Auto-detect provider
-        if model.starts_with("gemini") {
-            self.config.provider = "gemini".into();
-        } else if model.starts_with("compound") || model.starts_with("meta-llama") || model.starts_with("llama-") {
-            self.config.provider = "groq".into();
-        } else if model.contains("llama3") || model == "llama3.2" {
-            self.config.provider = "ollama".into();
-        } else {
-            // Default to ollama for unknown models
-            self.config.provider = "ollama".into();
+        self.config.provider = self.config.provider_for(model);
+    }
+
+    // Switches provider and moves to its default model; returns that model
+    pub fn set_provider(&mut self, provider: &str) -> Result<String, String> {
+        if !PROVIDERS.contains(&provider) {
+            return Err(format!("Unknown provider '{}' (one of: {})", provider, PROVIDERS.join(", ")));
         }
+        let model = self
+            .config
+            .default_model(provider)
+            .ok_or_else(|| format!("No default model for {}; use /model <name>", provider))?;
+        self.config.provider = provider.to_string();
+        self.config.model = model.clone();
+        Ok(model)
     }
 
-    pub async fn chat(&self, prompt: &str, cwd: &str, tool_results: Option<&str>, repo_context: Option<&str>) -> Result<ToolResponse, String> {
-        let system = SYSTEM_PROMPT.replace("{cwd}", cwd);
+    pub async fn chat(&self, prompt: &str, cwd: &str, tool_results: Option<&str>, repo_context: Option<&str>) -> Result<(ToolResponse, ChatMeta), String> {
+        let system = self
+            .config
+            .system_prompt
+            .as_deref()
+            .unwrap_or(SYSTEM_PROMPT)
+            .replace("{tools}", &tool_docs(&self.config.blocked_actions))
+            .replace("{cwd}", cwd);
+        let system = if self.config.lite { strip_examples(&system) } else { system };
         let user_msg = if let Some(results) = tool_results {
             format!(
                 "Tool results:\n{}\n\nOriginal request: {}\n\nBased on these results, provide final response or more tool calls.",
@@ -86,28 +179,61 @@ impl LLM {
             prompt.to_string()
         };
 
-        let response = match self.config.provider.as_str() {
-            "gemini" => self.call_gemini(&system, &user_msg).await?,
-            "groq" => self.call_groq(&system, &user_msg).await?,
-            "ollama" => self.call_ollama(&system, &user_msg).await?,
-            _ => return Err("Unknown provider".into()),
+        let (response, usage) = self.complete(&system, &user_msg).await?;
+
+        let meta = ChatMeta {
+            provider: self.config.provider.clone(),
+            model: self.config.model.clone(),
+            usage,
         };
+        Ok((self.parse_response(&response)?, meta))
+    }
 
-        self.parse_response(&response)
+    // One raw request to the active provider, bounded by request_timeout
+    async fn complete(&self, system: &str, user_msg: &str) -> Result<(String, Usage), String> {
+        let call = async {
+            match self.config.provider.as_str() {
+                "gemini" => self.call_gemini(system, user_msg).await,
+                "groq" => self.call_groq(system, user_msg).await,
+                "deepseek" => self.call_deepseek(system, user_msg).await,
+                "huggingface" => self.call_huggingface(system, user_msg).await,
+                "bedrock" => self.call_bedrock(system, user_msg).await,
+                "ollama" => self.call_ollama(system, user_msg).await,
+                _ => Err("Unknown provider".into()),
+            }
+        };
+        let timeout = self.config.limits.request_timeout;
+        tokio::time::timeout(std::time::Duration::from_secs(timeout as u64), call)
+            .await
+            .map_err(|_| format!("No answer within {}s (request_timeout)", timeout))?
+    }
+
+    // A minimal round-trip that expects {"response": "ok"} back. The prompt says JSON because DeepSeek's
+    // JSON mode refuses prompts without that word
+    pub async fn test_connection(&self) -> Result<(), String> {
+        let (reply, _) = self.complete(r#"Reply with exactly this JSON: {"response": "ok"}"#, "respond with: ok").await?;
+        if is_ok_reply(&reply) {
+            return Ok(());
+        }
+        let reply: String = sanitize_response(&reply).trim().chars().take(80).collect();
+        Err(format!("unexpected reply {:?}", reply))
     }
 
-    async fn call_gemini(&self, system: &str, user: &str) -> Result<String, String> {
+    async fn call_gemini(&self, system: &str, user: &str) -> Result<(String, Usage), String> {
         let api_key = self.config.gemini_api_key.as_ref().ok_or("GEMINI_API_KEY not set")?;
         let url = format!(
             "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
             self.config.model, api_key
         );
 
-        let body = json!({
+        let mut body = json!({
             "system_instruction": {"parts": [{"text": system}]},
             "contents": [{"parts": [{"text": user}]}],
-            "generationConfig": {"temperature": 0.7}
+            "generationConfig": {"temperature": self.config.temperature.unwrap_or(DEFAULT_TEMPERATURE)}
         });
+        if let Some(max_tokens) = self.config.max_tokens {
+            body["generationConfig"]["maxOutputTokens"] = json!(max_tokens);
+        }
 
         let resp = self.client.post(&url).json(&body).send().await.map_err(|e| e.to_string())?;
         let status = resp.status();
@@ -122,28 +248,113 @@ impl LLM {
             return Err(format!("Gemini error: {}", message));
         }
 
+        let usage = Usage::from_value(&json, "/usageMetadata/promptTokenCount", "/usageMetadata/candidatesTokenCount");
         json["candidates"][0]["content"]["parts"][0]["text"]
             .as_str()
-            .map(|s| s.to_string())
+            .map(|s| (s.to_string(), usage))
             .ok_or_else(|| format!("No response from Gemini: {}", json))
     }
 
-    async fn call_groq(&self, system: &str, user: &str) -> Result<String, String> {
+    async fn call_groq(&self, system: &str, user: &str) -> Result<(String, Usage), String> {
         let api_key = self.config.groq_api_key.as_ref().ok_or("GROQ_API_KEY not set")?;
+        self.call_openai_compatible(&GROQ, api_key, system, user).await
+    }
+
+    async fn call_deepseek(&self, system: &str, user: &str) -> Result<(String, Usage), String> {
+        let api_key = self.config.deepseek_api_key.as_ref().ok_or("DEEPSEEK_API_KEY not set")?;
+        self.call_openai_compatible(&DEEPSEEK, api_key, system, user).await
+    }
+
+    async fn call_huggingface(&self, system: &str, user: &str) -> Result<(String, Usage), String> {
+        let api_key = self.config.hf_api_key.as_ref().ok_or("HF_API_KEY not set")?;
+        self.call_openai_compatible(&HUGGINGFACE, api_key, system, user).await
+    }
 
-        let body = json!({
+    // OpenAI-style chat completions request, shared by every provider that speaks it
+    async fn call_openai_compatible(&self, provider: &OpenAiCompatible, api_key: &str, system: &str, user: &str) -> Result<(String, Usage), String> {
+        let name = provider.name;
+        let mut body = json!({
             "model": self.config.model,
             "messages": [
                 {"role": "system", "content": system},
                 {"role": "user", "content": user}
             ],
-            "temperature": 0.7
+            "temperature": self.config.temperature.unwrap_or(DEFAULT_TEMPERATURE)
         });
+        if provider.json_mode {
+            body["response_format"] = json!({"type": "json_object"});
+        }
+        if let Some(max_tokens) = self.config.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+
+        let mut attempts = 0;
+        loop {
+            let resp = self.client
+                .post(format!("{}/chat/completions", provider.base_url))
+                .header("Authorization", format!("Bearer {}", api_key))
+                .json(&body)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+
+            let status = resp.status();
+            let retry_after = resp
+                .headers()
+                .get("retry-after")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<f64>().ok());
+            let text = resp.text().await.map_err(|e| e.to_string())?;
+
+            // Rate limits send Retry-After; cold Hugging Face models answer 503 with an estimated_time (seconds)
+            if matches!(status.as_u16(), 429 | 503) && attempts < MAX_RETRIES {
+                let estimated = serde_json::from_str::<Value>(&text).ok().and_then(|v| v["estimated_time"].as_f64());
+                let wait = retry_after.or(estimated).unwrap_or(10.0).clamp(1.0, 60.0);
+                attempts += 1;
+                if estimated.is_some() {
+                    info!("  {} model is loading, retrying in {:.0}s...", name, wait);
+                } else {
+                    info!("  {} answered HTTP {}, retrying in {:.0}s...", name, status.as_u16(), wait);
+                }
+                tokio::time::sleep(std::time::Duration::from_secs_f64(wait)).await;
+                continue;
+            }
+
+            if !status.is_success() {
+                return Err(format!("{} error: HTTP {}: {}", name, status, text));
+            }
+
+            let json: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+            if let Some(message) = json.pointer("/error/message").and_then(|v| v.as_str()) {
+                return Err(format!("{} error: {}", name, message));
+            }
+
+            let usage = Usage::from_value(&json, "/usage/prompt_tokens", "/usage/completion_tokens");
+            return json["choices"][0]["message"]["content"]
+                .as_str()
+                .map(|s| (s.to_string(), usage))
+                .ok_or_else(|| format!("No response from {}: {}", name, json));
+        }
+    }
+
+    pub async fn transcribe_audio(&self, file_path: &Path) -> Result<String, String> {
+        let api_key = self.config.groq_api_key.as_ref().ok_or("GROQ_API_KEY not set")?;
+        let bytes = tokio::fs::read(file_path)
+            .await
+            .map_err(|e| format!("Cannot read {}: {}", file_path.display(), e))?;
+        let file_name = file_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "audio".into());
+
+        let form = Form::new()
+            .text("model", "whisper-large-v3")
+            .part("file", Part::bytes(bytes).file_name(file_name));
 
         let resp = self.client
-            .post("https://api.groq.com/openai/v1/chat/completions")
+            .post(format!("{}/audio/transcriptions", GROQ.base_url))
             .header("Authorization", format!("Bearer {}", api_key))
-            .json(&body)
+            .multipart(form)
             .send()
             .await
             .map_err(|e| e.to_string())?;
@@ -151,37 +362,95 @@ impl LLM {
         let status = resp.status();
         let text = resp.text().await.map_err(|e| e.to_string())?;
         if !status.is_success() {
-            return Err(format!("Groq error: HTTP {}: {}", status, text));
+            return Err(format!("Groq transcription error: HTTP {}: {}", status, text));
         }
 
         let json: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
-        if let Some(message) = json.pointer("/error/message").and_then(|v| v.as_str()) {
-            return Err(format!("Groq error: {}", message));
+        json["text"]
+            .as_str()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| format!("No transcript from Groq: {}", json))
+    }
+
+    // Uses the Converse API so one request shape covers Claude, Llama, Mistral, Titan, ...
+    async fn call_bedrock(&self, system: &str, user: &str) -> Result<(String, Usage), String> {
+        let creds = sigv4::Credentials {
+            access_key: self.config.aws_access_key_id.clone().ok_or("AWS_ACCESS_KEY_ID not set")?,
+            secret_key: self.config.aws_secret_access_key.clone().ok_or("AWS_SECRET_ACCESS_KEY not set")?,
+            session_token: self.config.aws_session_token.clone(),
+        };
+        let region = &self.config.aws_region;
+        let host = format!("bedrock-runtime.{}.amazonaws.com", region);
+        // Model ids contain ':' (e.g. "...-v2:0"), which must be percent-encoded in the path
+        let path = format!("/model/{}/converse", sigv4::uri_encode(&self.config.model));
+
+        let mut body = json!({
+            "system": [{"text": system}],
+            "messages": [{"role": "user", "content": [{"text": user}]}],
+            "inferenceConfig": {"temperature": self.config.temperature.unwrap_or(DEFAULT_TEMPERATURE)}
+        });
+        if let Some(max_tokens) = self.config.max_tokens {
+            body["inferenceConfig"]["maxTokens"] = json!(max_tokens);
         }
+        let body = serde_json::to_vec(&body).map_err(|e| e.to_string())?;
 
-        json["choices"][0]["message"]["content"]
-            .as_str()
-            .map(|s| s.to_string())
-            .ok_or_else(|| format!("No response from Groq: {}", json))
+        let mut request = self.client.post(format!("https://{}{}", host, path));
+        for (name, value) in sigv4::sign(&host, &path, &body, region, "bedrock", &creds, chrono::Utc::now()) {
+            request = request.header(name, value);
+        }
+        let resp = request.body(body).send().await.map_err(|e| format!("Bedrock connection error: {}", e))?;
+        let status = resp.status();
+        let text = resp.text().await.map_err(|e| e.to_string())?;
+        let json: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+
+        if !status.is_success() {
+            let message = json["message"].as_str().or(json["Message"].as_str()).unwrap_or(&text);
+            return Err(format!("Bedrock error: HTTP {}: {}", status, message));
+        }
+
+        let content = json
+            .pointer("/output/message/content")
+            .and_then(|c| c.as_array())
+            .map(|parts| parts.iter().filter_map(|p| p["text"].as_str()).collect::<String>())
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| format!("Invalid Bedrock response format: {}", text))?;
+        Ok((content, Usage::from_value(&json, "/usage/inputTokens", "/usage/outputTokens")))
     }
 
-    async fn call_ollama(&self, system: &str, user: &str) -> Result<String, String> {
+    async fn call_ollama(&self, system: &str, user: &str) -> Result<(String, Usage), String> {
         let url = self.config.ollama_url.as_ref().map(|u| format!("{}/api/generate", u))
             .unwrap_or("http://localhost:11434/api/generate".into());
 
-        let body = json!({
+        let mut body = json!({
             "model": self.config.model,
             "prompt": user,
             "system": system,
-            "stream": false
+            "stream": self.config.stream
         });
+        // Ollama models ship tuned defaults; only override when explicitly configured
+        if let Some(temperature) = self.config.temperature {
+            body["options"]["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = self.config.max_tokens {
+            body["options"]["num_predict"] = json!(max_tokens);
+        }
 
         let resp = self.client.post(&url).json(&body).send().await
             .map_err(|e| format!("Ollama connection error: {}", e))?;
         
         let status = resp.status();
         if !status.is_success() {
-            return Err(format!("Ollama error: HTTP {}", status));
+            // e.g. 404 {"error": "model \"x\" not found, try pulling it first"}
+            let body: Value = resp.json().await.unwrap_or_default();
+            return Err(match body["error"].as_str() {
+                Some(error) => format!("Ollama error: HTTP {}: {}", status, error),
+                None => format!("Ollama error: HTTP {}", status),
+            });
+        }
+
+        if self.config.stream {
+            return Self::read_ollama_stream(resp).await;
         }
         
         let json: Value = resp.json().await.map_err(|e| format!("Ollama parse error: {}", e))?;
@@ -190,18 +459,78 @@ impl LLM {
             if response_text.is_empty() {
                 return Err("Ollama returned empty response".into());
             }
-            Ok(response_text.to_string())
+            Ok((response_text.to_string(), Usage::from_value(&json, "/prompt_eval_count", "/eval_count")))
         } else {
             Err(format!("Invalid Ollama response format: {:?}", json))
         }
     }
 
+    // Ollama streams newline-delimited JSON objects, each carrying a "response" fragment
+    async fn read_ollama_stream(mut resp: reqwest::Response) -> Result<(String, Usage), String> {
+        let mut text = String::new();
+        let mut usage = Usage::default();
+        let mut pending: Vec<u8> = Vec::new();
+
+        loop {
+            let chunk = resp.chunk().await.map_err(|e| format!("Ollama stream error: {}", e))?;
+            let done = chunk.is_none();
+            if let Some(chunk) = chunk {
+                pending.extend_from_slice(&chunk);
+            } else if !pending.is_empty() {
+                pending.push(b'\n');
+            }
+
+            while let Some(pos) = pending.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line);
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let json: Value = serde_json::from_str(line.trim())
+                    .map_err(|e| format!("Ollama parse error: {}", e))?;
+                if let Some(err) = json["error"].as_str() {
+                    return Err(format!("Ollama error: {}", err));
+                }
+                if let Some(fragment) = json["response"].as_str() {
+                    crate::output::progress(&style::dim(fragment));
+                    text.push_str(fragment);
+                }
+                if json["done"].as_bool() == Some(true) {
+                    usage = Usage::from_value(&json, "/prompt_eval_count", "/eval_count");
+                }
+            }
+
+            if done {
+                break;
+            }
+        }
+
+        if !text.is_empty() {
+            info!();
+        }
+        if text.trim().is_empty() {
+            return Err("Ollama returned empty response".into());
+        }
+        Ok((text, usage))
+    }
+
     fn parse_response(&self, text: &str) -> Result<ToolResponse, String> {
-        let text = text.trim();
+        let text = sanitize_response(text);
+        let text = text.as_ref();
         
         if let Some(resp) = parse_tool_response(text) {
             return Ok(resp);
         }
+        if self.config.strict_json {
+            return Ok(ToolResponse { tools: None, response: Some(text.to_string()) });
+        }
+
+        // Smaller models sometimes answer in YAML when asked for structured output
+        if let Ok(value) = serde_yaml::from_str::<Value>(strip_fences(text)) {
+            if let Some(resp) = tool_response_from_value(value) {
+                return Ok(resp);
+            }
+        }
 
         // Try to extract code blocks and create files
         let mut tools = Vec::new();
@@ -226,6 +555,7 @@ impl LLM {
                         action: "create_file".into(),
                         path: Some(filename),
                         content: Some(content.trim_end().to_string()),
+                        ..Default::default()
                     });
                 }
             }
@@ -241,7 +571,19 @@ impl LLM {
     }
 }
 
+// Strips what some models put around their JSON: a UTF-8 BOM, ANSI color codes and carriage returns
+fn sanitize_response(text: &str) -> Cow<'_, str> {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    let text = text.trim().trim_start_matches('\u{feff}').trim();
+    if !text.contains(['\x1b', '\r']) {
+        return Cow::Borrowed(text);
+    }
+    let ansi = ANSI.get_or_init(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap());
+    Cow::Owned(ansi.replace_all(text, "").replace('\r', "").trim().to_string())
+}
+
 fn parse_tool_response(text: &str) -> Option<ToolResponse> {
+    let text = strip_fences(text);
     if let Ok(value) = serde_json::from_str::<Value>(text) {
         if let Some(resp) = tool_response_from_value(value) {
             return Some(resp);
@@ -259,10 +601,59 @@ fn parse_tool_response(text: &str) -> Option<ToolResponse> {
     None
 }
 
+// An error page or refusal that merely mentions "ok" (say "token not ok") is not a working connection
+fn is_ok_reply(reply: &str) -> bool {
+    parse_tool_response(reply).and_then(|r| r.response).is_some_and(|text| text.trim().eq_ignore_ascii_case("ok"))
+}
+
+// Auto-detect provider from a model id
+pub fn detect_provider(model: &str) -> &'static str {
+    if model.starts_with("gemini") {
+        "gemini"
+    } else if is_bedrock_model(model) {
+        "bedrock"
+    } else if model.starts_with("deepseek-") && !model.contains('/') {
+        // Not "deepseek-ai/...", which is a Hugging Face id
+        "deepseek"
+    } else if model.starts_with("compound") || model.starts_with("meta-llama") || model.starts_with("llama-") {
+        "groq"
+    } else if model.contains('/') {
+        // Hugging Face ids are namespaced as org/model
+        "huggingface"
+    } else {
+        // Default to ollama for unknown models
+        "ollama"
+    }
+}
+
+// Bedrock ids look like "anthropic.claude-...", optionally with a cross-region prefix ("us.anthropic...")
+fn is_bedrock_model(model: &str) -> bool {
+    const VENDORS: &[&str] = &["anthropic.", "amazon.", "meta.", "mistral.", "cohere.", "ai21.", "deepseek."];
+    let unprefixed = match model.split_once('.') {
+        Some(("us" | "eu" | "apac", rest)) => rest,
+        _ => model,
+    };
+    VENDORS.iter().any(|v| unprefixed.starts_with(v))
+}
+
+// Remove a wrapping ``` / ~~~ fence (with optional language tag, possibly indented)
+pub fn strip_fences(text: &str) -> &str {
+    let text = text.trim();
+    let Some(fence) = ["```", "~~~"].into_iter().find(|f| text.starts_with(f)) else {
+        return text;
+    };
+    // Everything up to the first newline is the fence plus language tag ("```json", "``` JSON")
+    let Some((_, body)) = text.split_once('\n') else {
+        return text.trim_start_matches(fence).trim_end_matches(fence).trim();
+    };
+    let body = body.trim_end();
+    body.strip_suffix(fence).unwrap_or(body).trim()
+}
+
 fn tool_response_from_value(value: Value) -> Option<ToolResponse> {
     if let Ok(resp) = serde_json::from_value::<ToolResponse>(value.clone()) {
-        let has_tools = resp.tools.as_ref().map_or(false, |tools| !tools.is_empty());
-        let has_response = resp.response.as_ref().map_or(false, |r| !r.trim().is_empty());
+        let has_tools = resp.tools.as_ref().is_some_and(|tools| !tools.is_empty());
+        let has_response = resp.response.as_ref().is_some_and(|r| !r.trim().is_empty());
         if has_tools || has_response {
             return Some(resp);
         }
@@ -390,3 +781,43 @@ fn extract_filename(line: &str) -> Option<String> {
     
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"{"tools": [{"action": "list_dir", "path": "."}]}"#;
+
+    #[test]
+    fn strip_fences_handles_the_styles_models_produce() {
+        let fenced = [
+            format!("```json\n{}\n```", JSON),
+            format!("``` JSON\n{}\n```", JSON),
+            format!("```\n{}\n```", JSON),
+            format!("~~~json\n{}\n~~~", JSON),
+            format!("\n\n   ```json\n   {}\n   ```\n", JSON),
+            format!("```json\r\n{}\r\n```\r\n", JSON),
+            format!("```{}```", JSON),
+            format!("```json\n{}", JSON),
+            JSON.to_string(),
+        ];
+        for text in &fenced {
+            assert_eq!(strip_fences(text), JSON, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn fenced_replies_parse_as_tool_calls() {
+        let response = parse_tool_response(&format!("  ```json\n{}\n  ```", JSON)).unwrap();
+        assert_eq!(response.tools.unwrap()[0].action, "list_dir");
+    }
+
+    #[test]
+    fn connection_test_wants_ok_as_the_whole_response() {
+        assert!(is_ok_reply(r#"{"response": "ok"}"#));
+        assert!(is_ok_reply("```json\n{\"response\": \" OK \"}\n```"));
+        assert!(!is_ok_reply("ok"));
+        assert!(!is_ok_reply(r#"{"response": "token not ok"}"#));
+        assert!(!is_ok_reply(r#"{"error": "invalid api key, look up the docs"}"#));
+    }
+}
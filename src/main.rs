@@ -1,106 +1,676 @@
+#[macro_use]
+mod output;
+
+mod clioignore;
+mod commands;
+mod completer;
+mod completions;
 mod config;
+mod config_file;
+mod dir_models;
+mod fetch;
+mod git;
+mod interrupt;
+mod keys;
 mod llm;
+mod models;
+mod notify;
+mod outline;
+mod patch;
+mod paths;
+mod policy;
+mod project;
+mod pull;
+mod rate_limit;
+mod readiness;
+mod search;
+mod server;
+mod session_file;
+mod sigv4;
+mod stats;
+mod style;
 mod tools;
+mod transcript;
+mod trust;
+mod undo;
+mod version;
+mod watch;
+mod wizard;
 
-use config::{Config, MODELS};
+use clap::{Parser, Subcommand};
+use config::Config;
 use llm::LLM;
-use rustyline::DefaultEditor;
+use policy::Decision;
+use project::ProjectType;
+use rate_limit::RateLimiter;
+use stats::{PromptStats, SessionStats};
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+use std::collections::HashMap;
 use std::env;
-use tools::{execute_tool, is_supported_action, ToolCall, ToolResult};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tools::{available_actions, execute_tool_async, is_supported_action, mutated_paths, resolve_path, ToolCall, ToolResult};
+use transcript::{ToolRecord, Turn};
+use interrupt::Interrupt;
+use undo::{Change, Snapshot, UndoStack};
+
+const HISTORY_SIZE: usize = 1000;
+
+struct Session {
+    llm: LLM,
+    cwd: PathBuf,
+    // Detected at startup and on /cd; the tool root when PATHS_FROM_GIT_ROOT is set
+    git_root: Option<PathBuf>,
+    undo: UndoStack,
+    last_response: Option<String>,
+    transcript: Vec<Turn>,
+    rate_limiter: RateLimiter,
+    last_stats: Option<PromptStats>,
+    stats: SessionStats,
+    // Text queued by /grep --attach, prepended to the next prompt
+    attachment: Option<String>,
+    // Most recent first, for completion
+    recent_files: Vec<String>,
+    // Transcripts saved with /branch, and the one currently checked out
+    branches: HashMap<String, Vec<Turn>>,
+    branch: Option<String>,
+    // Every Ctrl+C wait goes through this one listener
+    interrupt: Interrupt,
+    // Opened by the first /copy and kept: on Linux the copied text is only served while it is alive
+    clipboard: Option<arboard::Clipboard>,
+    // False in server mode, where a question on the server's console would stall the client's request
+    interactive: bool,
+}
+
+impl Session {
+    // Where the model's paths are resolved and contained: the working directory, or its git root
+    fn tool_root(&self) -> PathBuf {
+        match &self.git_root {
+            Some(root) if self.llm.config().paths_from_git_root => root.clone(),
+            _ => self.cwd.clone(),
+        }
+    }
+
+    // Whether a question can be asked and answered: a terminal is attached and no client is waiting
+    fn can_ask(&self) -> bool {
+        self.interactive && io::stdin().is_terminal()
+    }
+}
+
+#[derive(Parser)]
+#[command(name = "clio-ai", version = version::LONG, about = "A local-first AI CLI that can read and edit your project files")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+
+    /// Config profile to use, a [profile.NAME] section of config.toml (also CLIO_PROFILE)
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Model to start with (provider is detected from the id)
+    #[arg(long, short)]
+    model: Option<String>,
+
+    /// Maximum files/folders a single prompt may create
+    #[arg(long, value_name = "N")]
+    max_files: Option<usize>,
+
+    /// Print only the final response (also QUIET=true)
+    #[arg(long, short)]
+    quiet: bool,
+
+    /// Disable colored output (also honors NO_COLOR)
+    #[arg(long)]
+    no_color: bool,
+
+    /// Minimal context to save tokens: no repo context or prompt examples, 3 iterations, short tool results
+    #[arg(long)]
+    lite: bool,
+
+    /// Approve every confirmation without asking (CONFIRM actions, rewrites, multi-file deletes)
+    #[arg(long, short)]
+    yes: bool,
+
+    /// Skip the startup provider/config readiness check
+    #[arg(long)]
+    no_check: bool,
+
+    /// Skip the startup connection test enabled by CLIO_TEST_CONNECTION
+    #[arg(long)]
+    no_test_connection: bool,
+
+    /// Don't offer the first-run setup wizard
+    #[arg(long)]
+    no_wizard: bool,
+
+    /// Restore the last auto-saved session of this project
+    #[arg(long)]
+    resume: bool,
+
+    /// Serve JSON-RPC prompts for editor integrations on a Unix socket or Windows named pipe
+    /// (default $XDG_RUNTIME_DIR/clio-ai/clio-ai.sock or \\.\pipe\clio-ai)
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "")]
+    server: Option<String>,
+
+    /// Watch files matching GLOB and run the watch prompt when they change
+    #[arg(long, value_name = "GLOB")]
+    watch: Option<String>,
+
+    /// Prompt to run in --watch mode; {files} is replaced with the changed paths
+    #[arg(long, value_name = "PROMPT")]
+    watch_prompt: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// Print a shell completion script
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Run the setup wizard (provider, key, model, safety), or write an example config with --example
+    Init {
+        /// Write the commented example config.toml instead of asking questions
+        #[arg(long)]
+        example: bool,
+        /// With --example, overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+}
 
 #[tokio::main]
 async fn main() {
-    let config = Config::load();
-    let mut llm = LLM::new(config.clone());
-    let cwd = env::current_dir().unwrap();
-    let cwd_str = cwd.to_string_lossy().to_string();
+    let cli = Cli::parse();
+    style::init(cli.no_color);
+
+    match cli.command {
+        Some(CliCommand::Completions { shell }) => {
+            completions::print(shell);
+            return;
+        }
+        Some(CliCommand::Init { example: false, .. }) if io::stdin().is_terminal() => {
+            let config = Config::load(cli.profile.as_deref(), true);
+            if let Err(e) = wizard::run(&config).await {
+                eprintln!("{} {}", style::red("Error:"), e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(CliCommand::Init { force, .. }) => {
+            match config_file::init(force) {
+                Ok(path) => println!("Wrote {}", path.display()),
+                Err(e) => {
+                    eprintln!("{} {}", style::red("Error:"), e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        None => {}
+    }
+
+    let mut config = Config::load(cli.profile.as_deref(), cli.server.is_none());
+    let interactive = cli.watch.is_none() && cli.server.is_none() && io::stdin().is_terminal() && io::stdout().is_terminal();
+    if interactive && !cli.no_wizard && wizard::is_first_run(&config) {
+        match wizard::run(&config).await {
+            Ok(true) => config = Config::load(cli.profile.as_deref(), true),
+            Ok(false) => {}
+            Err(e) => warn!("{} {}", style::red("Setup failed:"), e),
+        }
+    }
+    if cli.profile.is_some() {
+        config.set_source("profile", config::Source::Cli("--profile"));
+    }
+    output::set_quiet(cli.quiet || config.quiet);
+    ASSUME_YES.store(cli.yes, Ordering::Relaxed);
+    set_auto_approve(config.auto_approve);
+    let mut session = Session {
+        llm: LLM::new(config.clone()),
+        cwd: env::current_dir().unwrap(),
+        git_root: env::current_dir().ok().and_then(|cwd| git::toplevel(&cwd)),
+        undo: UndoStack::default(),
+        last_response: None,
+        transcript: Vec::new(),
+        rate_limiter: RateLimiter::new(config.tool_rate_limit),
+        last_stats: None,
+        stats: SessionStats::default(),
+        attachment: None,
+        recent_files: Vec::new(),
+        branches: HashMap::new(),
+        branch: None,
+        interrupt: Interrupt::listen(),
+        clipboard: None,
+        interactive: cli.server.is_none(),
+    };
+    if let Some(model) = cli.model.as_deref() {
+        session.llm.set_model(model);
+        session.llm.config_mut().set_source("model", config::Source::Cli("--model"));
+        session.llm.config_mut().set_source("provider", config::Source::Cli("--model"));
+    }
+    if let Some(max_files) = cli.max_files {
+        session.llm.config_mut().max_files = max_files;
+        session.llm.config_mut().set_source("max_files", config::Source::Cli("--max-files"));
+    }
+    if cli.lite {
+        session.llm.config_mut().set_lite(config::Source::Cli("--lite"));
+    }
+    if cli.quiet {
+        session.llm.config_mut().quiet = true;
+        session.llm.config_mut().set_source("quiet", config::Source::Cli("--quiet"));
+    }
 
-    println!("clio-ai v0.1.0 | Model: {} | /help for commands", config.model);
+    if cli.resume && !resume_session(&mut session) {
+        return;
+    }
 
-    let mut rl = DefaultEditor::new().unwrap();
+    if let Some(notice) = paths::legacy_notice() {
+        warn!("{} {}", style::yellow("Note:"), notice);
+    }
+    commands::print_project_notice(session.llm.config());
+    commands::recall_dir_model(&mut session);
+    if session.tool_root() != session.cwd {
+        info!("{} {}", style::dim("Tool paths are relative to the git root"), session.tool_root().display());
+    }
+
+    // Errors stop the non-interactive watch and server modes; the REPL only reports them so they can be fixed in-session
+    let problems = session.llm.config().validate();
+    let has_errors = readiness::print_problems(session.llm.config(), &problems);
+    if has_errors && (cli.watch.is_some() || cli.server.is_some()) {
+        std::process::exit(2);
+    }
+
+    if let Some(address) = cli.server.as_deref() {
+        if let Err(e) = server::run(&mut session, address).await {
+            eprintln!("{} {}", style::red("Error:"), e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(pattern) = cli.watch.as_deref() {
+        let prompt = cli.watch_prompt.clone().unwrap_or(config.watch_prompt.clone());
+        if let Err(e) = watch::run(&mut session, pattern, &prompt).await {
+            eprintln!("{} {}", style::red("Error:"), e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if session.llm.config().test_connection && !cli.no_test_connection && !has_errors {
+        readiness::test_connection(&session.llm).await;
+    }
+
+    info!(
+        "{}{} | Model: {}{} | /help for commands",
+        style::bold(&format!("clio-ai v{}", version::VERSION)),
+        match &session.llm.config().profile {
+            Some(profile) => format!(" | Profile: {}", style::cyan(profile)),
+            None => String::new(),
+        },
+        session.llm.config().model,
+        if session.llm.config().lite { format!(" | {}", style::yellow("lite")) } else { String::new() }
+    );
+
+    if !cli.no_check {
+        let config = session.llm.config();
+        // The probe costs up to 500 ms, so only when Ollama is the provider in use
+        let ollama = match config.provider.as_str() {
+            "ollama" => Some(readiness::ping_ollama(config).await),
+            _ => None,
+        };
+        let report = readiness::report(config, ollama);
+        for line in &report.lines {
+            info!("{}", style::dim(line));
+        }
+        if let Some(problem) = report.problem.as_ref().filter(|_| !has_errors) {
+            warn!("{} {}", style::yellow("Warning:"), problem);
+        }
+    }
+
+    // Runs in the background; the result is reported before the next prompt
+    let mut update_check = session
+        .llm
+        .config()
+        .update_check
+        .then(|| tokio::spawn(version::check_for_update()));
+
+    // Bracketed paste keeps a pasted block in the buffer until Enter is pressed
+    let rl_config = rustyline::Config::builder()
+        .bracketed_paste(true)
+        .max_history_size(HISTORY_SIZE)
+        .and_then(|builder| builder.history_ignore_dups(true))
+        .unwrap()
+        .build();
+    let mut rl: Editor<completer::ClioHelper, DefaultHistory> = Editor::with_config(rl_config).unwrap();
+    rl.set_helper(Some(completer::ClioHelper::default()));
+    sync_helper(&mut rl, &session);
+    let history_file = session.llm.config().history_file.clone();
+    if let Some(path) = history_file.as_deref().filter(|p| p.exists()) {
+        if let Err(e) = rl.load_history(path) {
+            warn!("Warning: cannot read history {}: {}", path.display(), e);
+        }
+    }
+    // Set after a Ctrl+C at an empty prompt; a second consecutive one exits
+    let mut interrupted = false;
+    // CLIO_DEFAULT_PROMPT goes through the same path as typed input, so it may also be a /command
+    let mut pending = session.llm.config().default_prompt.clone();
 
     loop {
-        let readline = rl.readline(">>> ");
+        if let Some(handle) = update_check.take_if(|handle| handle.is_finished()) {
+            if let Ok(Some(latest)) = handle.await {
+                info!("{}", style::dim(&format!("clio-ai {} is available (you have {})", latest, version::VERSION)));
+            }
+        }
+
+        let readline = match pending.take() {
+            Some(prompt) => {
+                println!(">>> {}", prompt);
+                Ok(prompt)
+            }
+            None => rl.readline(">>> "),
+        };
         match readline {
             Ok(line) => {
-                let input = line.trim();
+                interrupted = false;
+                let fenced = line.trim_start().starts_with("```");
+                let line = if fenced {
+                    match read_multiline(&mut rl, &line) {
+                        Some(block) => assemble_input(&block),
+                        None => continue,
+                    }
+                } else {
+                    assemble_input(&line)
+                };
+                let input = line.as_str();
                 if input.is_empty() { continue; }
 
                 rl.add_history_entry(input).ok();
 
                 // Handle commands
-                if input.starts_with('/') {
-                    if handle_command(input, &mut llm) {
-                        continue;
+                // A fenced block is always a prompt, even if it starts with a slash
+                let prompt = if input.starts_with('/') && !fenced {
+                    let result = commands::handle(input, &mut session).await;
+                    sync_helper(&mut rl, &session);
+                    match result {
+                        commands::CommandResult::Handled => continue,
+                        commands::CommandResult::Quit => break,
+                        commands::CommandResult::Prompt(prompt) => prompt,
+                        commands::CommandResult::NotCommand => input.to_string(),
                     }
-                }
+                } else {
+                    input.to_string()
+                };
 
                 // Process with LLM
-                match process_prompt(&llm, input, &cwd_str).await {
-                    Ok(response) => println!("\n{}\n", response),
-                    Err(e) => println!("\nError: {}\n", e),
+                submit_prompt(&mut session, &prompt).await;
+            }
+            Err(ReadlineError::Interrupted) => {
+                if interrupted {
+                    break;
                 }
+                interrupted = true;
+                warn!("(press Ctrl+C again or /quit to exit)");
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("Input error: {}", e);
+                std::process::exit(1);
             }
-            Err(_) => break,
         }
     }
+
+    // Only the last HISTORY_SIZE entries are written, which keeps the file bounded
+    if let Some(path) = history_file.as_deref() {
+        let saved = path.parent().map_or(Ok(()), std::fs::create_dir_all).map_err(|e| e.to_string());
+        if let Err(e) = saved.and_then(|_| rl.save_history(path).map_err(|e| e.to_string())) {
+            warn!("Warning: cannot save history {}: {}", path.display(), e);
+        }
+    }
+    if session.llm.config().persist_settings {
+        match session.llm.config_mut().save() {
+            Ok((path, saved)) if !saved.is_empty() => info!("Saved {} to {}", saved.join(", "), path.display()),
+            Ok(_) => {}
+            Err(e) => warn!("Warning: cannot save settings: {}", e),
+        }
+    }
+    info!("Goodbye!");
 }
 
-fn handle_command(input: &str, llm: &mut LLM) -> bool {
-    let parts: Vec<&str> = input.splitn(2, ' ').collect();
-    let cmd = parts[0];
+// Completion candidates that come from session state
+fn sync_helper(rl: &mut Editor<completer::ClioHelper, DefaultHistory>, session: &Session) {
+    if let Some(helper) = rl.helper_mut() {
+        helper.recent_files.clone_from(&session.recent_files);
+        helper.branches = session.branches.keys().cloned().collect();
+        helper.models = session.llm.config().models.all().iter().map(|m| m.id.clone()).collect();
+        helper.profiles.clone_from(&session.llm.config().profiles);
+    }
+}
 
-    match cmd {
-        "/help" => {
-            println!("\nCommands:");
-            println!("  /models        - List available models");
-            println!("  /model <name>  - Switch model");
-            println!("  /config        - Show config path");
-            println!("  /quit          - Exit\n");
-        }
-        "/models" => {
-            println!("\nAvailable models:");
-            for (id, name, provider) in MODELS {
-                println!("  {} - {} ({})", id, name, provider);
+// A line starting with ``` collects input until a line that is only ```; the fences themselves are not sent
+fn read_multiline(rl: &mut Editor<completer::ClioHelper, DefaultHistory>, opening: &str) -> Option<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut pasted = pasted_after_fence(opening).map(str::to_string);
+    loop {
+        let input = match pasted.take() {
+            Some(input) => input,
+            None => match rl.readline("... ") {
+                Ok(input) => input,
+                Err(ReadlineError::Interrupted) => {
+                    println!("(multi-line input cancelled)");
+                    return None;
+                }
+                // End of input submits what was collected
+                Err(_) => return Some(lines.join("\n")),
+            },
+        };
+        for line in input.split('\n') {
+            if line.trim() == "```" {
+                return Some(lines.join("\n"));
             }
-            println!();
+            lines.push(line.to_string());
         }
-        "/model" => {
-            if parts.len() < 2 {
-                println!("Usage: /model <model_name>");
-                return true;
-            }
-            let model = parts[1].trim();
-            llm.set_model(model);
-            println!("Switched to: {}", model);
+    }
+}
+
+// The rest of the opening line is an info string (```rust) and is dropped; a whole pasted block arrives
+// with that line, and its lines follow the first newline
+fn pasted_after_fence(opening: &str) -> Option<&str> {
+    opening.trim_start()[3..].split_once('\n').map(|(_, rest)| rest)
+}
+
+// Normalizes a (possibly pasted, multi-line) buffer into a single prompt
+fn assemble_input(buffer: &str) -> String {
+    buffer
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+async fn submit_prompt(session: &mut Session, prompt: &str) {
+    let prompt = match session.attachment.take() {
+        Some(attachment) => format!("{}\n\n{}", attachment, prompt),
+        None => prompt.to_string(),
+    };
+    let turns_before = session.transcript.len();
+    let result = process_prompt(session, &prompt).await;
+    let success = result.is_ok();
+    match result {
+        Ok(response) => {
+            println!("\n{}\n", response);
+            print_stats_line(session);
+            session.last_response = Some(response);
         }
-        "/config" => {
-            let paths = Config::env_paths();
-            if paths.is_empty() {
-                println!("Config: .env in current dir");
-            } else {
-                let mut line = String::from("Config: .env in current dir");
-                for path in paths {
-                    line.push_str(" OR ");
-                    line.push_str(&format!("{:?}", path));
-                }
-                println!("{}", line);
+        Err(e) => eprintln!("\n{} {}\n", style::red("Error:"), e),
+    }
+
+    let config = session.llm.config();
+    // A cancelled prompt adds no turn and leaves the previous stats in place
+    if config.notify && session.transcript.len() > turns_before {
+        let elapsed = session.last_stats.as_ref().map(|s| s.elapsed).unwrap_or_default();
+        if elapsed.as_secs() >= config.notify_after_secs {
+            if let Some(turn) = session.transcript.last() {
+                notify::prompt_finished(turn, elapsed, success);
             }
         }
-        "/quit" | "/exit" => {
-            std::process::exit(0);
+    }
+}
+
+fn print_stats_line(session: &Session) {
+    if !session.llm.config().stats_line {
+        return;
+    }
+    if let Some(stats) = session.last_stats.as_ref() {
+        info!("{}\n", style::dim(&stats::stats_line(stats)));
+    }
+}
+
+const COMPLEXITY_WARN_CALLS: u32 = 8;
+
+struct ComplexityEstimate {
+    estimated_calls: u32,
+    estimated_tokens: u32,
+}
+
+fn estimate_complexity(prompt: &str) -> ComplexityEstimate {
+    let lower = prompt.to_lowercase();
+    let words = lower.split_whitespace().count() as u32;
+
+    let mut calls = 2 + words / 25;
+    for phrase in ["entire application", "full project", "production-ready", "production ready", "full-stack", "complete app"] {
+        if lower.contains(phrase) {
+            calls += 6;
+        }
+    }
+    for phrase in ["with tests", "docker", "ci/cd", "authentication", "database", "frontend", "backend"] {
+        if lower.contains(phrase) {
+            calls += 1;
         }
-        _ => {
-            return false; // Not a command, process as prompt
+    }
+
+    // Every call resends the system prompt (~1.2k tokens) and the request, plus the model's output
+    let per_call = 1200 + (prompt.len() as u32) / 4 + 800;
+    ComplexityEstimate {
+        estimated_calls: calls,
+        estimated_tokens: calls * per_call,
+    }
+}
+
+async fn process_prompt(session: &mut Session, prompt: &str) -> Result<String, String> {
+    let estimate = estimate_complexity(prompt);
+    if estimate.estimated_calls >= COMPLEXITY_WARN_CALLS && session.can_ask() {
+        let question = format!(
+            "This prompt may require many iterations and API calls. Estimated: ~{} calls (~{}k tokens). Continue? [Y/n]",
+            estimate.estimated_calls,
+            estimate.estimated_tokens / 1000
+        );
+        if matches!(ask(&question).as_str(), "n" | "no") {
+            return Ok("Cancelled.".into());
         }
     }
+
+    session.transcript.push(Turn {
+        prompt: prompt.to_string(),
+        model: session.llm.config().model.clone(),
+        response: None,
+        tools: Vec::new(),
+        replies: Vec::new(),
+    });
+    let started = Instant::now();
+    session.last_stats = Some(PromptStats::default());
+    let result = run_prompt(session, prompt).await;
+    if let Some(stats) = session.last_stats.as_mut() {
+        stats.elapsed = started.elapsed();
+        session.stats.add(stats);
+    }
+    if let Some(turn) = session.transcript.last_mut() {
+        turn.response = Some(match &result {
+            Ok(response) => response.clone(),
+            Err(e) => format!("Error: {}", e),
+        });
+    }
+    autosave_session(session);
+    result
+}
+
+fn autosave_session(session: &Session) {
+    let saved = session_file::SavedSession {
+        saved_at: chrono::Local::now().timestamp(),
+        cwd: session.cwd.clone(),
+        provider: session.llm.config().provider.clone(),
+        model: session.llm.config().model.clone(),
+        transcript: session.transcript.clone(),
+    };
+    if let Err(e) = session_file::save(&saved) {
+        eprintln!("{}", style::dim(&format!("(session autosave failed: {})", e)));
+    }
+}
+
+// Returns false when the user chose not to continue
+fn resume_session(session: &mut Session) -> bool {
+    let saved = match session_file::load(&session.cwd) {
+        Ok(saved) => saved,
+        Err(e) => {
+            warn!("No session to resume for this project ({})", e);
+            return true;
+        }
+    };
+
+    if saved.cwd.is_dir() {
+        if let Err(e) = commands::change_dir(session, &saved.cwd) {
+            warn!("Warning: cannot return to {}", e);
+        }
+    } else if !confirm(&format!(
+        "Saved directory {} no longer exists. Continue in {}?",
+        saved.cwd.display(),
+        session.cwd.display()
+    )) {
+        return false;
+    }
+
+    session.llm.set_model(&saved.model);
+    session.llm.config_mut().provider = saved.provider.clone();
+    session.transcript = saved.transcript;
+    session.last_response = session.transcript.last().and_then(|t| t.response.clone());
+
+    let saved_at = chrono::DateTime::from_timestamp(saved.saved_at, 0)
+        .map(|t| t.with_timezone(&chrono::Local).format("%H:%M").to_string())
+        .unwrap_or_default();
+    info!(
+        "restored session from {}, {} prompt(s), model {}, cwd {}",
+        saved_at,
+        session.transcript.len(),
+        saved.model,
+        display_home(&session.cwd)
+    );
+    info!("{}", style::dim("(the transcript is back for /export and /copy; the model starts fresh, as with every prompt)"));
     true
 }
 
-async fn process_prompt(llm: &LLM, prompt: &str, cwd: &str) -> Result<String, String> {
-    let cwd_path = std::path::Path::new(cwd);
+fn display_home(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(|p| p.to_path_buf())) {
+        Some(rel) => format!("~/{}", rel.display()),
+        None => path.display().to_string(),
+    }
+}
+
+async fn run_prompt(session: &mut Session, prompt: &str) -> Result<String, String> {
+    let cwd_path = session.tool_root();
+    let cwd = cwd_path.to_string_lossy().to_string();
     let mut tool_results: Option<String> = None;
-    let max_iterations = 10;
+    let max_iterations = session.llm.config().limits.max_iterations;
+    let max_tools = session.llm.config().limits.max_tools_per_iteration;
+    let max_files = session.llm.config().max_files;
+    let lite = session.llm.config().lite;
+    let mut files_created = 0;
+    let mut correction_sent = false;
 
     // Check if prompt needs repo context (summarize, explain, understand, etc.)
     let needs_context = prompt.to_lowercase().contains("summarize")
@@ -112,14 +682,42 @@ async fn process_prompt(llm: &LLM, prompt: &str, cwd: &str) -> Result<String, St
         || prompt.to_lowercase().contains("about this");
 
     // Auto-gather repo context if needed
-    let repo_context = if needs_context {
-        Some(gather_repo_context(cwd_path))
+    let repo_context = if needs_context && !lite {
+        let mut context = gather_repo_context(&cwd_path, session.llm.config());
+        // Leave at least half of a known context window for the prompt, tools and reply (~4 chars per token)
+        let config = session.llm.config();
+        if let Some(window) = config.models.get(&config.model).and_then(|m| m.context_window) {
+            let budget = (window as usize / 2) * 4;
+            if let Some((cut, _)) = context.char_indices().nth(budget) {
+                context.truncate(cut);
+                context.push_str("\n[repo context truncated to fit the model's context window]");
+            }
+        }
+        Some(context)
     } else {
         None
     };
 
     for _ in 0..max_iterations {
-        let response = llm.chat(prompt, cwd, tool_results.as_deref(), repo_context.as_deref()).await?;
+        let llm_started = Instant::now();
+        let (response, meta) = match session.llm.chat(prompt, &cwd, tool_results.as_deref(), repo_context.as_deref()).await {
+            Err(e) if pull::is_missing_model(session.llm.config(), &e) && pull::offer(session.llm.config(), &session.interrupt, session.can_ask()).await => {
+                session.llm.chat(prompt, &cwd, tool_results.as_deref(), repo_context.as_deref()).await?
+            }
+            result => result?,
+        };
+        if let Some(stats) = session.last_stats.as_mut() {
+            stats.iterations += 1;
+            stats.usage.add(meta.usage);
+            if let Some(cost) = session.llm.config().models.get(&meta.model).and_then(|m| m.cost(meta.usage)) {
+                *stats.cost.get_or_insert(0.0) += cost;
+            }
+            stats.llm_time += llm_started.elapsed();
+            stats.answered_by = Some(format!("{}/{}", meta.provider, meta.model));
+        }
+        if let Some(turn) = session.transcript.last_mut() {
+            turn.replies.push(meta);
+        }
 
         if let Some(text) = response.response {
             return Ok(text);
@@ -134,10 +732,23 @@ async fn process_prompt(llm: &LLM, prompt: &str, cwd: &str) -> Result<String, St
             let mut blocked: Vec<(ToolCall, String)> = Vec::new();
             let mut ignored = Vec::new();
 
+            let mut tools = tools;
+            for tool in tools.split_off(tools.len().min(max_tools)) {
+                let reason = format!(
+                    "Not run: at most {} actions per reply (max_tools_per_iteration); send the rest in your next reply",
+                    max_tools
+                );
+                blocked.push((tool, reason));
+            }
+
+            let blocked_actions = session.llm.config().blocked_actions.clone();
+            let project = project::detect_project_type(&session.cwd);
             for tool in tools {
-                if is_supported_action(&tool.action) {
-                    if let Some(reason) = should_block_tool_for_prompt(&tool, prompt) {
-                        blocked.push((tool, reason.to_string()));
+                if let Decision::Deny(reason) = policy::resolve(session.llm.config(), &tool, &cwd_path) {
+                    blocked.push((tool, reason));
+                } else if is_supported_action(&tool.action, &blocked_actions) {
+                    if let Some(reason) = should_block_tool_for_prompt(&tool, prompt, project) {
+                        blocked.push((tool, reason));
                     } else {
                         supported.push(tool);
                     }
@@ -146,35 +757,120 @@ async fn process_prompt(llm: &LLM, prompt: &str, cwd: &str) -> Result<String, St
                 }
             }
 
+            let collisions = batch_collisions(&supported);
+            if !collisions.is_empty() {
+                let (rejected, rest): (Vec<ToolCall>, Vec<ToolCall>) = supported
+                    .into_iter()
+                    .partition(|t| collisions.contains(&normalize_batch_path(t.path.as_deref().unwrap_or(""))));
+                supported = rest;
+                for tool in rejected {
+                    let reason = format!(
+                        "Rejected: '{}' is targeted by both create_folder and create_file in the same batch; use one",
+                        tool.path.as_deref().unwrap_or("")
+                    );
+                    blocked.push((tool, reason));
+                }
+            }
+
             if supported.is_empty() && blocked.is_empty() && ignored.is_empty() {
                 return Ok("No action taken.".into());
             }
 
+            let interactive = session.can_ask();
+            let approval = BatchApproval::ask(&supported, session.llm.config(), ASSUME_YES.load(Ordering::Relaxed), interactive);
+
             let mut results = Vec::new();
+            let mut batch = BatchRun::default();
+            let mut created = Vec::new();
+            let mut capped = 0;
             for tool in &supported {
-                println!("  → {} {}", tool.action, tool.path.as_deref().unwrap_or(""));
-                let result = execute_tool(tool, cwd_path);
+                if let Some(result) = batch.not_run(tool) {
+                    print_action(tool, "skipped");
+                    results.push(serde_json::to_string(&result).unwrap());
+                    continue;
+                }
+                let creates_file = matches!(tool.action.as_str(), "create_file" | "create_folder");
+                if creates_file && files_created >= max_files {
+                    capped += 1;
+                    let result = ToolResult::failed(tool, format!("File cap exceeded: at most {} files may be created per prompt", max_files));
+                    results.push(serde_json::to_string(&result).unwrap());
+                    continue;
+                }
+                if let Some(reason) = approval.skip_reason(tool, session.llm.config(), &cwd_path) {
+                    let result = ToolResult::failed(tool, reason);
+                    print_action(tool, "skipped");
+                    results.push(serde_json::to_string(&result).unwrap());
+                    continue;
+                }
+                if let Some(wait) = session.rate_limiter.wait_time() {
+                    warn!(
+                        "{}",
+                        style::yellow(&format!("Rate limit: pausing for {}s to avoid runaway file creation", wait.as_secs().max(1)))
+                    );
+                    tokio::time::sleep(wait).await;
+                }
+                session.rate_limiter.record();
+                if creates_file {
+                    files_created += 1;
+                }
+                let tool_started = Instant::now();
+                let mut result = batch.run(tool, &cwd_path, session.llm.config()).await;
+                if let Some(stats) = session.last_stats.as_mut() {
+                    stats.tool_time += tool_started.elapsed();
+                    stats.record_action(&tool.action, Duration::from_millis(result.duration_ms));
+                }
+                print_action(tool, &format!("{}ms", result.duration_ms));
+                if batch.rolled_back.is_some() {
+                    created.clear();
+                }
+                if tool.action == "create_file" && result.success {
+                    created.push(result.path.clone());
+                    if interactive && tools::is_env_file(&result.path) {
+                        offer_env_example(tool, &cwd_path, session.llm.config(), &mut batch).await;
+                    }
+                }
+                if let Some(turn) = session.transcript.last_mut() {
+                    turn.tools.push(ToolRecord {
+                        action: result.action.clone(),
+                        path: result.path.clone(),
+                        success: result.success,
+                        content: tool.content.clone(),
+                    });
+                }
+                if lite {
+                    truncate_for_lite(&mut result.result);
+                }
+                if let Some(note) = tool.note.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+                    result.result = format!("(Note: {}) {}", note, result.result);
+                }
                 results.push(serde_json::to_string(&result).unwrap());
             }
+            session.undo.record(batch.changes);
+            if capped > 0 {
+                warn!(
+                    "{}",
+                    style::yellow(&format!(
+                        "File cap reached: skipped {} file-creating action(s) (limit {}, see --max-files)",
+                        capped, max_files
+                    ))
+                );
+            }
+
+            // A batch made only of successful file writes is the finished task; skip the confirmation round-trip
+            if created.len() == supported.len() && blocked.is_empty() && ignored.is_empty() {
+                return Ok(format!("Created {} file(s): {}", created.len(), created.join(", ")));
+            }
             for (tool, reason) in &blocked {
-                let result = ToolResult {
-                    action: tool.action.clone(),
-                    path: tool.path.clone().unwrap_or_default(),
-                    success: false,
-                    result: reason.clone(),
-                };
+                let result = ToolResult::failed(tool, reason.clone());
                 results.push(serde_json::to_string(&result).unwrap());
             }
             for tool in &ignored {
-                let result = ToolResult {
-                    action: tool.action.clone(),
-                    path: tool.path.clone().unwrap_or_default(),
-                    success: false,
-                    result: "Unsupported action".into(),
-                };
+                let result = ToolResult::failed(tool, "Unsupported action");
                 results.push(serde_json::to_string(&result).unwrap());
             }
 
+            results.extend(unsupported_action_correction(&ignored, &blocked_actions, &mut correction_sent));
+
             let results_str = results.join("\n");
             if tool_results.as_deref() == Some(results_str.as_str()) {
                 return Ok("No further progress possible.".into());
@@ -188,7 +884,334 @@ async fn process_prompt(llm: &LLM, prompt: &str, cwd: &str) -> Result<String, St
     Ok("Max iterations reached.".into())
 }
 
-fn should_block_tool_for_prompt(tool: &ToolCall, prompt: &str) -> Option<&'static str> {
+const LITE_RESULT_CHARS: usize = 512;
+
+// Only what the model sees is cut; the transcript and files are untouched
+fn truncate_for_lite(text: &mut String) {
+    if let Some((cut, _)) = text.char_indices().nth(LITE_RESULT_CHARS) {
+        text.truncate(cut);
+        text.push_str("... [truncated in lite mode]");
+    }
+}
+
+// Tells the model once per prompt, firmly, which actions exist instead of letting it burn iterations
+fn unsupported_action_correction(ignored: &[ToolCall], blocked_actions: &[String], sent: &mut bool) -> Option<String> {
+    if ignored.is_empty() || *sent {
+        return None;
+    }
+    *sent = true;
+    let mut actions: Vec<&str> = ignored.iter().map(|t| t.action.as_str()).collect();
+    actions.sort();
+    actions.dedup();
+    Some(format!(
+        "CORRECTION: the action(s) {} are NOT supported and were not executed. \
+         Use ONLY these actions: {}. Do not emit any other action.",
+        actions.join(", "),
+        available_actions(blocked_actions).join(", ")
+    ))
+}
+
+// The file changes one model reply has made so far. With ATOMIC_BATCH a failed change rolls them all
+// back and the rest of the reply is not run
+#[derive(Default)]
+struct BatchRun {
+    changes: Vec<Change>,
+    rolled_back: Option<String>,
+}
+
+impl BatchRun {
+    fn not_run(&self, tool: &ToolCall) -> Option<ToolResult> {
+        let failure = self.rolled_back.as_ref()?;
+        Some(ToolResult::failed(tool, format!("Not run: the batch was rolled back after {} failed (ATOMIC_BATCH)", failure)))
+    }
+
+    // Runs the call and keeps what it changed for /undo
+    async fn record(&mut self, tool: &ToolCall, cwd: &Path, config: &Config) -> ToolResult {
+        let targets: Vec<(String, PathBuf, Snapshot)> = mutated_paths(tool, cwd)
+            .into_iter()
+            .filter_map(|p| resolve_path(cwd, &p).map(|full| (p, full)))
+            .map(|(p, full)| {
+                let before = Snapshot::capture(&full);
+                if before == Snapshot::TooLarge {
+                    let limit = undo::MAX_SNAPSHOT_BYTES / (1024 * 1024);
+                    warn!("{}", style::yellow(&format!("{} is over {} MB; /undo and a batch rollback cannot restore it", p, limit)));
+                }
+                (p, full, before)
+            })
+            .collect();
+        let result = execute_tool_async(tool, cwd, config).await;
+        for (display, path, before) in targets {
+            let after = Snapshot::capture(&path);
+            self.changes.push(Change { path, display, before, after });
+        }
+        result
+    }
+
+    async fn run(&mut self, tool: &ToolCall, cwd: &Path, config: &Config) -> ToolResult {
+        let mut result = self.record(tool, cwd, config).await;
+        if config.atomic_batch && tools::is_mutating_action(&tool.action) && !result.success {
+            let failure = format!("{} {}", tool.action, tool.path.as_deref().unwrap_or(""));
+            match undo::rollback(&self.changes) {
+                Ok(paths) => {
+                    warn!("{}", style::yellow(&format!("{} failed; rolled back {} change(s) from this batch", failure, paths.len())));
+                    result.result = format!("{} (batch rolled back: every change in this batch was undone, the tree is unchanged)", result.result);
+                }
+                Err(e) => {
+                    warn!("{}", style::red(&format!("{} failed and the rollback stopped at {}", failure, e)));
+                    result.result = format!("{} (batch rollback failed at {}; some changes remain)", result.result, e);
+                }
+            }
+            self.changes.clear();
+            self.rolled_back = Some(failure);
+        }
+        result
+    }
+}
+
+// Post-create hook for .env files: offer a redacted .env.example alongside
+async fn offer_env_example(tool: &ToolCall, cwd: &Path, config: &Config, batch: &mut BatchRun) {
+    let Some(env_path) = tool.path.as_deref() else { return };
+    if !confirm(&format!("    Generate {} with secrets redacted?", tools::env_example_path(env_path)))
+    {
+        return;
+    }
+    let hook = ToolCall { action: "generate_env_example".into(), content: None, ..tool.clone() };
+    let result = batch.record(&hook, cwd, config).await;
+    if result.success {
+        info!("    {}", style::dim(&result.result));
+    } else {
+        warn!("    {}", style::yellow(&result.result));
+    }
+}
+
+// "→ create_file src/main.rs (12ms)", with the model's reason dimmed after it
+fn print_action(tool: &ToolCall, outcome: &str) {
+    let reason = tool.reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    info!(
+        "  {} {} {} {}{}",
+        style::dim("→"),
+        tool.action,
+        tool.path.as_deref().unwrap_or(""),
+        style::dim(&format!("({})", outcome)),
+        reason.map(|r| format!(" {}", style::dim(&format!("({})", r)))).unwrap_or_default()
+    );
+}
+
+// Ask the user before risky calls; returns the skip reason when declined.
+// `ask` is the tool policy's verdict; rewriting a file with uncommitted changes asks either way.
+// Without `interactive` (no terminal, or server mode) a call that needs an answer is skipped
+fn confirm_tool(tool: &ToolCall, cwd: &Path, ask: bool, interactive: bool) -> Option<String> {
+    if tool.action == "create_file" {
+        if let Some(outcome) = confirm_rewrite(tool, cwd, interactive) {
+            return outcome;
+        }
+    }
+    if !ask || assume_yes() {
+        return None;
+    }
+    let target = tool.path.as_deref().unwrap_or("");
+    if !interactive {
+        return Some(format!("Skipped: '{}' requires confirmation (CONFIRM) and no terminal is attached", tool.action));
+    }
+    if confirm(&format!("    Run {} {}?", tool.action, target)) {
+        None
+    } else {
+        Some(format!("Skipped: user declined {} {}", tool.action, target))
+    }
+}
+
+// Files a slash command writes for the user get what the model's create_file gets: the tool policy, then
+// the rewrite and CONFIRM questions. Returns why the write is skipped
+fn check_command_write(session: &Session, path: &str, content: &str) -> Option<String> {
+    let tool = ToolCall { action: "create_file".into(), path: Some(path.into()), content: Some(content.into()), ..Default::default() };
+    match policy::resolve(session.llm.config(), &tool, &session.cwd) {
+        Decision::Deny(reason) => Some(reason),
+        decision => confirm_tool(&tool, &session.cwd, decision == Decision::Confirm, session.can_ask()),
+    }
+}
+
+// The answers that cover a whole batch, asked once before it runs
+struct BatchApproval {
+    deletes: usize,
+    deletes_declined: Option<String>,
+    writes_confirmed: Option<Result<(), String>>,
+    interactive: bool,
+}
+
+impl BatchApproval {
+    fn ask(supported: &[ToolCall], config: &Config, yes: bool, interactive: bool) -> Self {
+        // Several deletes in one batch are approved together, whatever CONFIRM says
+        let deletes: Vec<&str> =
+            supported.iter().filter(|t| t.action == "delete").map(|t| t.path.as_deref().unwrap_or("")).collect();
+        let writes: Vec<&ToolCall> = supported.iter().filter(|t| t.action == "create_file").collect();
+        if writes.len() > 1 && interactive {
+            let bytes: usize = writes.iter().map(|t| t.content.as_deref().map_or(0, str::len)).sum();
+            info!("    About to write {} files ({} total), delete {}", writes.len(), format_bytes(bytes), deletes.len());
+        }
+        let deletes_declined = if deletes.len() > 1 { confirm_deletes(&deletes, yes, interactive).err() } else { None };
+        // Likewise one answer covers several writes when CONFIRM includes create_file
+        let writes_confirmed = if writes.len() > 1 {
+            confirm_writes(writes.len(), policy::for_action(config, "create_file") == Decision::Confirm, interactive)
+        } else {
+            None
+        };
+        BatchApproval { deletes: deletes.len(), deletes_declined, writes_confirmed, interactive }
+    }
+
+    // Why `tool` must not run; calls no batch answer covers are asked about on their own
+    fn skip_reason(&self, tool: &ToolCall, config: &Config, cwd: &Path) -> Option<String> {
+        match &self.deletes_declined {
+            Some(reason) if tool.action == "delete" => Some(reason.clone()),
+            _ if tool.action == "delete" && self.deletes > 1 => None,
+            _ => match &self.writes_confirmed {
+                Some(Err(reason)) if tool.action == "create_file" => Some(reason.clone()),
+                // Overwriting a file with uncommitted changes still asks on its own
+                Some(Ok(())) if tool.action == "create_file" => confirm_tool(tool, cwd, false, self.interactive),
+                _ => {
+                    let ask = policy::resolve(config, tool, cwd) == Decision::Confirm;
+                    confirm_tool(tool, cwd, ask, self.interactive)
+                }
+            },
+        }
+    }
+}
+
+// None when the policy does not ask, so each write goes through confirm_tool as usual
+fn confirm_writes(count: usize, ask: bool, interactive: bool) -> Option<Result<(), String>> {
+    if !ask || assume_yes() || !interactive {
+        return None;
+    }
+    Some(if confirm(&format!("    Write all {}?", count)) {
+        Ok(())
+    } else {
+        Err(format!("Skipped: user declined writing {} files", count))
+    })
+}
+
+// 512 B, 12.3 KB, 4.0 MB
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+// Only --yes skips this; mode = "auto" still asks before several deletes
+fn confirm_deletes(paths: &[&str], yes: bool, interactive: bool) -> Result<(), String> {
+    if yes {
+        return Ok(());
+    }
+    eprintln!("    {} paths to delete:", paths.len());
+    for path in paths {
+        eprintln!("      {}", style::red(path));
+    }
+    if !interactive {
+        return Err(format!("Skipped: deleting {} paths in one batch requires confirmation and no terminal is attached", paths.len()));
+    }
+    if matches!(ask(&format!("    Delete all {}? [y/N]", paths.len())).as_str(), "y" | "yes") {
+        Ok(())
+    } else {
+        Err(format!("Skipped: user declined deleting {} paths", paths.len()))
+    }
+}
+
+// Shows the size of a full-file rewrite and asks about it; None when the call is not a rewrite
+fn confirm_rewrite(tool: &ToolCall, cwd: &Path, interactive: bool) -> Option<Option<String>> {
+    let display = tool.path.as_deref().unwrap_or("");
+    let path = resolve_path(cwd, display)?;
+    let existing = std::fs::read_to_string(&path).ok()?;
+    let new = tool.content.as_deref().unwrap_or("");
+    if existing == new {
+        return None;
+    }
+
+    // Full-file rewrites are the easiest way to lose work, so always surface their size
+    let diff = similar::TextDiff::from_lines(existing.as_str(), new);
+    let (mut added, mut removed) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            similar::ChangeTag::Insert => added += 1,
+            similar::ChangeTag::Delete => removed += 1,
+            similar::ChangeTag::Equal => {}
+        }
+    }
+    let dirty = git::is_dirty_tracked(cwd, &path);
+    eprintln!(
+        "    rewrite {}: {} {} lines{}",
+        display,
+        style::green(&format!("+{}", added)),
+        style::red(&format!("-{}", removed)),
+        if dirty { style::yellow(" (has uncommitted changes)") } else { String::new() }
+    );
+
+    // --yes and mode = "auto" cover clean and untracked files; uncommitted work always needs a yes
+    if !dirty && (assume_yes() || !interactive) {
+        return Some(None);
+    }
+    if dirty && !interactive {
+        return Some(Some(format!("Skipped: {} has uncommitted changes and no terminal is attached to confirm overwriting it", display)));
+    }
+    loop {
+        match ask(&format!("    Overwrite {}? [y/N/d=show diff]", display)).as_str() {
+            "y" | "yes" => return Some(None),
+            "d" | "diff" => {
+                let unified = diff.unified_diff().context_radius(3).header(display, display).to_string();
+                for line in unified.lines() {
+                    eprintln!("{}", style::diff_line(line));
+                }
+            }
+            _ if dirty => return Some(Some("Skipped: user declined overwriting a file with uncommitted changes".into())),
+            _ => return Some(Some("Skipped: user declined the rewrite".into())),
+        }
+    }
+}
+
+fn ask(question: &str) -> String {
+    // Questions go to stderr so they still reach the terminal when stdout is piped
+    eprint!("{} ", question);
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).ok();
+    answer.trim().to_lowercase()
+}
+
+// Set by --yes
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+// Set while a project file's mode = "auto" applies; kept apart from --yes so leaving the project restores it
+static AUTO_APPROVE: AtomicBool = AtomicBool::new(false);
+
+fn assume_yes() -> bool {
+    ASSUME_YES.load(Ordering::Relaxed) || AUTO_APPROVE.load(Ordering::Relaxed)
+}
+
+fn set_auto_approve(on: bool) {
+    AUTO_APPROVE.store(on, Ordering::Relaxed);
+}
+
+fn confirm(question: &str) -> bool {
+    assume_yes() || matches!(ask(&format!("{} [y/N]", question)).as_str(), "y" | "yes")
+}
+
+// Paths that one batch asks to create both as a folder and as a file
+fn batch_collisions(tools: &[ToolCall]) -> Vec<String> {
+    let paths_for = |action: &str| -> Vec<String> {
+        tools
+            .iter()
+            .filter(|t| t.action == action)
+            .map(|t| normalize_batch_path(t.path.as_deref().unwrap_or("")))
+            .collect()
+    };
+    let folders = paths_for("create_folder");
+    paths_for("create_file").into_iter().filter(|p| folders.contains(p)).collect()
+}
+
+fn normalize_batch_path(path: &str) -> String {
+    path.trim().trim_start_matches("./").trim_end_matches('/').to_string()
+}
+
+// Keeps one language's files out of another's project or request unless the prompt names that language
+fn should_block_tool_for_prompt(tool: &ToolCall, prompt: &str, project: Option<ProjectType>) -> Option<String> {
     if tool.action != "create_file" && tool.action != "create_folder" {
         return None;
     }
@@ -202,41 +1225,63 @@ fn should_block_tool_for_prompt(tool: &ToolCall, prompt: &str) -> Option<&'stati
     let wants_python = contains_any(&prompt_lower, &["python", "streamlit"]);
     let wants_rust = contains_any(&prompt_lower, &["rust", "cargo"]);
 
-    if wants_python && !wants_rust && is_rust_path(path) {
-        return Some("Blocked Rust-specific file for Python/Streamlit request");
+    if is_rust_path(path) && !wants_rust {
+        if wants_python {
+            return Some("Blocked Rust-specific file for Python/Streamlit request".into());
+        }
+        if let Some(kind) = project.filter(|kind| *kind != ProjectType::Rust) {
+            return Some(format!("Blocked Rust-specific file in a {} project; mention Rust in the prompt to allow it", kind.name()));
+        }
+    }
+    if is_python_path(path) && !wants_python && project == Some(ProjectType::Rust) {
+        return Some("Blocked Python-specific file in a Rust project; mention Python in the prompt to allow it".into());
     }
 
     None
 }
 
 fn is_rust_path(path: &str) -> bool {
-    let lower = path.to_ascii_lowercase();
-    lower == "cargo.toml" || lower == "cargo.lock" || lower.ends_with(".rs")
+    let name = file_name_lower(path);
+    name == "cargo.toml" || name == "cargo.lock" || name.ends_with(".rs")
+}
+
+fn is_python_path(path: &str) -> bool {
+    let name = file_name_lower(path);
+    ["pyproject.toml", "requirements.txt", "setup.py", "setup.cfg", "pipfile"].contains(&name.as_str()) || name.ends_with(".py")
+}
+
+fn file_name_lower(path: &str) -> String {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    name.to_ascii_lowercase()
 }
 
 fn contains_any(haystack: &str, needles: &[&str]) -> bool {
     needles.iter().any(|needle| haystack.contains(needle))
 }
 
-fn gather_repo_context(cwd: &std::path::Path) -> String {
+fn gather_repo_context(cwd: &std::path::Path, config: &Config) -> String {
     let mut context = String::new();
     
     // List files
     context.push_str("FILES:\n");
     if let Ok(entries) = std::fs::read_dir(cwd) {
         for entry in entries.filter_map(|e| e.ok()) {
+            if clioignore::is_ignored(cwd, &entry.path(), entry.path().is_dir()) {
+                continue;
+            }
             let name = entry.file_name().to_string_lossy().to_string();
-            let prefix = if entry.path().is_dir() { "📁 " } else { "📄 " };
-            context.push_str(&format!("{}{}\n", prefix, name));
+            context.push_str(&format!("{}\n", tools::entry_label(&name, entry.path().is_dir(), config.nerd_fonts)));
         }
     }
     
     // Read key files if they exist
     for file in ["README.md", "Cargo.toml", "package.json", "pyproject.toml", "go.mod"] {
         let path = cwd.join(file);
-        if path.exists() {
-            if let Ok(content) = std::fs::read_to_string(&path) {
-                let truncated: String = content.chars().take(1500).collect();
+        if path.exists() && !clioignore::is_ignored(cwd, &path, false) {
+            if let Ok(file_handle) = std::fs::File::open(&path) {
+                let lines: Vec<String> =
+                    io::BufReader::new(file_handle).lines().map_while(Result::ok).take(config.limits.context_lines).collect();
+                let truncated: String = lines.join("\n").chars().take(config.limits.max_context_chars).collect();
                 context.push_str(&format!("\n--- {} ---\n{}\n", file, truncated));
             }
         }
@@ -244,3 +1289,120 @@ fn gather_repo_context(cwd: &std::path::Path) -> String {
     
     context
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tools::{test_call as call, test_write as write};
+
+    fn tree(dir: &Path) -> Vec<(String, String)> {
+        let mut entries: Vec<(String, String)> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .map(|p| (p.file_name().unwrap().to_string_lossy().to_string(), std::fs::read_to_string(&p).unwrap_or_default()))
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    #[tokio::test]
+    async fn atomic_batch_rolls_back_when_a_middle_write_fails() {
+        let dir = paths::test_dir("atomic-batch");
+        // A file where the second write needs a folder
+        std::fs::write(dir.join("blocker"), "keep").unwrap();
+        let before = tree(&dir);
+        let mut config = Config::defaults();
+        config.atomic_batch = true;
+
+        let mut batch = BatchRun::default();
+        let mut results = Vec::new();
+        for tool in [write("a.txt", "one"), write("blocker/b.txt", "two"), write("c.txt", "three")] {
+            let result = match batch.not_run(&tool) {
+                Some(result) => result,
+                None => batch.run(&tool, &dir, &config).await,
+            };
+            results.push(result);
+        }
+
+        assert!(results[0].success);
+        assert!(!results[1].success);
+        assert!(results[1].result.contains("batch rolled back"));
+        assert!(results[2].result.starts_with("Not run"));
+        assert!(!dir.join("a.txt").exists());
+        assert!(batch.changes.is_empty());
+        assert_eq!(tree(&dir), before);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn correction_names_unsupported_actions_once_and_lists_only_allowed_ones() {
+        let blocked = ["delete".to_string()];
+        let mut sent = false;
+        assert_eq!(unsupported_action_correction(&[], &blocked, &mut sent), None);
+        let ignored = [call("exec", "ls"), call("rename", "a"), call("exec", "pwd")];
+        let text = unsupported_action_correction(&ignored, &blocked, &mut sent).unwrap();
+        assert!(text.starts_with("CORRECTION: the action(s) exec, rename are NOT supported"));
+        let allowed = text.split("Use ONLY these actions: ").nth(1).unwrap();
+        assert!(allowed.contains("create_file"));
+        assert!(!allowed.contains("delete"));
+        // The next batch of the same prompt gets only the per-call "Unsupported action" results
+        assert_eq!(unsupported_action_correction(&[call("exec", "make")], &blocked, &mut sent), None);
+    }
+
+    #[test]
+    fn batch_collisions_matches_folder_and_file_on_the_same_normalized_path() {
+        let tools = [
+            call("create_folder", "./src/"),
+            write("src", "x"),
+            call("create_folder", "docs"),
+            write("docs/index.md", "x"),
+            write("README.md", "x"),
+        ];
+        assert_eq!(batch_collisions(&tools), vec!["src".to_string()]);
+        assert!(batch_collisions(&[write("a", "x"), write("b", "x")]).is_empty());
+    }
+
+    #[test]
+    fn opening_fence_info_string_is_not_part_of_the_prompt() {
+        assert_eq!(pasted_after_fence("```rust"), None);
+        assert_eq!(pasted_after_fence("```"), None);
+        assert_eq!(pasted_after_fence("```python\nprint(1)\n```"), Some("print(1)\n```"));
+    }
+
+    #[test]
+    fn pasted_windows_block_becomes_one_prompt() {
+        let pasted = "error[E0308]: mismatched types\r\n --> src/main.rs:4:5\r\n  |\r\n4 |     x\r\n  |     ^ expected `u32`\r\n\r\n";
+        assert_eq!(
+            assemble_input(pasted),
+            "error[E0308]: mismatched types\n --> src/main.rs:4:5\n  |\n4 |     x\n  |     ^ expected `u32`"
+        );
+    }
+
+    #[tokio::test]
+    async fn two_deletes_need_one_confirmation_unless_yes() {
+        let dir = paths::test_dir("batch-deletes");
+        std::fs::write(dir.join("a.txt"), "a").unwrap();
+        std::fs::write(dir.join("b.txt"), "b").unwrap();
+        let config = Config::defaults();
+        let deletes = [call("delete", "a.txt"), call("delete", "b.txt")];
+
+        // Without a terminal nobody can approve the batch, so both deletes are declined
+        let approval = BatchApproval::ask(&deletes, &config, false, false);
+        let mut batch = BatchRun::default();
+        let mut results = Vec::new();
+        for tool in &deletes {
+            let result = match approval.skip_reason(tool, &config, &dir) {
+                Some(reason) => ToolResult::failed(tool, reason),
+                None => batch.run(tool, &dir, &config).await,
+            };
+            results.push(result);
+        }
+
+        assert!(results.iter().all(|r| !r.success && r.result.contains("deleting 2 paths in one batch requires confirmation")));
+        assert!(dir.join("a.txt").exists());
+        assert!(dir.join("b.txt").exists());
+        let approval = BatchApproval::ask(&deletes, &config, true, false);
+        assert!(deletes.iter().all(|tool| approval.skip_reason(tool, &config, &dir).is_none()));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
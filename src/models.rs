@@ -0,0 +1,194 @@
+use crate::config::{Config, MODELS, PROVIDERS};
+use crate::llm::{self, Usage};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub provider: String,
+    #[serde(default)]
+    pub context_window: Option<u64>,
+    // USD per million tokens
+    #[serde(default)]
+    pub input_price: Option<f64>,
+    #[serde(default)]
+    pub output_price: Option<f64>,
+    #[serde(skip)]
+    pub custom: bool,
+    // Added this session by /models refresh
+    #[serde(skip)]
+    pub discovered: bool,
+}
+
+impl ModelInfo {
+    pub fn cost(&self, usage: Usage) -> Option<f64> {
+        let input = self.input_price? * usage.input_tokens as f64;
+        let output = self.output_price? * usage.output_tokens as f64;
+        Some((input + output) / 1_000_000.0)
+    }
+}
+
+// Built-in MODELS plus [[models]] from the config file; a custom entry replaces a built-in with the same id
+#[derive(Debug, Clone)]
+pub struct ModelRegistry {
+    models: Vec<ModelInfo>,
+}
+
+impl Default for ModelRegistry {
+    fn default() -> Self {
+        let models = MODELS
+            .iter()
+            .map(|(id, name, provider)| ModelInfo {
+                id: id.to_string(),
+                name: name.to_string(),
+                provider: provider.to_string(),
+                context_window: None,
+                input_price: None,
+                output_price: None,
+                custom: false,
+                discovered: false,
+            })
+            .collect();
+        Self { models }
+    }
+}
+
+impl ModelRegistry {
+    pub fn with_custom(custom: Vec<ModelInfo>) -> Self {
+        let mut registry = Self::default();
+        for mut model in custom {
+            if let Err(e) = validate(&model) {
+                warn!("Warning: ignoring [[models]] entry '{}': {}", model.id, e);
+                continue;
+            }
+            if model.name.trim().is_empty() {
+                model.name.clone_from(&model.id);
+            }
+            model.custom = true;
+            match registry.models.iter_mut().find(|m| m.id == model.id) {
+                Some(existing) => *existing = model,
+                None => registry.models.push(model),
+            }
+        }
+        registry
+    }
+
+    pub fn all(&self) -> &[ModelInfo] {
+        &self.models
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ModelInfo> {
+        self.models.iter().find(|m| m.id == id)
+    }
+
+    pub fn first_for(&self, provider: &str) -> Option<&ModelInfo> {
+        self.models.iter().find(|m| m.provider == provider)
+    }
+
+    pub fn custom(&self) -> impl Iterator<Item = &ModelInfo> {
+        self.models.iter().filter(|m| m.custom)
+    }
+
+    // Adds a model a provider reported; false if the id is already known
+    pub fn discover(&mut self, id: &str, provider: &str) -> bool {
+        if self.get(id).is_some() {
+            return false;
+        }
+        self.models.push(ModelInfo {
+            id: id.to_string(),
+            name: id.to_string(),
+            provider: provider.to_string(),
+            context_window: None,
+            input_price: None,
+            output_price: None,
+            custom: false,
+            discovered: true,
+        });
+        true
+    }
+}
+
+// Model ids from every provider that can list them, queried concurrently. Providers without a key are skipped
+pub async fn fetch_all(config: &Config) -> Vec<(&'static str, Result<Vec<String>, String>)> {
+    let Ok(client) = Client::builder().timeout(Duration::from_secs(10)).build() else {
+        return Vec::new();
+    };
+    let (ollama, groq, gemini, deepseek) = tokio::join!(
+        fetch_ollama(&client, config),
+        fetch_openai_style(&client, llm::GROQ.base_url, config.groq_api_key.as_deref()),
+        fetch_gemini(&client, config.gemini_api_key.as_deref()),
+        fetch_openai_style(&client, llm::DEEPSEEK.base_url, config.deepseek_api_key.as_deref()),
+    );
+    [("ollama", Some(ollama)), ("groq", groq), ("gemini", gemini), ("deepseek", deepseek)]
+        .into_iter()
+        .filter_map(|(provider, result)| result.map(|r| (provider, r)))
+        .collect()
+}
+
+async fn get_json(request: reqwest::RequestBuilder) -> Result<Value, String> {
+    let resp = request.send().await.map_err(|e| e.to_string())?;
+    let status = resp.status();
+    let text = resp.text().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("HTTP {}", status));
+    }
+    serde_json::from_str(&text).map_err(|e| e.to_string())
+}
+
+fn ids(json: &Value, list: &str, field: &str) -> Vec<String> {
+    json[list]
+        .as_array()
+        .map(|items| items.iter().filter_map(|m| m[field].as_str().map(String::from)).collect())
+        .unwrap_or_default()
+}
+
+async fn fetch_ollama(client: &Client, config: &Config) -> Result<Vec<String>, String> {
+    let url = config.ollama_url.as_deref().unwrap_or("http://localhost:11434");
+    let json = get_json(client.get(format!("{}/api/tags", url.trim_end_matches('/')))).await?;
+    Ok(ids(&json, "models", "name"))
+}
+
+async fn fetch_openai_style(client: &Client, base_url: &str, key: Option<&str>) -> Option<Result<Vec<String>, String>> {
+    let key = key.filter(|k| !k.trim().is_empty())?;
+    let json = match get_json(client.get(format!("{}/models", base_url)).bearer_auth(key)).await {
+        Ok(json) => json,
+        Err(e) => return Some(Err(e)),
+    };
+    // Speech models (Groq's Whisper and TTS) cannot answer chat prompts
+    let chat = |id: &String| !id.contains("whisper") && !id.contains("tts");
+    Some(Ok(ids(&json, "data", "id").into_iter().filter(chat).collect()))
+}
+
+async fn fetch_gemini(client: &Client, key: Option<&str>) -> Option<Result<Vec<String>, String>> {
+    let key = key.filter(|k| !k.trim().is_empty())?;
+    let url = "https://generativelanguage.googleapis.com/v1beta/models?pageSize=1000";
+    let json = match get_json(client.get(url).query(&[("key", key)])).await {
+        Ok(json) => json,
+        Err(e) => return Some(Err(e)),
+    };
+    let generates = |m: &&Value| {
+        m["supportedGenerationMethods"].as_array().is_some_and(|methods| methods.iter().any(|x| x == "generateContent"))
+    };
+    let models = json["models"].as_array().map(Vec::as_slice).unwrap_or_default();
+    Some(Ok(models
+        .iter()
+        .filter(generates)
+        .filter_map(|m| m["name"].as_str())
+        .map(|name| name.trim_start_matches("models/").to_string())
+        .collect()))
+}
+
+fn validate(model: &ModelInfo) -> Result<(), String> {
+    if model.id.trim().is_empty() {
+        return Err("\"id\" is empty".into());
+    }
+    if !PROVIDERS.contains(&model.provider.as_str()) {
+        return Err(format!("unknown provider '{}' (valid: {})", model.provider, PROVIDERS.join(", ")));
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use crate::transcript::Turn;
+use std::time::Duration;
+
+const PROMPT_PREVIEW_CHARS: usize = 60;
+
+// Only a summary and the truncated first prompt line leave the process, never file contents
+pub fn prompt_finished(turn: &Turn, elapsed: Duration, success: bool) {
+    let created = turn.tools.iter().filter(|t| t.success && t.action == "create_file").count();
+    let failed = turn.tools.iter().filter(|t| !t.success).count();
+
+    let mut summary = vec![if success { "done".to_string() } else { "failed".to_string() }];
+    if created > 0 {
+        summary.push(format!("{} file(s) created", created));
+    }
+    if failed > 0 {
+        summary.push(format!("{} action(s) failed", failed));
+    }
+    summary.push(format!("{}s", elapsed.as_secs()));
+
+    let first_line = turn.prompt.lines().next().unwrap_or_default();
+    let mut preview: String = first_line.chars().take(PROMPT_PREVIEW_CHARS).collect();
+    if first_line.chars().count() > PROMPT_PREVIEW_CHARS {
+        preview.push('…');
+    }
+
+    let summary = format!("clio-ai: {}", summary.join(", "));
+    // Notification daemons can block or be missing entirely; never hold up the REPL
+    std::thread::spawn(move || {
+        let _ = notify_rust::Notification::new()
+            .appname("clio-ai")
+            .summary(&summary)
+            .body(&preview)
+            .show();
+    });
+}
@@ -0,0 +1,100 @@
+use regex::Regex;
+use std::path::Path;
+
+// Lines kept from each end of a file no extractor understands
+const EDGE_LINES: usize = 20;
+// Longest declaration line shown; the rest of a long signature is elided
+const MAX_LINE_CHARS: usize = 160;
+
+// Declaration patterns per language, matched against each line
+const EXTRACTORS: &[(&[&str], &str)] = &[
+    (
+        &["rs"],
+        r"^\s*(pub(\([^)]*\))?\s+)?((async|const|unsafe|extern\s+\S+)\s+)*(fn|struct|enum|trait|impl|mod|type|union|macro_rules!)[\s<{!]",
+    ),
+    (&["py", "pyi"], r"^\s*((async\s+)?def|class)\s+\w+"),
+    (
+        &["js", "jsx", "mjs", "cjs", "ts", "tsx"],
+        r"^\s*(export\s+(default\s+)?)?((async\s+)?function\*?\s*\w*|class\s+\w+|interface\s+\w+|type\s+\w+\s*=|enum\s+\w+|(const|let)\s+\w+\s*=\s*(async\s*)?(\([^)]*\)|\w+)\s*=>)",
+    ),
+    (&["go"], r"^(func|type)\s"),
+    (&["rb"], r"^\s*(def|class|module)\s"),
+    (
+        &["java", "kt", "cs", "scala"],
+        r"^\s*((public|private|protected|internal|static|final|abstract|sealed|open|override|data|suspend|async|virtual)\s+)*(class|interface|enum|record|object|fun|def)\s+\w+|^\s+(public|private|protected|internal)\s+[\w<>\[\],.? ]+\s+\w+\s*\(",
+    ),
+    (&["c", "h", "cc", "cpp", "hpp"], r"^(struct|enum|union|class|typedef|namespace)\b|^[A-Za-z_][\w\s\*&:<>,]*\s\**\w+\s*\([^;]*$"),
+    (&["sh", "bash", "zsh"], r"^\s*(function\s+\w+|\w+\s*\(\)\s*\{?)"),
+];
+
+// A condensed view of a file: declarations for code, the heading outline for Markdown,
+// otherwise the first and last lines
+pub fn summarize(path: &Path, text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let header = format!("{} lines", lines.len());
+
+    let outline = match ext.as_str() {
+        "md" | "markdown" => markdown_headings(&lines),
+        _ => EXTRACTORS
+            .iter()
+            .find(|(exts, _)| exts.contains(&ext.as_str()))
+            .map(|(_, pattern)| declarations(&lines, pattern))
+            .unwrap_or_default(),
+    };
+    if !outline.is_empty() {
+        return format!("{}; {} outline entries (line: text):\n{}", header, outline.len(), outline.join("\n"));
+    }
+
+    if lines.len() <= EDGE_LINES * 2 {
+        return format!("{}; short enough to show whole:\n{}", header, numbered(&lines, 0).join("\n"));
+    }
+    format!(
+        "{}; first and last {}:\n{}\n... {} lines omitted ...\n{}",
+        header,
+        EDGE_LINES,
+        numbered(&lines[..EDGE_LINES], 0).join("\n"),
+        lines.len() - EDGE_LINES * 2,
+        numbered(&lines[lines.len() - EDGE_LINES..], lines.len() - EDGE_LINES).join("\n")
+    )
+}
+
+fn declarations(lines: &[&str], pattern: &str) -> Vec<String> {
+    let Ok(re) = Regex::new(pattern) else {
+        return Vec::new();
+    };
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| re.is_match(line))
+        .map(|(i, line)| format!("{}: {}", i + 1, signature(line)))
+        .collect()
+}
+
+// The declaration without its body: cut at the opening brace, keeping indentation for nesting
+fn signature(line: &str) -> String {
+    let line = line.trim_end();
+    let line = line.strip_suffix('{').unwrap_or(line).trim_end();
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((cut, _)) => format!("{}...", &line[..cut]),
+        None => line.to_string(),
+    }
+}
+
+fn markdown_headings(lines: &[&str]) -> Vec<String> {
+    let mut in_fence = false;
+    let mut headings = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if !in_fence && trimmed.starts_with('#') && trimmed.trim_start_matches('#').starts_with(' ') {
+            headings.push(format!("{}: {}", i + 1, trimmed.trim_end()));
+        }
+    }
+    headings
+}
+
+fn numbered(lines: &[&str], offset: usize) -> Vec<String> {
+    lines.iter().enumerate().map(|(i, line)| format!("{}: {}", offset + i + 1, line)).collect()
+}
@@ -0,0 +1,36 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+// Incremental output (streamed tokens) without a trailing newline
+pub fn progress(fragment: &str) {
+    if !is_quiet() {
+        use std::io::Write;
+        print!("{}", fragment);
+        std::io::stdout().flush().ok();
+    }
+}
+
+// Decorations and progress: banners, tool echo, stats. Suppressed in quiet mode.
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+// Warnings always reach the user, on stderr so they never mix with piped output
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        eprintln!($($arg)*)
+    };
+}
@@ -0,0 +1,171 @@
+// Minimal unified diff support: parse `---`/`+++`/`@@` sections and apply them with exact context
+#[derive(Debug)]
+pub struct FilePatch {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    hunks: Vec<Hunk>,
+}
+
+#[derive(Debug)]
+struct Hunk {
+    old_start: usize,
+    lines: Vec<(char, String)>,
+}
+
+impl FilePatch {
+    // The file the patch reads from and writes to; /dev/null sides are None
+    pub fn target(&self) -> Option<&str> {
+        self.new_path.as_deref().or(self.old_path.as_deref())
+    }
+
+    pub fn is_delete(&self) -> bool {
+        self.new_path.is_none()
+    }
+}
+
+fn strip_path(raw: &str) -> Option<String> {
+    // Drop trailing timestamps ("file\t2024-01-01 ...") and the git a/ b/ prefixes
+    let path = raw.split('\t').next().unwrap_or("").trim();
+    if path == "/dev/null" || path.is_empty() {
+        return None;
+    }
+    let path = path.strip_prefix("a/").or_else(|| path.strip_prefix("b/")).unwrap_or(path);
+    Some(path.to_string())
+}
+
+// "12,3" -> (12, 3); a bare "12" is one line
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let (start, count) = range.split_once(',').unwrap_or((range, "1"));
+    Some((start.parse().ok()?, count.parse().ok()?))
+}
+
+pub fn parse(text: &str) -> Result<Vec<FilePatch>, String> {
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut lines = text.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if let Some(old) = line.strip_prefix("--- ") {
+            let new = lines
+                .next()
+                .and_then(|l| l.strip_prefix("+++ "))
+                .ok_or_else(|| format!("Expected '+++' after '{}'", line))?;
+            patches.push(FilePatch {
+                old_path: strip_path(old),
+                new_path: strip_path(new),
+                hunks: Vec::new(),
+            });
+        } else if let Some(header) = line.strip_prefix("@@ ") {
+            let patch = patches
+                .last_mut()
+                .ok_or("Hunk found before any '---'/'+++' file header")?;
+            let malformed = || format!("Malformed hunk header: {}", line);
+            let mut ranges = header.split_whitespace();
+            let (old_start, mut old_left) =
+                ranges.next().and_then(|r| r.strip_prefix('-')).and_then(parse_range).ok_or_else(malformed)?;
+            let (_, mut new_left) = ranges.next().and_then(|r| r.strip_prefix('+')).and_then(parse_range).ok_or_else(malformed)?;
+
+            // While the header's line counts last, a line is part of the hunk even when it reads like a
+            // file header (a removed "-- comment" is "--- comment"). Past them, hunk lines are still taken
+            // up to the next header, since model-written diffs often miscount
+            let mut hunk = Hunk { old_start, lines: Vec::new() };
+            while let Some(next) = lines.peek() {
+                let counted = old_left > 0 || new_left > 0;
+                if next.starts_with("@@ ") || next.starts_with("diff ") || (!counted && next.starts_with("--- ")) {
+                    break;
+                }
+                let next = lines.next().unwrap_or_default();
+                match next.chars().next() {
+                    Some(kind @ (' ' | '-' | '+')) => {
+                        if kind != '+' {
+                            old_left = old_left.saturating_sub(1);
+                        }
+                        if kind != '-' {
+                            new_left = new_left.saturating_sub(1);
+                        }
+                        hunk.lines.push((kind, next[1..].to_string()))
+                    }
+                    // Some tools emit bare empty lines for empty context lines
+                    None => {
+                        old_left = old_left.saturating_sub(1);
+                        new_left = new_left.saturating_sub(1);
+                        hunk.lines.push((' ', String::new()))
+                    }
+                    // "\ No newline at end of file"
+                    Some('\\') => {}
+                    Some(_) => break,
+                }
+            }
+            patch.hunks.push(hunk);
+        }
+    }
+
+    if patches.is_empty() {
+        return Err("No file headers ('---'/'+++') found in diff".into());
+    }
+    if let Some(p) = patches.iter().find(|p| p.target().is_none()) {
+        return Err(format!("Patch has no usable file path: {:?}", p));
+    }
+    Ok(patches)
+}
+
+// Returns the patched content, or an error naming the first hunk whose context does not match
+pub fn apply(original: &str, patch: &FilePatch) -> Result<String, String> {
+    let trailing_newline = original.is_empty() || original.ends_with('\n');
+    let source: Vec<&str> = original.lines().collect();
+    let mut out: Vec<String> = Vec::new();
+    let mut pos = 0;
+
+    for (n, hunk) in patch.hunks.iter().enumerate() {
+        let old: Vec<&str> = hunk
+            .lines
+            .iter()
+            .filter(|(kind, _)| *kind != '+')
+            .map(|(_, text)| text.as_str())
+            .collect();
+
+        // Prefer the stated line, then search outward for the same context (never before pos)
+        let wanted = hunk.old_start.saturating_sub(1).max(pos);
+        let matches_at = |at: usize| at + old.len() <= source.len() && source[at..at + old.len()] == old[..];
+        let start = (0..source.len().saturating_sub(pos) + 1)
+            .flat_map(|offset| [wanted + offset, wanted.wrapping_sub(offset)])
+            .filter(|at| *at >= pos && *at <= source.len())
+            .find(|at| matches_at(*at))
+            .ok_or_else(|| format!("Hunk {} does not apply cleanly (context mismatch near line {})", n + 1, hunk.old_start))?;
+
+        out.extend(source[pos..start].iter().map(|l| l.to_string()));
+        out.extend(hunk.lines.iter().filter(|(kind, _)| *kind != '-').map(|(_, text)| text.clone()));
+        pos = start + old.len();
+    }
+    out.extend(source[pos..].iter().map(|l| l.to_string()));
+
+    let mut result = out.join("\n");
+    if trailing_newline && !result.is_empty() {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_lines_that_look_like_file_headers_stay_in_the_hunk() {
+        let diff = "--- a/schema.sql\n+++ b/schema.sql\n@@ -1,3 +1,2 @@\n--- users\n--- old note\n+-- accounts\n create table users (id int);\n--- a/README.md\n+++ b/README.md\n@@ -1 +1 @@\n-old\n+new\n";
+        let patches = parse(diff).unwrap();
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].target(), Some("schema.sql"));
+        assert_eq!(patches[1].target(), Some("README.md"));
+
+        let sql = "-- users\n-- old note\ncreate table users (id int);\n";
+        assert_eq!(apply(sql, &patches[0]).unwrap(), "-- accounts\ncreate table users (id int);\n");
+        assert_eq!(apply("old\n", &patches[1]).unwrap(), "new\n");
+    }
+
+    #[test]
+    fn undercounted_hunks_keep_their_remaining_lines() {
+        let diff = "--- a/x.txt\n+++ b/x.txt\n@@ -1,1 +1,1 @@\n a\n-b\n+c\n";
+        let patches = parse(diff).unwrap();
+        assert_eq!(apply("a\nb\n", &patches[0]).unwrap(), "a\nc\n");
+    }
+}
@@ -0,0 +1,96 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// New files go to the platform directories from the dirs crate (XDG on Linux, ~/Library on macOS,
+// %APPDATA% on Windows); ~/.clio-ai from older versions is still read where a file already exists there
+const APP: &str = "clio-ai";
+
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP))
+}
+
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(APP))
+}
+
+// History and sessions. Only Linux has a state dir; elsewhere the local data dir stands in
+pub fn state_dir() -> Option<PathBuf> {
+    dirs::state_dir().or_else(dirs::data_local_dir).map(|dir| dir.join(APP))
+}
+
+pub fn legacy_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".clio-ai"))
+}
+
+// A file under the state dir, or its ~/.clio-ai twin while only that one exists
+pub fn state_file(name: &str) -> Option<PathBuf> {
+    let new = state_dir()?.join(name);
+    match legacy_dir().map(|dir| dir.join(name)) {
+        Some(old) if old.exists() && !new.exists() => Some(old),
+        _ => Some(new),
+    }
+}
+
+// Writes a temp file unique to this process and renames it over `path`, so a crash never leaves a torn
+// file and two running instances never share a temp file. The temp file starts owner-only and gets `mode`
+// once written (Unix), so the content is never readable by more than that, not even before the rename
+pub fn write_atomic(path: &Path, bytes: &[u8], mode: u32) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let temp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    let _ = fs::remove_file(&temp);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let written = options.open(&temp).and_then(|mut file| {
+        file.write_all(bytes)?;
+        #[cfg(unix)]
+        if mode != 0o600 {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(mode))?;
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+        Ok(())
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(format!("{}: {}", temp.display(), e));
+    }
+    fs::rename(&temp, path).map_err(|e| {
+        let _ = fs::remove_file(&temp);
+        format!("{}: {}", path.display(), e)
+    })
+}
+
+// Shown once when ~/.clio-ai exists; a marker in the state dir remembers that it was
+pub fn legacy_notice() -> Option<String> {
+    let legacy = legacy_dir().filter(|dir| dir.is_dir())?;
+    let state = state_dir()?;
+    let marker = state.join("legacy-notice");
+    if marker.exists() {
+        return None;
+    }
+    fs::create_dir_all(&state).ok()?;
+    fs::write(&marker, "").ok()?;
+    Some(format!(
+        "clio-ai now keeps config in {}, history and sessions in {} and caches in {}. Files in {} are still read; move them when convenient.",
+        config_dir()?.display(),
+        state.display(),
+        cache_dir()?.display(),
+        legacy.display()
+    ))
+}
+
+// An empty directory for one test, under the system temp dir and canonical so path checks match
+#[cfg(test)]
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("clio-ai-test-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
@@ -0,0 +1,83 @@
+use crate::config::Config;
+use crate::tools::{self, ToolCall};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+// What happens to one tool call. Every layer (config.toml, .clio.toml, /mode, /set) ends up in
+// blocked_actions, confirm_actions and protected_paths, so those three are all this reads
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    Allow,
+    Confirm,
+    Deny(String),
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decision::Allow => write!(f, "allow"),
+            Decision::Confirm => write!(f, "confirm"),
+            Decision::Deny(_) => write!(f, "deny"),
+        }
+    }
+}
+
+// The decision for an action regardless of its paths
+pub fn for_action(config: &Config, action: &str) -> Decision {
+    if config.blocked_actions.iter().any(|a| a == action) {
+        return Decision::Deny(format!(
+            "Denied by tool policy: '{}' is not allowed (blocked_actions from {})",
+            action,
+            config.source("blocked_actions")
+        ));
+    }
+    if config.confirm_actions.iter().any(|a| a == action) {
+        Decision::Confirm
+    } else {
+        Decision::Allow
+    }
+}
+
+pub fn resolve(config: &Config, tool: &ToolCall, root: &Path) -> Decision {
+    let decision = for_action(config, &tool.action);
+    if matches!(decision, Decision::Deny(_)) {
+        return decision;
+    }
+    let protected = tools::mutated_paths(tool, root).into_iter().find_map(|path| {
+        let pattern = config.protected_pattern(root, &tools::resolve_path(root, &path)?)?;
+        Some((path, pattern))
+    });
+    match protected {
+        Some((path, pattern)) => Decision::Deny(format!(
+            "Denied by tool policy: '{}' is protected (pattern '{}' from {}); leave it unchanged",
+            path,
+            pattern,
+            config.source("protected_paths")
+        )),
+        None => decision,
+    }
+}
+
+// [tools] delete = "confirm", create_file = "allow", apply_diff = "deny": each rule moves its action
+// between the two lists. Anything that is not an action name is reported like any unknown key
+pub fn apply_rules(path: &Path, rules: &BTreeMap<String, toml::Value>, blocked: &mut Vec<String>, confirm: &mut Vec<String>) {
+    for (action, value) in rules {
+        if !tools::TOOLS.iter().any(|(name, _)| name == action) {
+            warn!("Warning: unknown key 'tools.{}' in {}", action, path.display());
+            continue;
+        }
+        let rule = value.as_str().unwrap_or_default();
+        if !["allow", "confirm", "deny"].contains(&rule) {
+            warn!("Warning: tools.{} in {} must be \"allow\", \"confirm\" or \"deny\"", action, path.display());
+            continue;
+        }
+        blocked.retain(|a| a != action);
+        confirm.retain(|a| a != action);
+        match rule {
+            "deny" => blocked.push(action.clone()),
+            "confirm" => confirm.push(action.clone()),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectType {
+    Rust,
+    Node,
+    Python,
+    Go,
+    Java,
+    Ruby,
+}
+
+// Checked in order, so a repo with both Cargo.toml and package.json counts as Rust
+const MARKERS: &[(&str, ProjectType)] = &[
+    ("Cargo.toml", ProjectType::Rust),
+    ("package.json", ProjectType::Node),
+    ("pyproject.toml", ProjectType::Python),
+    ("requirements.txt", ProjectType::Python),
+    ("setup.py", ProjectType::Python),
+    ("go.mod", ProjectType::Go),
+    ("pom.xml", ProjectType::Java),
+    ("build.gradle", ProjectType::Java),
+    ("build.gradle.kts", ProjectType::Java),
+    ("Gemfile", ProjectType::Ruby),
+];
+
+pub fn detect_project_type(cwd: &Path) -> Option<ProjectType> {
+    MARKERS.iter().find(|(file, _)| cwd.join(file).is_file()).map(|(_, kind)| *kind)
+}
+
+impl ProjectType {
+    pub fn name(self) -> &'static str {
+        match self {
+            ProjectType::Rust => "Rust",
+            ProjectType::Node => "Node.js",
+            ProjectType::Python => "Python",
+            ProjectType::Go => "Go",
+            ProjectType::Java => "Java",
+            ProjectType::Ruby => "Ruby",
+        }
+    }
+
+    // Build/test commands and what a CI cache should hold, keyed on the lockfile
+    pub fn ci_hint(self) -> &'static str {
+        match self {
+            ProjectType::Rust => "Run cargo build and cargo test. Cache ~/.cargo/registry, ~/.cargo/git and target/, keyed on Cargo.lock.",
+            ProjectType::Node => {
+                "Install with the package manager the lockfile belongs to (npm ci, yarn install --frozen-lockfile or pnpm install --frozen-lockfile), \
+                 then run the test script. Cache that package manager's download cache, keyed on the lockfile."
+            }
+            ProjectType::Python => {
+                "Install dependencies with pip (from requirements.txt or pyproject.toml) and run pytest. Cache ~/.cache/pip, keyed on those files."
+            }
+            ProjectType::Go => "Run go build ./... and go test ./.... Cache ~/go/pkg/mod and ~/.cache/go-build, keyed on go.sum.",
+            ProjectType::Java => {
+                "Build with Maven (mvn -B verify) or Gradle (./gradlew build), whichever build file exists. \
+                 Cache ~/.m2/repository or ~/.gradle/caches, keyed on the build files."
+            }
+            ProjectType::Ruby => "Run bundle install and the test suite (bundle exec rake test or rspec). Cache vendor/bundle, keyed on Gemfile.lock.",
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::config::Config;
+use crate::interrupt::Interrupt;
+use crate::{confirm, output, style};
+use serde_json::{json, Value};
+use std::io::{self, IsTerminal, Write};
+
+const BAR_WIDTH: usize = 30;
+
+// Ollama's answer to /api/generate for a model that was never pulled
+pub fn is_missing_model(config: &Config, error: &str) -> bool {
+    config.provider == "ollama" && error.contains("not found") && error.contains("pull")
+}
+
+// OLLAMA_AUTO_PULL decides whether the active model is pulled; true once it is available.
+// "ask" only asks when `interactive`
+pub async fn offer(config: &Config, interrupt: &Interrupt, interactive: bool) -> bool {
+    let model = &config.model;
+    let approved = match config.ollama_auto_pull.as_str() {
+        "always" => true,
+        "ask" if interactive => confirm(&format!("Ollama does not have {} yet. Pull it now?", model)),
+        _ => false,
+    };
+    if !approved {
+        warn!("{}", style::dim(&format!("Pull it with: ollama pull {}", model)));
+        return false;
+    }
+
+    let result = tokio::select! {
+        result = pull(config) => result,
+        _ = interrupt.cancelled() => Err("cancelled".into()),
+    };
+    draw("");
+    match result {
+        Ok(()) => {
+            info!("{} {}", style::green("✓ Pulled"), model);
+            true
+        }
+        Err(e) => {
+            warn!("{} {}: {}", style::yellow("Pull of"), model, e);
+            false
+        }
+    }
+}
+
+// POST /api/pull streams one JSON status line per step, with byte counts while layers download
+async fn pull(config: &Config) -> Result<(), String> {
+    let url = config.ollama_url.as_deref().unwrap_or("http://localhost:11434");
+    let mut resp = reqwest::Client::new()
+        .post(format!("{}/api/pull", url.trim_end_matches('/')))
+        .json(&json!({"model": config.model, "stream": true}))
+        .send()
+        .await
+        .map_err(|e| format!("Ollama connection error: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {}", resp.status()));
+    }
+
+    let mut pending: Vec<u8> = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(|e| e.to_string())? {
+        pending.extend_from_slice(&chunk);
+        while let Some(pos) = pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=pos).collect();
+            let Ok(json) = serde_json::from_slice::<Value>(&line) else {
+                continue;
+            };
+            if let Some(err) = json["error"].as_str() {
+                return Err(err.to_string());
+            }
+            match json["status"].as_str().unwrap_or("") {
+                "success" => return Ok(()),
+                status => draw(&status_line(status, json["completed"].as_u64(), json["total"].as_u64())),
+            }
+        }
+    }
+    Err("the download ended before Ollama reported success".into())
+}
+
+// "pulling 6a0746a1ec1a [██████░░░░]  20%  412/2019 MB"
+fn status_line(status: &str, completed: Option<u64>, total: Option<u64>) -> String {
+    let status: String = status.chars().take(24).collect();
+    match (completed, total) {
+        (Some(done), Some(total)) if total > 0 => {
+            let done = done.min(total);
+            let filled = (done * BAR_WIDTH as u64 / total) as usize;
+            format!(
+                "{} [{}{}] {:>3}%  {}/{} MB",
+                status,
+                "█".repeat(filled),
+                "░".repeat(BAR_WIDTH - filled),
+                done * 100 / total,
+                done / 1_000_000,
+                total / 1_000_000
+            )
+        }
+        _ => status,
+    }
+}
+
+// Redraws the progress line in place; an empty line clears it
+fn draw(line: &str) {
+    if output::is_quiet() || !io::stdout().is_terminal() {
+        return;
+    }
+    print!("\r\x1b[2K{}", line);
+    io::stdout().flush().ok();
+}
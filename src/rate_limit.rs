@@ -0,0 +1,43 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Sliding-window counter for tool executions; a limit of 0 disables it
+#[derive(Debug)]
+pub struct RateLimiter {
+    max: usize,
+    window: Duration,
+    hits: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            window: Duration::from_secs(60),
+            hits: VecDeque::new(),
+        }
+    }
+
+    // How long to wait before the next execution is allowed, if at all
+    pub fn wait_time(&mut self) -> Option<Duration> {
+        if self.max == 0 {
+            return None;
+        }
+        let now = Instant::now();
+        while self.hits.front().is_some_and(|t| now.duration_since(*t) >= self.window) {
+            self.hits.pop_front();
+        }
+        if self.hits.len() < self.max {
+            return None;
+        }
+        self.hits.front().map(|oldest| self.window.saturating_sub(now.duration_since(*oldest)))
+    }
+
+    pub fn record(&mut self) {
+        self.hits.push_back(Instant::now());
+    }
+
+    pub fn reset(&mut self) {
+        self.hits.clear();
+    }
+}
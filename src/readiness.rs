@@ -0,0 +1,136 @@
+use crate::config::{Config, Problem, PROVIDERS};
+use crate::llm::LLM;
+use crate::style;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub struct Report {
+    pub lines: Vec<String>,
+    // Set when the active provider cannot work as configured
+    pub problem: Option<String>,
+}
+
+// Pure over Config; the Ollama reachability is passed in (None = not checked)
+pub fn report(config: &Config, ollama_reachable: Option<bool>) -> Report {
+    let mark = |ok: bool| if ok { style::green("✓") } else { style::red("✗") };
+
+    let providers: Vec<String> = PROVIDERS
+        .iter()
+        .map(|provider| {
+            let status = match (*provider, ollama_reachable) {
+                // Only probed when it is the active provider
+                ("ollama", None) => style::dim("-"),
+                ("ollama", Some(ok)) => mark(ok),
+                _ => mark(config.has_key(provider)),
+            };
+            format!("{} {}", provider, status)
+        })
+        .collect();
+
+    let source = match &config.env_file {
+        Some(path) => path.display().to_string(),
+        None => "process environment (no .env found)".into(),
+    };
+
+    let problem = match (config.provider.as_str(), config.key_var(&config.provider)) {
+        ("ollama", _) if ollama_reachable == Some(false) => Some(format!(
+            "Ollama is not responding at {}; start it or set OLLAMA_URL",
+            config.ollama_url.as_deref().unwrap_or("http://localhost:11434")
+        )),
+        (provider, Some(var)) if !config.has_key(provider) => Some(format!(
+            "{} not set; add to .env or {}",
+            var,
+            Config::env_paths()
+                .first()
+                .map(|p| p.display().to_string())
+                .unwrap_or(".env".into())
+        )),
+        _ => None,
+    };
+
+    Report {
+        lines: vec![
+            format!("Providers: {}", providers.join("  ")),
+            format!("Active:    {} ({}) {}", config.model, config.provider, mark(problem.is_none())),
+            format!("Config:    {}", source),
+        ],
+        problem,
+    }
+}
+
+// Prints each problem with the setting it concerns, where that value came from and how to fix it; true if any is an error
+pub fn print_problems(config: &Config, problems: &[Problem]) -> bool {
+    for p in problems {
+        let label = if p.error { style::red("Error:") } else { style::yellow("Warning:") };
+        let source = match config.sources.get(p.setting) {
+            Some(source) => format!(" (from {})", source),
+            None => String::new(),
+        };
+        warn!("{} {}{}: {}", label, p.setting, source, p.message);
+        warn!("  {} {}", style::dim("fix:"), p.fix);
+    }
+    problems.iter().any(|p| p.error)
+}
+
+pub const CONNECTION_TEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+// CLIO_TEST_CONNECTION: a tiny prompt through the active provider, reported before the banner
+pub async fn test_connection(llm: &LLM) {
+    let model = &llm.config().model;
+    match tokio::time::timeout(CONNECTION_TEST_TIMEOUT, llm.test_connection()).await {
+        Ok(Ok(())) => info!("{} Connected to {}", style::green("✓"), model),
+        Ok(Err(e)) => warn!("{} Failed: {}", style::red("✗"), e),
+        Err(_) => warn!("{} Failed: no answer from {} within {}s", style::red("✗"), model, CONNECTION_TEST_TIMEOUT.as_secs()),
+    }
+}
+
+pub async fn ping_ollama(config: &Config) -> bool {
+    let url = config.ollama_url.as_deref().unwrap_or("http://localhost:11434");
+    let Ok(client) = reqwest::Client::builder().timeout(Duration::from_millis(500)).build() else {
+        return false;
+    };
+    client
+        .get(format!("{}/api/version", url.trim_end_matches('/')))
+        .send()
+        .await
+        .is_ok_and(|resp| resp.status().is_success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_key_for_the_active_provider_is_the_problem() {
+        let mut config = Config::defaults();
+        config.provider = "groq".into();
+        config.groq_api_key = None;
+        let report = report(&config, None);
+        let var = config.key_var("groq").unwrap();
+        assert!(report.problem.as_deref().unwrap().starts_with(&format!("{} not set", var)));
+        assert!(report.lines[0].starts_with("Providers: "));
+
+        config.groq_api_key = Some("gsk_test".into());
+        assert!(report_problem(&config, None).is_none());
+    }
+
+    #[test]
+    fn unreachable_ollama_is_only_a_problem_when_it_is_active() {
+        let mut config = Config::defaults();
+        config.provider = "ollama".into();
+        config.ollama_url = Some("http://localhost:9".into());
+        assert!(report_problem(&config, Some(false)).unwrap().contains("http://localhost:9"));
+        assert!(report_problem(&config, Some(true)).is_none());
+        // Not checked (e.g. --no-check) is not reported as down
+        assert!(report_problem(&config, None).is_none());
+
+        config.provider = "gemini".into();
+        config.gemini_api_key = Some("key".into());
+        assert!(report_problem(&config, Some(false)).is_none());
+        assert!(report(&config, None).lines[0].contains("ollama -"));
+    }
+
+    fn report_problem(config: &Config, ollama: Option<bool>) -> Option<String> {
+        report(config, ollama).problem
+    }
+}
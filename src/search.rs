@@ -0,0 +1,128 @@
+use crate::clioignore;
+use globset::{Glob, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+use std::fs;
+use std::path::Path;
+
+// Directories never worth searching (build output, VCS metadata, dependencies)
+pub const IGNORED_DIRS: &[&str] = &[".git", "target", "node_modules"];
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const MAX_LINE_CHARS: usize = 200;
+
+pub struct SearchOptions {
+    pub pattern: String,
+    pub case_insensitive: bool,
+    pub glob: Option<String>,
+    pub max_results: usize,
+}
+
+pub struct Match {
+    pub path: String,
+    pub line: usize,
+    pub text: String,
+}
+
+pub struct SearchResult {
+    pub regex: Regex,
+    pub matches: Vec<Match>,
+    pub truncated: bool,
+}
+
+impl SearchResult {
+    // Plain "path:line: text" listing, used for the model and for /grep --attach
+    pub fn to_text(&self) -> String {
+        let mut out: Vec<String> = self
+            .matches
+            .iter()
+            .map(|m| format!("{}:{}: {}", m.path, m.line, m.text))
+            .collect();
+        out.push(self.summary());
+        out.join("\n")
+    }
+
+    pub fn summary(&self) -> String {
+        if self.truncated {
+            format!("{} matches (stopped at the limit)", self.matches.len())
+        } else {
+            format!("{} matches", self.matches.len())
+        }
+    }
+}
+
+// Searches `root` recursively; reported paths are relative to `base`
+pub fn search(root: &Path, base: &Path, opts: &SearchOptions) -> Result<SearchResult, String> {
+    let regex = RegexBuilder::new(&opts.pattern)
+        .case_insensitive(opts.case_insensitive)
+        .build()
+        .map_err(|e| format!("Invalid pattern: {}", e))?;
+    let glob = match &opts.glob {
+        Some(glob) => Some(Glob::new(glob).map_err(|e| format!("Invalid glob: {}", e))?.compile_matcher()),
+        None => None,
+    };
+
+    let base = base.canonicalize().unwrap_or(base.to_path_buf());
+    let mut result = SearchResult { regex, matches: Vec::new(), truncated: false };
+    if root.is_file() {
+        search_file(root, &base, &mut result, opts.max_results);
+    } else {
+        walk(root, &base, glob.as_ref(), &mut result, opts.max_results);
+    }
+    Ok(result)
+}
+
+fn walk(dir: &Path, base: &Path, glob: Option<&GlobMatcher>, result: &mut SearchResult, max: usize) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        if result.truncated {
+            return;
+        }
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else { continue };
+        if clioignore::is_ignored(base, &path, file_type.is_dir()) {
+            continue;
+        }
+        if file_type.is_dir() {
+            if !IGNORED_DIRS.iter().any(|d| entry.file_name() == *d) {
+                walk(&path, base, glob, result, max);
+            }
+        } else if file_type.is_file() {
+            let rel = path.strip_prefix(base).unwrap_or(&path);
+            // Match the glob against either the relative path or just the file name
+            if glob.is_some_and(|g| !g.is_match(rel) && !g.is_match(entry.file_name())) {
+                continue;
+            }
+            search_file(&path, base, result, max);
+        }
+    }
+}
+
+fn search_file(path: &Path, base: &Path, result: &mut SearchResult, max: usize) {
+    if fs::metadata(path).map(|m| m.len() > MAX_FILE_BYTES).unwrap_or(true) {
+        return;
+    }
+    let Ok(bytes) = fs::read(path) else { return };
+    // Skip binaries the same way grep does: a NUL byte near the start
+    if crate::tools::is_binary(&bytes) {
+        return;
+    }
+    let text = String::from_utf8_lossy(&bytes);
+    let display = path.strip_prefix(base).unwrap_or(path).to_string_lossy().replace('\\', "/");
+
+    for (i, line) in text.lines().enumerate() {
+        if !result.regex.is_match(line) {
+            continue;
+        }
+        if result.matches.len() >= max {
+            result.truncated = true;
+            return;
+        }
+        let mut line = line.trim_end().to_string();
+        if line.chars().count() > MAX_LINE_CHARS {
+            line = line.chars().take(MAX_LINE_CHARS).collect::<String>() + "…";
+        }
+        result.matches.push(Match { path: display.clone(), line: i + 1, text: line });
+    }
+}
@@ -0,0 +1,191 @@
+use crate::commands::change_dir;
+use crate::{process_prompt, style, Session};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+// JSON-RPC error codes; -32000 is the server-defined "the prompt itself failed"
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const PROMPT_FAILED: i32 = -32000;
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[cfg(windows)]
+fn default_address() -> Option<String> {
+    Some(r"\\.\pipe\clio-ai".to_string())
+}
+
+#[cfg(unix)]
+fn default_address() -> Option<String> {
+    let dir = dirs::runtime_dir().map(|dir| dir.join("clio-ai")).or_else(crate::paths::state_dir)?;
+    Some(dir.join("clio-ai.sock").to_string_lossy().to_string())
+}
+
+// Serve prompts from editor extensions over a Unix socket or Windows named pipe until Ctrl+C.
+// Clients are handled one at a time and share this session's conversation
+pub async fn run(session: &mut Session, address: &str) -> Result<(), String> {
+    let address = match address {
+        "" => default_address().ok_or("No default socket location; pass --server PATH")?,
+        address => address.to_string(),
+    };
+    listen(session, &address).await?;
+    info!("Server stopped.");
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn listen(session: &mut Session, address: &str) -> Result<(), String> {
+    use std::fs;
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+    use std::path::Path;
+    use tokio::net::{UnixListener, UnixStream};
+
+    let path = Path::new(address);
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        // Only a socket is ever removed here; any other file at the path is the user's
+        if !metadata.file_type().is_socket() {
+            return Err(format!("{} exists and is not a socket", path.display()));
+        }
+        // A socket left behind by a crashed server refuses connections; a live one belongs to another server
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(format!("{} is in use by another server", path.display()));
+        }
+        fs::remove_file(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty() && !p.exists()) {
+        fs::DirBuilder::new().recursive(true).mode(0o700).create(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+    }
+    // Whoever can connect can edit files as this user, so the socket is created owner-only rather than
+    // narrowed after bind, which would leave a window where anyone could connect
+    let previous = unsafe { libc::umask(0o177) };
+    let bound = UnixListener::bind(path);
+    unsafe { libc::umask(previous) };
+    let listener = bound.map_err(|e| format!("{}: {}", path.display(), e))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(|e| format!("{}: {}", path.display(), e))?;
+    info!("Listening on {} (Ctrl+C to stop)", path.display());
+    let interrupt = session.interrupt.clone();
+
+    let result = loop {
+        let stream = tokio::select! {
+            _ = interrupt.cancelled() => break Ok(()),
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => break Err(e.to_string()),
+            },
+        };
+        if !serve_client(session, stream).await {
+            break Ok(());
+        }
+    };
+    fs::remove_file(path).ok();
+    result
+}
+
+#[cfg(windows)]
+async fn listen(session: &mut Session, address: &str) -> Result<(), String> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(address)
+        .map_err(|e| format!("{}: {}", address, e))?;
+    info!("Listening on {} (Ctrl+C to stop)", address);
+    let interrupt = session.interrupt.clone();
+
+    loop {
+        tokio::select! {
+            _ = interrupt.cancelled() => return Ok(()),
+            connected = server.connect() => connected.map_err(|e| e.to_string())?,
+        }
+        // The next instance has to exist before this one is handed off, or clients see "pipe not found"
+        let client = server;
+        server = ServerOptions::new().create(address).map_err(|e| format!("{}: {}", address, e))?;
+        if !serve_client(session, client).await {
+            return Ok(());
+        }
+    }
+}
+
+// Returns false when Ctrl+C stopped the server during the connection
+async fn serve_client<S: AsyncRead + AsyncWrite + Unpin>(session: &mut Session, stream: S) -> bool {
+    let interrupt = session.interrupt.clone();
+    tokio::select! {
+        _ = interrupt.cancelled() => false,
+        result = serve(session, stream) => {
+            if let Err(e) = result {
+                warn!("{} {}", style::yellow("Connection closed:"), e);
+            }
+            true
+        }
+    }
+}
+
+// One JSON request per line, each answered with one line
+async fn serve<S: AsyncRead + AsyncWrite + Unpin>(session: &mut Session, stream: S) -> std::io::Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => handle(session, request).await,
+            Err(e) => json!({ "id": null, "error": { "code": PARSE_ERROR, "message": e.to_string() } }),
+        };
+        writer.write_all(format!("{}\n", reply).as_bytes()).await?;
+        writer.flush().await?;
+    }
+    Ok(())
+}
+
+async fn handle(session: &mut Session, request: Request) -> Value {
+    let result = match request.method.as_str() {
+        "prompt" => prompt(session, &request.params).await,
+        other => Err((METHOD_NOT_FOUND, format!("Unknown method '{}'", other))),
+    };
+    match result {
+        Ok(result) => json!({ "id": request.id, "result": result }),
+        Err((code, message)) => json!({ "id": request.id, "error": { "code": code, "message": message } }),
+    }
+}
+
+async fn prompt(session: &mut Session, params: &Value) -> Result<Value, (i32, String)> {
+    let text = params
+        .get("text")
+        .and_then(Value::as_str)
+        .filter(|text| !text.trim().is_empty())
+        .ok_or((INVALID_PARAMS, "params.text is required".to_string()))?;
+    if let Some(cwd) = params.get("cwd").and_then(Value::as_str).filter(|cwd| !cwd.is_empty()) {
+        let dir = session.cwd.join(cwd);
+        if !dir.is_dir() {
+            return Err((INVALID_PARAMS, format!("cwd {} is not a directory", dir.display())));
+        }
+        if dir != session.cwd {
+            change_dir(session, &dir).map_err(|e| (INVALID_PARAMS, format!("cwd {}", e)))?;
+        }
+    }
+
+    info!("{} {}", style::dim("prompt:"), text);
+    let response = process_prompt(session, text).await.map_err(|e| (PROMPT_FAILED, e))?;
+    let tools: Vec<Value> = session
+        .transcript
+        .last()
+        .map(|turn| {
+            turn.tools
+                .iter()
+                .map(|tool| json!({ "action": tool.action, "path": tool.path, "success": tool.success }))
+                .collect()
+        })
+        .unwrap_or_default();
+    session.last_response = Some(response.clone());
+    Ok(json!({ "response": response, "tools": tools }))
+}
@@ -0,0 +1,37 @@
+use crate::dir_models;
+use crate::paths;
+use crate::transcript::Turn;
+use crate::trust;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub saved_at: i64,
+    pub cwd: PathBuf,
+    pub provider: String,
+    pub model: String,
+    pub transcript: Vec<Turn>,
+}
+
+// One file per project (git root, or the directory outside a repository), so sessions in two projects
+// never overwrite each other and --resume picks up the one for where it starts
+pub fn session_path(cwd: &Path) -> Option<PathBuf> {
+    let root = dir_models::project_root(cwd);
+    let name = trust::hash(&root.to_string_lossy())[..16].to_string();
+    paths::state_file(&format!("sessions/{}.json", name))
+}
+
+// Owner-only, since a session holds the conversation
+pub fn save(session: &SavedSession) -> Result<(), String> {
+    let path = session_path(&session.cwd).ok_or("No home directory")?;
+    let json = serde_json::to_vec(session).map_err(|e| e.to_string())?;
+    paths::write_atomic(&path, &json, 0o600)
+}
+
+pub fn load(cwd: &Path) -> Result<SavedSession, String> {
+    let path = session_path(cwd).ok_or("No home directory")?;
+    let json = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,145 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+// Minimal AWS Signature Version 4 for JSON POST requests (no query string)
+pub struct Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: Option<String>,
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+// RFC 3986 encoding as SigV4 expects: only unreserved characters pass through
+pub fn uri_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// Returns the headers to add to the request (x-amz-date, optional token, authorization)
+pub fn sign(
+    host: &str,
+    path: &str,
+    body: &[u8],
+    region: &str,
+    service: &str,
+    creds: &Credentials,
+    now: DateTime<Utc>,
+) -> Vec<(String, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+    let mut headers = vec![
+        ("content-type".to_string(), "application/json".to_string()),
+        ("host".to_string(), host.to_string()),
+        ("x-amz-date".to_string(), amz_date.clone()),
+    ];
+    if let Some(token) = &creds.session_token {
+        headers.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    headers.sort();
+    let authorization = authorization("POST", path, &headers, body, region, service, creds, &amz_date);
+
+    let mut out: Vec<(String, String)> = headers.into_iter().filter(|(k, _)| k != "host").collect();
+    out.push(("authorization".to_string(), authorization));
+    out
+}
+
+// `headers` are lowercase and sorted, and all of them are signed; there is never a query string
+#[allow(clippy::too_many_arguments)]
+fn authorization(
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: &[u8],
+    region: &str,
+    service: &str,
+    creds: &Credentials,
+    amz_date: &str,
+) -> String {
+    let date = &amz_date[..8];
+
+    // Services other than S3 encode each (already encoded) path segment a second time
+    let canonical_path = path.split('/').map(uri_encode).collect::<Vec<_>>().join("/");
+    let canonical_headers: String = headers.iter().map(|(k, v)| format!("{}:{}\n", k, v.trim())).collect();
+    let signed_headers = headers.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method,
+        canonical_path,
+        canonical_headers,
+        signed_headers,
+        sha256_hex(body)
+    );
+
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, sha256_hex(canonical_request.as_bytes()));
+
+    let key = hmac(format!("AWS4{}", creds.secret_key).as_bytes(), date);
+    let key = hmac(&key, region);
+    let key = hmac(&key, service);
+    let key = hmac(&key, "aws4_request");
+    let signature = hex::encode(hmac(&key, &string_to_sign));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        creds.access_key, scope, signed_headers, signature
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // From the AWS Signature Version 4 test suite
+    fn suite_credentials() -> Credentials {
+        Credentials {
+            access_key: "AKIDEXAMPLE".into(),
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".into(),
+            session_token: None,
+        }
+    }
+
+    fn suite_authorization(method: &str, headers: &[(&str, &str)], body: &str) -> String {
+        let headers: Vec<(String, String)> = headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        authorization(method, "/", &headers, body.as_bytes(), "us-east-1", "service", &suite_credentials(), "20150830T123600Z")
+    }
+
+    #[test]
+    fn get_vanilla() {
+        let headers = [("host", "example.amazonaws.com"), ("x-amz-date", "20150830T123600Z")];
+        assert_eq!(
+            suite_authorization("GET", &headers, ""),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn post_x_www_form_urlencoded() {
+        let headers = [
+            ("content-type", "application/x-www-form-urlencoded"),
+            ("host", "example.amazonaws.com"),
+            ("x-amz-date", "20150830T123600Z"),
+        ];
+        assert_eq!(
+            suite_authorization("POST", &headers, "Param1=value1"),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=ff11897932ad3f4e8b18135d722051e5ac45fc38421b1da7b9d196a0fe09473a"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use crate::llm::Usage;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default)]
+pub struct PromptStats {
+    pub elapsed: Duration,
+    pub iterations: u32,
+    pub usage: Usage,
+    pub llm_time: Duration,
+    pub tool_time: Duration,
+    // Per action: how many ran and their total time
+    pub actions: BTreeMap<String, (u32, Duration)>,
+    pub answered_by: Option<String>,
+    // Only known when the model has pricing in [[models]]
+    pub cost: Option<f64>,
+}
+
+impl PromptStats {
+    pub fn record_action(&mut self, action: &str, time: Duration) {
+        let entry = self.actions.entry(action.to_string()).or_default();
+        entry.0 += 1;
+        entry.1 += time;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SessionStats {
+    pub prompts: u32,
+    pub iterations: u32,
+    pub usage: Usage,
+    pub elapsed: Duration,
+    pub llm_time: Duration,
+    pub tool_time: Duration,
+    pub actions: BTreeMap<String, (u32, Duration)>,
+    pub cost: Option<f64>,
+}
+
+impl SessionStats {
+    pub fn add(&mut self, prompt: &PromptStats) {
+        self.prompts += 1;
+        self.iterations += prompt.iterations;
+        self.usage.add(prompt.usage);
+        self.elapsed += prompt.elapsed;
+        self.llm_time += prompt.llm_time;
+        self.tool_time += prompt.tool_time;
+        for (action, (count, time)) in &prompt.actions {
+            let entry = self.actions.entry(action.clone()).or_default();
+            entry.0 += count;
+            entry.1 += *time;
+        }
+        if let Some(cost) = prompt.cost {
+            *self.cost.get_or_insert(0.0) += cost;
+        }
+    }
+}
+
+pub fn format_tokens(n: u64) -> String {
+    if n >= 1000 {
+        format!("{:.1}k", n as f64 / 1000.0)
+    } else {
+        n.to_string()
+    }
+}
+
+pub fn stats_line(stats: &PromptStats) -> String {
+    let mut line = format!(
+        "⏱ {:.1}s • {} iteration{} • {} in / {} out",
+        stats.elapsed.as_secs_f64(),
+        stats.iterations,
+        if stats.iterations == 1 { "" } else { "s" },
+        format_tokens(stats.usage.input_tokens),
+        format_tokens(stats.usage.output_tokens)
+    );
+    if let Some(cost) = stats.cost {
+        line.push_str(&format!(" • ~${:.4}", cost));
+    }
+    if let Some(answered_by) = stats.answered_by.as_deref() {
+        line.push_str(&format!(" • {}", answered_by));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_line_ends_with_cost_only_when_priced() {
+        let mut stats = PromptStats {
+            elapsed: Duration::from_millis(6400),
+            iterations: 2,
+            usage: Usage { input_tokens: 3100, output_tokens: 820 },
+            ..PromptStats::default()
+        };
+        assert_eq!(stats_line(&stats), "⏱ 6.4s • 2 iterations • 3.1k in / 820 out");
+        stats.cost = Some(0.004);
+        assert_eq!(stats_line(&stats), "⏱ 6.4s • 2 iterations • 3.1k in / 820 out • ~$0.0040");
+    }
+}
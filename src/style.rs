@@ -0,0 +1,84 @@
+use std::env;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+// Decide once at startup whether ANSI styling is allowed
+pub fn init(no_color_flag: bool) {
+    let no_color_env = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let enabled = !no_color_flag && !no_color_env && std::io::stdout().is_terminal() && enable_ansi();
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_color() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+fn paint(code: &str, text: &str) -> String {
+    if is_color() {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn bold(text: &str) -> String {
+    paint("1", text)
+}
+
+pub fn dim(text: &str) -> String {
+    paint("2", text)
+}
+
+pub fn red(text: &str) -> String {
+    paint("31", text)
+}
+
+pub fn green(text: &str) -> String {
+    paint("32", text)
+}
+
+pub fn yellow(text: &str) -> String {
+    paint("33", text)
+}
+
+pub fn cyan(text: &str) -> String {
+    paint("36", text)
+}
+
+pub fn diff_line(line: &str) -> String {
+    if line.starts_with("+++") || line.starts_with("---") {
+        bold(line)
+    } else if line.starts_with('+') {
+        green(line)
+    } else if line.starts_with('-') {
+        red(line)
+    } else if line.starts_with("@@") {
+        cyan(line)
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(windows)]
+fn enable_ansi() -> bool {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING, STD_OUTPUT_HANDLE,
+    };
+
+    // Older consoles reject the flag; fall back to plain output there
+    unsafe {
+        let handle = GetStdHandle(STD_OUTPUT_HANDLE);
+        let mut mode = 0;
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            return false;
+        }
+        SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+    }
+}
+
+#[cfg(not(windows))]
+fn enable_ansi() -> bool {
+    true
+}
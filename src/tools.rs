@@ -1,12 +1,40 @@
+use crate::clioignore;
+use crate::config::Config;
+use crate::fetch;
+use crate::outline;
+use crate::patch;
+use crate::search;
+use crate::undo::Snapshot;
+use notify::{EventKind, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct ToolCall {
     pub action: String,
     pub path: Option<String>,
     pub content: Option<String>,
+    #[serde(default, alias = "comment", skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    // Annotation for the next iteration, echoed back at the start of this call's result
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    // list_dir pagination (1-based page)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<usize>,
+    // search_replace: the replacement text, and how many occurrences to replace (default all)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -21,83 +49,349 @@ pub struct ToolResult {
     pub path: String,
     pub success: bool,
     pub result: String,
+    pub duration_ms: u64,
 }
 
-pub fn is_supported_action(action: &str) -> bool {
+impl ToolResult {
+    pub fn ok(tool: &ToolCall, result: impl Into<String>) -> Self {
+        ToolResult {
+            action: tool.action.clone(),
+            path: tool.path.clone().unwrap_or_default(),
+            success: true,
+            result: result.into(),
+            duration_ms: 0,
+        }
+    }
+
+    pub fn failed(tool: &ToolCall, reason: impl Into<String>) -> Self {
+        ToolResult { success: false, ..ToolResult::ok(tool, reason) }
+    }
+}
+
+// Every action the model may use, with the example line shown in the system prompt
+pub const TOOLS: &[(&str, &str)] = &[
+    ("create_file", r#"{"action": "create_file", "path": "file.txt", "content": "file content"}"#),
+    ("create_folder", r#"{"action": "create_folder", "path": "folder"}"#),
+    ("create_symlink", r#"{"action": "create_symlink", "path": "public", "destination": "dist"} (symbolic link at "path" pointing to "destination", relative to the link's folder; the target must be inside the working directory)"#),
+    ("read_file", r#"{"action": "read_file", "path": "file.txt"}"#),
+    ("summarize", r#"{"action": "summarize", "path": "src/main.rs"} (cheap overview with line numbers: declarations for code, headings for Markdown, else the first and last lines; use before reading a large file in full)"#),
+    ("delete", r#"{"action": "delete", "path": "file.txt"}"#),
+    ("list_dir", r#"{"action": "list_dir", "path": ".", "page": 1, "page_size": 50} (page fields optional; large directories are paged)"#),
+    ("search", r#"{"action": "search", "path": "src", "content": "fn \\w+_config"} (regex search; returns file:line: matches; prefix the pattern with (?i) to ignore case)"#),
+    ("summarize_dir", r#"{"action": "summarize_dir", "path": "src"} (snippets of up to 5 key files, to learn what a directory is for)"#),
+    ("watch_file", r#"{"action": "watch_file", "path": "server.log", "content": "30"} (waits up to N seconds for the file to change; only if enabled)"#),
+    ("search_replace", r#"{"action": "search_replace", "path": "src/x.rs", "content": "old text", "destination": "new text", "count": 1} (exact text replacement; "count" optional, default all occurrences)"#),
+    ("read_url", r#"{"action": "read_url", "path": "https://example.com/file.txt"} (fetches a public http(s) URL as text, up to 512 KB; nothing is saved unless you write it; only if enabled)"#),
+    ("apply_diff", r#"{"action": "apply_diff", "content": "--- a/src/x.rs\n+++ b/src/x.rs\n@@ -1,3 +1,3 @@\n..."} (unified diff across one or more files; nothing is written unless every hunk applies. Or give "path" to a .patch file instead of content)"#),
+    ("generate_env_example", r#"{"action": "generate_env_example", "path": ".env"} (writes .env.example next to it with secret values replaced by placeholders; an existing one only gains the missing variables)"#),
+    ("check_permissions", r#"{"action": "check_permissions", "path": "file_or_folder"} (readable/writable/executable and owner, before writing)"#),
+    ("env_info", r#"{"action": "env_info"} (OS, architecture, shell and which common tool env vars are set)"#),
+    ("validate_json", r#"{"action": "validate_json", "path": "data.json"} (check syntax after writing JSON/JSONL)"#),
+    ("validate_toml", r#"{"action": "validate_toml", "path": "Cargo.toml"}"#),
+    ("validate_yaml", r#"{"action": "validate_yaml", "path": "docker-compose.yml"}"#),
+];
+
+pub const SEARCH_MAX_RESULTS: usize = 100;
+
+pub fn is_supported_action(action: &str, blocked: &[String]) -> bool {
+    TOOLS.iter().any(|(name, _)| *name == action) && !blocked.iter().any(|b| b == action)
+}
+
+pub fn available_actions(blocked: &[String]) -> Vec<&'static str> {
+    TOOLS
+        .iter()
+        .map(|(name, _)| *name)
+        .filter(|name| !blocked.iter().any(|b| b == name))
+        .collect()
+}
+
+pub fn tool_docs(blocked: &[String]) -> String {
+    TOOLS
+        .iter()
+        .filter(|(name, _)| !blocked.iter().any(|b| b == name))
+        .map(|(_, doc)| format!("- {}", doc))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn is_mutating_action(action: &str) -> bool {
     matches!(
         action,
-        "read_file" | "create_file" | "create_folder" | "delete" | "list_dir"
+        "create_file" | "create_folder" | "create_symlink" | "delete" | "search_replace" | "apply_diff" | "generate_env_example"
     )
 }
 
-pub fn execute_tool(tool: &ToolCall, cwd: &Path) -> ToolResult {
-    let path_str = tool.path.clone().unwrap_or(".".into());
-    let full_path = cwd.join(&path_str);
-    
-    // Security: ensure path is within cwd
-    let canonical_cwd = cwd.canonicalize().unwrap_or(cwd.to_path_buf());
-    let canonical_path = full_path.canonicalize().unwrap_or(full_path.clone());
-    
-    if !canonical_path.starts_with(&canonical_cwd) && tool.action != "list_dir" {
-        return ToolResult {
-            action: tool.action.clone(),
-            path: path_str,
-            success: false,
-            result: "Access denied: path outside current directory".into(),
+// Paths a tool call may modify, used to snapshot state for /undo
+pub fn mutated_paths(tool: &ToolCall, cwd: &Path) -> Vec<String> {
+    if !is_mutating_action(&tool.action) {
+        return Vec::new();
+    }
+    if tool.action == "apply_diff" {
+        let Some(patches) = diff_text(tool, cwd).ok().and_then(|text| patch::parse(&text).ok()) else {
+            return Vec::new();
         };
+        let mut paths: Vec<String> = patches
+            .iter()
+            .flat_map(|p| [p.old_path.clone(), p.new_path.clone()])
+            .flatten()
+            .collect();
+        paths.sort();
+        paths.dedup();
+        return paths;
+    }
+    if tool.action == "generate_env_example" {
+        return tool.path.iter().filter(|p| !p.trim().is_empty()).map(|p| env_example_path(p)).collect();
+    }
+    tool.path.iter().filter(|p| !p.trim().is_empty()).cloned().collect()
+}
+
+// ".env", ".env.local", "config/.env" but not the example itself
+pub fn is_env_file(path: &str) -> bool {
+    let name = Path::new(path).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    (name == ".env" || name.starts_with(".env.")) && name != ".env.example"
+}
+
+pub fn env_example_path(env_path: &str) -> String {
+    match Path::new(env_path).parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(parent) => parent.join(".env.example").to_string_lossy().to_string(),
+        None => ".env.example".into(),
+    }
+}
+
+const SECRET_MARKERS: &[&str] = &["KEY", "SECRET", "TOKEN", "PASSWORD", "URL"];
+
+// Keeps comments, ordering and non-secret values; secret-looking values become placeholders
+pub fn env_example(content: &str) -> String {
+    let mut out: Vec<String> = content
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with('#') {
+                return line.to_string();
+            }
+            let Some((key, _)) = line.split_once('=') else {
+                return line.to_string();
+            };
+            let name = env_key(key);
+            let upper = name.to_uppercase();
+            if SECRET_MARKERS.iter().any(|m| upper.contains(m)) {
+                format!("{}=your_{}_here", key, name.to_lowercase())
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    out.push(String::new());
+    out.join("\n")
+}
+
+fn env_key(key: &str) -> &str {
+    key.trim().trim_start_matches("export ").trim()
+}
+
+// An existing .env.example is often curated by hand, so it only gains the variables it lacks, at the end
+pub fn merge_env_example(existing: &str, generated: &str) -> (String, usize) {
+    let is_var = |line: &&str| !line.trim_start().starts_with('#');
+    let known: HashSet<&str> =
+        existing.lines().filter(is_var).filter_map(|line| line.split_once('=')).map(|(key, _)| env_key(key)).collect();
+    let missing: Vec<&str> = generated
+        .lines()
+        .filter(is_var)
+        .filter(|line| line.split_once('=').is_some_and(|(key, _)| !known.contains(env_key(key))))
+        .collect();
+    if missing.is_empty() {
+        return (existing.to_string(), 0);
+    }
+    let mut merged = existing.to_string();
+    if !merged.is_empty() && !merged.ends_with('\n') {
+        merged.push('\n');
+    }
+    merged.push_str(&missing.join("\n"));
+    merged.push('\n');
+    (merged, missing.len())
+}
+
+// Resolve a tool path against cwd, returning None if it escapes cwd
+pub fn resolve_path(cwd: &Path, path: &str) -> Option<PathBuf> {
+    let canonical_cwd = cwd.canonicalize().unwrap_or(cwd.to_path_buf());
+    let full_path = normalize(&canonical_cwd.join(path));
+    canonicalize_existing(&full_path).starts_with(&canonical_cwd).then_some(full_path)
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+// Canonicalize the deepest existing ancestor so symlinked parents of new files are caught
+fn canonicalize_existing(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    while existing.canonicalize().is_err() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+    let mut out = existing.canonicalize().unwrap_or(existing.to_path_buf());
+    for name in rest.into_iter().rev() {
+        out.push(name);
+    }
+    out
+}
+
+// Reject malformed calls up front so the model gets a clear message instead of an OS error
+pub fn validate(tool: &ToolCall) -> Result<(), String> {
+    let path = tool.path.as_deref().map(str::trim);
+    match path {
+        None if matches!(tool.action.as_str(), "list_dir" | "env_info" | "search") => {}
+        None if tool.action == "apply_diff" && tool.content.is_some() => {}
+        None | Some("") => return Err(format!("'{}' requires a non-empty \"path\"", tool.action)),
+        Some(_) => {}
+    }
+    if tool.action == "create_file" && tool.content.is_none() {
+        return Err("'create_file' requires \"content\" (use \"\" for an empty file)".into());
     }
+    if tool.action == "create_symlink" && tool.destination.as_deref().is_none_or(|d| d.trim().is_empty()) {
+        return Err("'create_symlink' requires the link target in \"destination\"".into());
+    }
+    if tool.action == "search" && tool.content.as_deref().is_none_or(|c| c.is_empty()) {
+        return Err("'search' requires a regex pattern in \"content\"".into());
+    }
+    if tool.action == "search_replace" {
+        if tool.content.as_deref().is_none_or(|c| c.is_empty()) {
+            return Err("'search_replace' requires the exact text to find in \"content\"".into());
+        }
+        if tool.destination.is_none() {
+            return Err("'search_replace' requires the replacement text in \"destination\" (use \"\" to remove)".into());
+        }
+        if tool.count == Some(0) {
+            return Err("'search_replace' \"count\" must be at least 1".into());
+        }
+    }
+    if tool.action == "apply_diff" && tool.content.as_deref().is_some_and(|c| c.trim().is_empty()) {
+        return Err("'apply_diff' requires a unified diff in \"content\"".into());
+    }
+    Ok(())
+}
+
+// Runs the (blocking) file I/O of a tool on the blocking pool so the runtime stays responsive
+pub async fn execute_tool_async(tool: &ToolCall, cwd: &Path, config: &Config) -> ToolResult {
+    let started = Instant::now();
+    let mut result = if tool.action == "read_url" && validate(tool).is_ok() {
+        read_url(tool, config).await
+    } else {
+        let (owned_tool, cwd, config) = (tool.clone(), cwd.to_path_buf(), config.clone());
+        match tokio::task::spawn_blocking(move || execute_tool(&owned_tool, &cwd, &config)).await {
+            Ok(result) => result,
+            Err(e) => ToolResult::failed(tool, format!("Tool execution failed: {}", e)),
+        }
+    };
+    cap_result(&mut result.result, config.limits.max_result_bytes);
+    result.duration_ms = started.elapsed().as_millis() as u64;
+    result
+}
+
+// Cuts on a char boundary and says how much was dropped
+fn cap_result(text: &mut String, max_bytes: usize) {
+    if text.len() <= max_bytes {
+        return;
+    }
+    let total = text.len();
+    let mut cut = max_bytes;
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    text.truncate(cut);
+    text.push_str(&format!("\n[result cut to {} of {} bytes (max_result_bytes); use search or summarize for the rest]", cut, total));
+}
+
+async fn read_url(tool: &ToolCall, config: &Config) -> ToolResult {
+    let url = tool.path.clone().unwrap_or_default();
+    if !config.allow_net_read {
+        return ToolResult::failed(tool, "read_url is disabled; set ALLOW_NET_READ=true to enable it");
+    }
+    match fetch::read_url(&url).await {
+        Ok(text) => ToolResult::ok(tool, text),
+        Err(e) => ToolResult::failed(tool, e),
+    }
+}
+
+pub fn execute_tool(tool: &ToolCall, cwd: &Path, config: &Config) -> ToolResult {
+    if let Err(e) = validate(tool) {
+        return ToolResult::failed(tool, format!("Invalid tool call: {}", e));
+    }
+
+    let path_str = tool.path.clone().unwrap_or(".".into());
+
+    // Security: ensure path is within cwd
+    let full_path = match resolve_path(cwd, &path_str) {
+        Some(path) => path,
+        None if tool.action == "list_dir" => cwd.join(&path_str),
+        None => return ToolResult::failed(tool, "Access denied: path outside current directory"),
+    };
 
     match tool.action.as_str() {
         "read_file" => {
-            match fs::read_to_string(&full_path) {
-                Ok(content) => ToolResult {
-                    action: "read_file".into(),
-                    path: path_str,
-                    success: true,
-                    result: content,
-                },
-                Err(e) => ToolResult {
-                    action: "read_file".into(),
-                    path: path_str,
-                    success: false,
-                    result: e.to_string(),
+            let size = fs::metadata(&full_path).map(|m| m.len()).unwrap_or(0);
+            if size > config.limits.max_read_bytes as u64 {
+                return ToolResult::failed(
+                    tool,
+                    format!(
+                        "File is {} bytes, over the {} byte max_read_bytes limit; use summarize or search instead",
+                        size, config.limits.max_read_bytes
+                    ),
+                );
+            }
+            match read_text(&full_path) {
+                Ok(content) => match format_notes(&content).filter(|_| config.file_metadata) {
+                    Some(notes) => ToolResult::ok(tool, format!("[file format: {}]\n{}", notes, content)),
+                    None => ToolResult::ok(tool, content),
                 },
+                Err(e) => ToolResult::failed(tool, e),
             }
         }
+        "summarize" => match read_text(&full_path) {
+            Ok(content) => ToolResult::ok(tool, outline::summarize(&full_path, &content)),
+            Err(e) => ToolResult::failed(tool, e),
+        },
         "create_file" => {
             let content = tool.content.clone().unwrap_or_default();
+            // Rewriting identical content would only bump the mtime and wake watchers and builds
+            if same_content(&full_path, &content) {
+                return ToolResult::ok(tool, format!("Unchanged: file already has these {} bytes", content.len()));
+            }
             if let Some(parent) = full_path.parent() {
                 fs::create_dir_all(parent).ok();
             }
             match fs::write(&full_path, &content) {
-                Ok(_) => ToolResult {
-                    action: "create_file".into(),
-                    path: path_str,
-                    success: true,
-                    result: format!("Created file with {} bytes", content.len()),
-                },
-                Err(e) => ToolResult {
-                    action: "create_file".into(),
-                    path: path_str,
-                    success: false,
-                    result: e.to_string(),
-                },
+                Ok(_) => ToolResult::ok(tool, format!("Created file with {} bytes", content.len())),
+                Err(e) => ToolResult::failed(tool, e.to_string()),
             }
         }
         "create_folder" => {
             match fs::create_dir_all(&full_path) {
-                Ok(_) => ToolResult {
-                    action: "create_folder".into(),
-                    path: path_str,
-                    success: true,
-                    result: "Folder created".into(),
-                },
-                Err(e) => ToolResult {
-                    action: "create_folder".into(),
-                    path: path_str,
-                    success: false,
-                    result: e.to_string(),
-                },
+                Ok(_) => ToolResult::ok(tool, "Folder created"),
+                Err(e) => ToolResult::failed(tool, e.to_string()),
+            }
+        }
+        "create_symlink" => {
+            let target = tool.destination.as_deref().unwrap_or_default().trim();
+            match create_symlink(cwd, &full_path, target) {
+                Ok(result) => ToolResult::ok(tool, result),
+                Err(e) => ToolResult::failed(tool, e),
             }
         }
         "delete" => {
@@ -107,50 +401,568 @@ pub fn execute_tool(tool: &ToolCall, cwd: &Path) -> ToolResult {
                 fs::remove_file(&full_path)
             };
             match result {
-                Ok(_) => ToolResult {
-                    action: "delete".into(),
-                    path: path_str,
-                    success: true,
-                    result: "Deleted".into(),
-                },
-                Err(e) => ToolResult {
-                    action: "delete".into(),
-                    path: path_str,
-                    success: false,
-                    result: e.to_string(),
-                },
+                Ok(_) => ToolResult::ok(tool, "Deleted"),
+                Err(e) => ToolResult::failed(tool, e.to_string()),
             }
         }
         "list_dir" => {
             match fs::read_dir(&full_path) {
                 Ok(entries) => {
-                    let files: Vec<String> = entries
+                    let mut entries: Vec<_> = entries
                         .filter_map(|e| e.ok())
+                        .filter(|e| !clioignore::is_ignored(cwd, &e.path(), e.path().is_dir()))
+                        .collect();
+                    entries.sort_by_key(|e| e.file_name());
+                    let files: Vec<String> = entries
+                        .iter()
                         .map(|e| {
                             let name = e.file_name().to_string_lossy().to_string();
-                            if e.path().is_dir() { format!("{}/", name) } else { name }
+                            entry_label(&name, e.path().is_dir(), config.nerd_fonts)
                         })
                         .collect();
-                    ToolResult {
-                        action: "list_dir".into(),
-                        path: path_str,
-                        success: true,
-                        result: files.join("\n"),
+                    match paginate(&files, tool.page, tool.page_size) {
+                        Ok(result) => ToolResult::ok(tool, result),
+                        Err(e) => ToolResult::failed(tool, e),
                     }
                 }
-                Err(e) => ToolResult {
-                    action: "list_dir".into(),
-                    path: path_str,
-                    success: false,
-                    result: e.to_string(),
-                },
+                Err(e) => ToolResult::failed(tool, e.to_string()),
             }
         }
-        _ => ToolResult {
-            action: tool.action.clone(),
-            path: path_str,
-            success: false,
-            result: "Unknown action".into(),
+        "summarize_dir" => {
+            match summarize_dir(&full_path, cwd) {
+                Ok(summary) => ToolResult::ok(tool, summary),
+                Err(e) => ToolResult::failed(tool, e.to_string()),
+            }
+        }
+        "watch_file" => {
+            if !config.allow_watch {
+                return ToolResult::failed(tool, "watch_file is disabled; set CLIO_ALLOW_WATCH=1 to enable it");
+            }
+            let timeout = tool
+                .content
+                .as_deref()
+                .and_then(|c| c.trim().parse::<u64>().ok())
+                .unwrap_or(30)
+                .min(300);
+            match watch_file(&full_path, Duration::from_secs(timeout)) {
+                Ok(result) => ToolResult::ok(tool, result),
+                Err(e) => ToolResult::failed(tool, e),
+            }
+        }
+        "search" => {
+            let opts = search::SearchOptions {
+                pattern: tool.content.clone().unwrap_or_default(),
+                case_insensitive: false,
+                glob: None,
+                max_results: SEARCH_MAX_RESULTS,
+            };
+            match search::search(&full_path, cwd, &opts) {
+                Ok(found) => ToolResult::ok(tool, found.to_text()),
+                Err(e) => ToolResult::failed(tool, e),
+            }
+        }
+        "search_replace" => match search_replace(tool, &full_path) {
+            Ok(result) => ToolResult::ok(tool, result),
+            Err(e) => ToolResult::failed(tool, e),
+        },
+        "apply_diff" => match apply_diff(tool, cwd) {
+            Ok(result) => ToolResult::ok(tool, result),
+            Err(e) => ToolResult::failed(tool, e),
         },
+        "generate_env_example" => {
+            let example = env_example_path(&path_str);
+            let written = resolve_path(cwd, &example)
+                .ok_or_else(|| "Access denied: path outside current directory".to_string())
+                .and_then(|target| {
+                    let generated = env_example(&fs::read_to_string(&full_path).map_err(|e| e.to_string())?);
+                    let outcome = match fs::read_to_string(&target) {
+                        Ok(existing) => match merge_env_example(&existing, &generated) {
+                            (_, 0) => return Ok(format!("{} already lists every variable; left unchanged", example)),
+                            (merged, added) => {
+                                fs::write(&target, merged).map_err(|e| e.to_string())?;
+                                format!("Added {} missing variable(s) to {} with secret values redacted", added, example)
+                            }
+                        },
+                        Err(_) => {
+                            fs::write(&target, generated).map_err(|e| e.to_string())?;
+                            format!("Wrote {} with secret values redacted", example)
+                        }
+                    };
+                    Ok(outcome)
+                });
+            match written {
+                Ok(outcome) => ToolResult::ok(tool, outcome),
+                Err(e) => ToolResult::failed(tool, e),
+            }
+        }
+        "check_permissions" => match check_permissions(&full_path) {
+            Ok(result) => ToolResult::ok(tool, result),
+            Err(e) => ToolResult::failed(tool, e.to_string()),
+        },
+        "env_info" => ToolResult::ok(tool, env_info()),
+        "validate_json" | "validate_toml" | "validate_yaml" => {
+            match fs::read_to_string(&full_path) {
+                Ok(content) => match validate_syntax(&tool.action, &path_str, &content) {
+                    Ok(()) => ToolResult::ok(tool, format!("Valid {}", syntax_name(&tool.action))),
+                    Err(e) => ToolResult::failed(tool, e),
+                },
+                Err(e) => ToolResult::failed(tool, e.to_string()),
+            }
+        }
+        _ => ToolResult::failed(tool, "Unknown action"),
+    }
+}
+
+fn same_content(path: &Path, content: &str) -> bool {
+    let hash = |bytes: &[u8]| Sha256::digest(bytes);
+    fs::read(path).is_ok_and(|existing| hash(&existing) == hash(content.as_bytes()))
+}
+
+fn search_replace(tool: &ToolCall, path: &Path) -> Result<String, String> {
+    let search = tool.content.as_deref().unwrap_or_default();
+    let replacement = tool.destination.as_deref().unwrap_or_default();
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let found = text.matches(search).count();
+    if found == 0 {
+        return Err("Search text not found (it must match exactly, including whitespace)".into());
+    }
+    let replaced = tool.count.map_or(found, |count| count.min(found));
+    let updated = text.replacen(search, replacement, replaced);
+    if updated == text {
+        return Ok(format!("Unchanged: replacement is identical to the {} match(es)", found));
+    }
+    fs::write(path, updated).map_err(|e| e.to_string())?;
+    Ok(format!("Replaced {} of {} occurrence(s)", replaced, found))
+}
+
+// The diff comes inline in "content", or from a patch file named by "path"
+fn diff_text(tool: &ToolCall, cwd: &Path) -> Result<String, String> {
+    if let Some(content) = &tool.content {
+        return Ok(content.clone());
+    }
+    let path = tool.path.as_deref().unwrap_or_default();
+    let full_path = resolve_path(cwd, path).ok_or("Access denied: path outside current directory")?;
+    fs::read_to_string(&full_path).map_err(|e| format!("{}: {}", path, e))
+}
+
+// All-or-nothing: every hunk is checked against the current files before anything is written
+fn apply_diff(tool: &ToolCall, cwd: &Path) -> Result<String, String> {
+    let patches = patch::parse(&diff_text(tool, cwd)?)?;
+
+    let mut writes: Vec<(PathBuf, Option<String>)> = Vec::new();
+    let mut touched = Vec::new();
+    for file in &patches {
+        let target = file.target().unwrap_or_default();
+        let resolve = |path: &str| {
+            resolve_path(cwd, path).ok_or_else(|| format!("Access denied: {} is outside current directory", path))
+        };
+        let target_path = resolve(target)?;
+        let source_path = match &file.old_path {
+            Some(old) => Some(resolve(old)?),
+            None if target_path.exists() => return Err(format!("{} already exists", target)),
+            None => None,
+        };
+
+        let original = match &source_path {
+            Some(path) => fs::read_to_string(path).map_err(|e| format!("{}: {}", target, e))?,
+            None => String::new(),
+        };
+        let patched = patch::apply(&original, file).map_err(|e| format!("{}: {}", target, e))?;
+
+        if file.is_delete() {
+            writes.push((target_path, None));
+        } else {
+            if let Some(source) = source_path.filter(|source| *source != target_path) {
+                writes.push((source, None));
+            }
+            writes.push((target_path, Some(patched)));
+        }
+        touched.push(target.to_string());
+    }
+
+    // Every hunk applied in memory; a write that still fails puts back the files written before it
+    let mut done: Vec<(&PathBuf, Snapshot)> = Vec::new();
+    for (path, content) in &writes {
+        done.push((path, Snapshot::capture(path)));
+        let written = match content {
+            Some(content) => path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(path, content)),
+            None => fs::remove_file(path),
+        };
+        if let Err(e) = written {
+            let restored = done.iter().rev().all(|(path, before)| before.restore(path).is_ok());
+            let outcome = if restored { "no file was changed" } else { "some files could not be restored" };
+            return Err(format!("{}: {} ({})", path.display(), e, outcome));
+        }
+    }
+
+    Ok(format!("Applied patch to {} file(s): {}", touched.len(), touched.join(", ")))
+}
+
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(8192).any(|b| *b == 0)
+}
+
+// Reads a text file, refusing binaries with a message instead of dumping bytes
+pub fn read_text(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    if is_binary(&bytes) {
+        return Err(format!("Binary file ({} bytes); not shown", bytes.len()));
+    }
+    String::from_utf8(bytes).map_err(|_| "File is not valid UTF-8 text; not shown".to_string())
+}
+
+// What an edit should preserve that the text alone makes easy to miss; None for plain LF files
+fn format_notes(content: &str) -> Option<String> {
+    let mut notes = Vec::new();
+    let crlf = content.matches("\r\n").count();
+    let lf = content.matches('\n').count();
+    if crlf > 0 {
+        notes.push(if crlf == lf { "CRLF line endings" } else { "mixed CRLF and LF line endings" });
+    }
+    if content.starts_with('\u{feff}') {
+        notes.push("UTF-8 BOM");
+    }
+    if !content.is_empty() && !content.ends_with('\n') {
+        notes.push("no trailing newline");
+    }
+    (!notes.is_empty()).then(|| notes.join(", "))
+}
+
+const LIST_DIR_PAGE_SIZE: usize = 50;
+
+fn paginate(entries: &[String], page: Option<usize>, page_size: Option<usize>) -> Result<String, String> {
+    let page_size = page_size.filter(|n| *n > 0).unwrap_or(LIST_DIR_PAGE_SIZE);
+    let pages = entries.len().div_ceil(page_size).max(1);
+    let page = page.unwrap_or(1);
+    if page == 0 || page > pages {
+        return Err(format!("Page {} out of range (1-{})", page, pages));
+    }
+
+    let start = (page - 1) * page_size;
+    let end = (start + page_size).min(entries.len());
+    let mut out = vec![format!("{} entries", entries.len())];
+    out.extend(entries[start..end].iter().cloned());
+    if pages > 1 {
+        let next = if page < pages {
+            format!(" — use page:{} for next", page + 1)
+        } else {
+            String::new()
+        };
+        out.push(format!("[Page {}/{}{}]", page, pages, next));
+    }
+    Ok(out.join("\n"))
+}
+
+fn check_permissions(path: &Path) -> std::io::Result<String> {
+    let meta = fs::metadata(path)?;
+    let (readable, writable, executable, owner) = permissions(path, &meta);
+    Ok(json!({
+        "kind": if meta.is_dir() { "directory" } else { "file" },
+        "readable": readable,
+        "writable": writable,
+        "executable": executable,
+        "owner": owner,
+    })
+    .to_string())
+}
+
+// The target is resolved the way the OS will read it (relative to the link's folder, through any
+// symlinked folders) and must stay inside the working directory, like every other tool path
+fn create_symlink(cwd: &Path, link: &Path, target: &str) -> Result<String, String> {
+    let canonical_cwd = cwd.canonicalize().unwrap_or(cwd.to_path_buf());
+    let resolved = normalize(&canonicalize_existing(&link.parent().unwrap_or(&canonical_cwd).join(target)));
+    if !resolved.starts_with(&canonical_cwd) {
+        return Err(format!("Access denied: link target '{}' is outside the current directory", target));
+    }
+    if fs::symlink_metadata(link).is_ok() {
+        return Err("Already exists; delete it first to replace it with a link".into());
+    }
+    if let Some(parent) = link.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    make_symlink(Path::new(target), link, resolved.is_dir()).map_err(|e| e.to_string())?;
+    Ok(if resolved.exists() {
+        format!("Linked to {}", target)
+    } else {
+        format!("Linked to {} (the target does not exist yet)", target)
+    })
+}
+
+#[cfg(unix)]
+pub fn make_symlink(target: &Path, link: &Path, _is_dir: bool) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+// Windows links are typed; a link to a folder that does not exist yet is made as a file link
+#[cfg(windows)]
+pub fn make_symlink(target: &Path, link: &Path, is_dir: bool) -> std::io::Result<()> {
+    if is_dir {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+// Asks the OS on behalf of the current user, so ACLs and group membership are honored
+#[cfg(unix)]
+fn permissions(path: &Path, meta: &fs::Metadata) -> (bool, bool, bool, Option<String>) {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+
+    let access = |mode| match CString::new(path.as_os_str().as_bytes()) {
+        Ok(c_path) => unsafe { libc::access(c_path.as_ptr(), mode) == 0 },
+        Err(_) => false,
+    };
+
+    let owner = unsafe {
+        let mut pwd: libc::passwd = std::mem::zeroed();
+        let mut buf = vec![0 as libc::c_char; 1024];
+        let mut result: *mut libc::passwd = std::ptr::null_mut();
+        let rc = libc::getpwuid_r(meta.uid(), &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result);
+        if rc == 0 && !result.is_null() {
+            Some(CStr::from_ptr(pwd.pw_name).to_string_lossy().to_string())
+        } else {
+            Some(meta.uid().to_string())
+        }
+    };
+
+    (access(libc::R_OK), access(libc::W_OK), access(libc::X_OK), owner)
+}
+
+// Windows only exposes the read-only attribute without pulling in the security APIs
+#[cfg(not(unix))]
+fn permissions(path: &Path, meta: &fs::Metadata) -> (bool, bool, bool, Option<String>) {
+    let executable = meta.is_dir()
+        || path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ["exe", "bat", "cmd", "com", "ps1"].contains(&ext.to_ascii_lowercase().as_str()));
+    (true, !meta.permissions().readonly(), executable, None)
+}
+
+// Only these variables are ever exposed by value; nothing here may hold a secret
+const ENV_INFO_VALUES: &[&str] = &["SHELL", "TERM", "TERM_PROGRAM", "LANG", "LC_ALL", "EDITOR"];
+// These are reported as set/unset only
+const ENV_INFO_PRESENCE: &[&str] = &[
+    "PATH",
+    "HOME",
+    "VIRTUAL_ENV",
+    "CONDA_PREFIX",
+    "CARGO_HOME",
+    "GOPATH",
+    "JAVA_HOME",
+    "NVM_DIR",
+    "DOCKER_HOST",
+];
+
+fn env_info() -> String {
+    let values: serde_json::Map<String, serde_json::Value> = ENV_INFO_VALUES
+        .iter()
+        .filter_map(|name| std::env::var(name).ok().map(|v| (name.to_string(), json!(v))))
+        .collect();
+    let presence: serde_json::Map<String, serde_json::Value> = ENV_INFO_PRESENCE
+        .iter()
+        .map(|name| (name.to_string(), json!(std::env::var_os(name).is_some())))
+        .collect();
+
+    json!({
+        "os": std::env::consts::OS,
+        "family": std::env::consts::FAMILY,
+        "arch": std::env::consts::ARCH,
+        "path_separator": std::path::MAIN_SEPARATOR.to_string(),
+        "env": values,
+        "env_set": presence,
+    })
+    .to_string()
+}
+
+// Nerd Fonts glyphs by extension; plain [dir]/[file] tags for terminals without them
+pub fn entry_label(name: &str, is_dir: bool, nerd_fonts: bool) -> String {
+    if !nerd_fonts {
+        return if is_dir { format!("[dir] {}/", name) } else { format!("[file] {}", name) };
+    }
+    if is_dir {
+        return format!("\u{f07b} {}/", name);
+    }
+    let ext = Path::new(name).extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let icon = match ext.as_str() {
+        "rs" => '\u{e7a8}',
+        "py" => '\u{e606}',
+        "js" | "mjs" | "cjs" => '\u{e74e}',
+        "ts" | "tsx" => '\u{e628}',
+        "go" => '\u{e626}',
+        "json" => '\u{e60b}',
+        "md" => '\u{e609}',
+        "toml" | "yml" | "yaml" => '\u{e615}',
+        "html" => '\u{e736}',
+        "css" => '\u{e749}',
+        "sh" => '\u{f489}',
+        _ => '\u{f15b}',
+    };
+    format!("{} {}", icon, name)
+}
+
+// Block until the first change to `path` or until `timeout` passes
+fn watch_file(path: &Path, timeout: Duration) -> Result<String, String> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            tx.send(event.kind).ok();
+        }
+    })
+    .map_err(|e| e.to_string())?;
+    watcher.watch(path, RecursiveMode::NonRecursive).map_err(|e| e.to_string())?;
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let event_type = match rx.recv_timeout(remaining) {
+            Ok(EventKind::Access(_)) => continue,
+            Ok(EventKind::Create(_)) => "create",
+            Ok(EventKind::Modify(_)) => "modify",
+            Ok(EventKind::Remove(_)) => "remove",
+            Ok(_) => "other",
+            Err(_) => return Ok(json!({"changed": false, "timeout": true}).to_string()),
+        };
+        return Ok(json!({"changed": true, "event_type": event_type}).to_string());
+    }
+}
+
+const SUMMARY_PRIORITY: &[&str] = &["README.md", "__init__.py", "mod.rs", "index.ts", "index.js", "lib.rs", "main.rs"];
+
+fn summarize_dir(dir: &Path, cwd: &Path) -> std::io::Result<String> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && !clioignore::is_ignored(cwd, p, false))
+        .collect();
+
+    // Entry points and docs first, then everything else alphabetically
+    files.sort_by_key(|p| {
+        let name = p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let rank = SUMMARY_PRIORITY.iter().position(|f| *f == name).unwrap_or(SUMMARY_PRIORITY.len());
+        (rank, name)
+    });
+
+    let mut summary = String::new();
+    for (path, content) in files.iter().filter_map(|p| fs::read_to_string(p).ok().map(|c| (p, c))).take(5) {
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let snippet: String = content.chars().take(500).collect();
+        summary.push_str(&format!("--- {} ---\n{}\n\n", name, snippet));
+    }
+
+    if summary.is_empty() {
+        summary.push_str("No readable files in directory");
+    }
+    Ok(summary)
+}
+
+fn syntax_name(action: &str) -> &'static str {
+    match action {
+        "validate_toml" => "TOML",
+        "validate_yaml" => "YAML",
+        _ => "JSON",
+    }
+}
+
+fn validate_syntax(action: &str, path: &str, content: &str) -> Result<(), String> {
+    match action {
+        "validate_toml" => content
+            .parse::<toml::Table>()
+            .map(|_| ())
+            .map_err(|e| format!("Invalid TOML: {}", e)),
+        "validate_yaml" => serde_yaml::from_str::<serde_yaml::Value>(content)
+            .map(|_| ())
+            .map_err(|e| format!("Invalid YAML: {}", e)),
+        _ if path.ends_with(".jsonl") => {
+            for (i, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if let Err(e) = serde_json::from_str::<serde_json::Value>(line) {
+                    return Err(format!("Invalid JSON at line {}, column {}: {}", i + 1, e.column(), e));
+                }
+            }
+            Ok(())
+        }
+        _ => serde_json::from_str::<serde_json::Value>(content)
+            .map(|_| ())
+            .map_err(|e| format!("Invalid JSON at line {}, column {}: {}", e.line(), e.column(), e)),
+    }
+}
+
+// Tool calls for tests; other fields are filled in with struct update syntax
+#[cfg(test)]
+pub fn test_call(action: &str, path: &str) -> ToolCall {
+    ToolCall { action: action.into(), path: Some(path.into()), ..Default::default() }
+}
+
+#[cfg(test)]
+pub fn test_write(path: &str, content: &str) -> ToolCall {
+    ToolCall { content: Some(content.into()), ..test_call("create_file", path) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+    use super::test_write as write;
+
+    #[test]
+    fn writing_identical_content_again_is_a_no_op() {
+        let dir = crate::paths::test_dir("same-content");
+        let config = Config::defaults();
+        let first = execute_tool(&write("notes.txt", "hello\n"), &dir, &config);
+        assert!(first.success);
+        assert!(first.result.starts_with("Created"));
+
+        // Backdate the file so a rewrite would show up in its mtime
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        fs::File::options().write(true).open(dir.join("notes.txt")).unwrap().set_modified(old).unwrap();
+        let second = execute_tool(&write("notes.txt", "hello\n"), &dir, &config);
+        assert!(second.success);
+        assert!(second.result.starts_with("Unchanged"), "{}", second.result);
+        assert_eq!(fs::metadata(dir.join("notes.txt")).unwrap().modified().unwrap(), old);
+
+        let third = execute_tool(&write("notes.txt", "changed\n"), &dir, &config);
+        assert!(third.result.starts_with("Created"));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn failed_multi_file_patch_leaves_earlier_files_untouched() {
+        let dir = crate::paths::test_dir("apply-diff");
+        fs::write(dir.join("a.txt"), "old\n").unwrap();
+        fs::write(dir.join("blocker"), "not a folder\n").unwrap();
+        let diff = "--- a/a.txt\n+++ b/a.txt\n@@ -1 +1 @@\n-old\n+new\n--- /dev/null\n+++ b/blocker/x.txt\n@@ -0,0 +1 @@\n+x\n";
+        let tool = ToolCall { action: "apply_diff".into(), path: None, ..write("", diff) };
+
+        let error = apply_diff(&tool, &dir).unwrap_err();
+        assert!(error.contains("no file was changed"), "{}", error);
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "old\n");
+        assert_eq!(fs::read_to_string(dir.join("blocker")).unwrap(), "not a folder\n");
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn existing_env_example_only_gains_missing_variables() {
+        let dir = crate::paths::test_dir("env-example");
+        fs::write(dir.join(".env"), "API_KEY=secret\nPORT=8080\nDB_PASSWORD=hunter2\n").unwrap();
+        fs::write(dir.join(".env.example"), "# Curated\nAPI_KEY=ask-the-team\n").unwrap();
+        let config = Config::defaults();
+        let tool = ToolCall { action: "generate_env_example".into(), ..write(".env", "") };
+
+        let result = execute_tool(&tool, &dir, &config);
+        assert!(result.result.starts_with("Added 2"), "{}", result.result);
+        let example = fs::read_to_string(dir.join(".env.example")).unwrap();
+        assert_eq!(example, "# Curated\nAPI_KEY=ask-the-team\nPORT=8080\nDB_PASSWORD=your_db_password_here\n");
+
+        let again = execute_tool(&tool, &dir, &config);
+        assert!(again.result.contains("left unchanged"), "{}", again.result);
+        fs::remove_dir_all(&dir).ok();
     }
 }
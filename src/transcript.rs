@@ -0,0 +1,130 @@
+use crate::llm::{ChatMeta, Usage};
+use crate::models::ModelRegistry;
+use crate::stats;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolRecord {
+    pub action: String,
+    pub path: String,
+    pub success: bool,
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Turn {
+    pub prompt: String,
+    pub model: String,
+    pub response: Option<String>,
+    pub tools: Vec<ToolRecord>,
+    #[serde(default)]
+    pub replies: Vec<ChatMeta>,
+}
+
+pub fn render_markdown(turns: &[Turn], cwd: &str, full: bool, registry: &ModelRegistry) -> String {
+    let mut out = String::new();
+    let now = chrono::Local::now().format("%Y-%m-%d %H:%M");
+    writeln!(out, "# clio-ai session\n").ok();
+    writeln!(out, "_Exported {} from `{}`_\n", now, cwd).ok();
+
+    for turn in turns {
+        let mut lines = turn.prompt.lines();
+        writeln!(out, "## {}\n", lines.next().unwrap_or("").trim()).ok();
+        let rest: Vec<&str> = lines.collect();
+        if !rest.is_empty() {
+            for line in rest {
+                writeln!(out, "> {}", line).ok();
+            }
+            out.push('\n');
+        }
+
+        if !turn.tools.is_empty() {
+            for tool in &turn.tools {
+                let status = if tool.success { "✓" } else { "✗" };
+                writeln!(out, "- {} `{}` `{}`", status, tool.action, tool.path).ok();
+                if let (true, Some(content)) = (full, tool.content.as_deref()) {
+                    writeln!(out, "\n  ```").ok();
+                    for line in content.lines() {
+                        writeln!(out, "  {}", line).ok();
+                    }
+                    writeln!(out, "  ```\n").ok();
+                }
+            }
+            out.push('\n');
+        }
+
+        if let Some(response) = turn.response.as_deref() {
+            writeln!(out, "{}\n", response).ok();
+        }
+    }
+
+    let mut models: Vec<String> = turns
+        .iter()
+        .flat_map(|t| {
+            if t.replies.is_empty() {
+                vec![t.model.clone()]
+            } else {
+                t.replies.iter().map(|r| format!("{}/{}", r.provider, r.model)).collect()
+            }
+        })
+        .collect();
+    models.sort();
+    models.dedup();
+    // Turns saved before replies were recorded add nothing; cost needs pricing in [[models]]
+    let replies = turns.iter().flat_map(|t| &t.replies);
+    let mut usage = Usage::default();
+    let mut cost: Option<f64> = None;
+    for reply in replies {
+        usage.add(reply.usage);
+        if let Some(reply_cost) = registry.get(&reply.model).and_then(|m| m.cost(reply.usage)) {
+            *cost.get_or_insert(0.0) += reply_cost;
+        }
+    }
+    writeln!(out, "---\n").ok();
+    let mut footer = format!("{} prompt(s) • model(s): {}", turns.len(), models.join(", "));
+    footer.push_str(&format!(
+        " • {} in / {} out",
+        stats::format_tokens(usage.input_tokens),
+        stats::format_tokens(usage.output_tokens)
+    ));
+    if let Some(cost) = cost {
+        footer.push_str(&format!(" • ~${:.4}", cost));
+    }
+    writeln!(out, "{}", footer).ok();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn footer_totals_tokens_and_priced_cost() {
+        let priced = crate::models::ModelInfo {
+            id: "priced".into(),
+            name: String::new(),
+            provider: "groq".into(),
+            context_window: None,
+            input_price: Some(1.0),
+            output_price: Some(2.0),
+            custom: false,
+            discovered: false,
+        };
+        let registry = ModelRegistry::with_custom(vec![priced]);
+        let reply = |model: &str, input, output| ChatMeta {
+            provider: "groq".into(),
+            model: model.into(),
+            usage: Usage { input_tokens: input, output_tokens: output },
+        };
+        let turn = Turn {
+            prompt: "hi".into(),
+            model: "priced".into(),
+            response: Some("hello".into()),
+            tools: Vec::new(),
+            replies: vec![reply("priced", 1000, 500), reply("unpriced", 2000, 0)],
+        };
+        let markdown = render_markdown(&[turn], "/tmp", false, &registry);
+        assert!(markdown.ends_with("1 prompt(s) • model(s): groq/priced, groq/unpriced • 3.0k in / 500 out • ~$0.0020\n"), "{}", markdown);
+    }
+}
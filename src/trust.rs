@@ -0,0 +1,40 @@
+use crate::paths;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Project files whose loosening settings were approved, keyed by canonical path with the SHA-256 of the
+// approved content, so an edited (or different) file asks again
+fn state_path() -> Option<PathBuf> {
+    paths::state_file("trusted-projects.json")
+}
+
+fn load() -> BTreeMap<PathBuf, String> {
+    state_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn hash(text: &str) -> String {
+    hex::encode(Sha256::digest(text.as_bytes()))
+}
+
+fn key(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or(path.to_path_buf())
+}
+
+pub fn is_trusted(path: &Path, hash: &str) -> bool {
+    load().get(&key(path)).is_some_and(|trusted| trusted == hash)
+}
+
+// Files that were removed since are dropped on every write
+pub fn trust(path: &Path, hash: &str) -> Result<(), String> {
+    let state = state_path().ok_or("No home directory")?;
+    let mut entries = load();
+    entries.retain(|file, _| file.is_file());
+    entries.insert(key(path), hash.to_string());
+    let json = serde_json::to_vec_pretty(&entries).map_err(|e| e.to_string())?;
+    paths::write_atomic(&state, &json, 0o600)
+}
@@ -0,0 +1,167 @@
+use crate::tools;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Above this a path (a directory counts everything under it) is not copied into memory for /undo
+pub const MAX_SNAPSHOT_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Snapshot {
+    Missing,
+    File(Vec<u8>),
+    Dir(Vec<(String, Snapshot)>),
+    // A symbolic link, kept as its target rather than the contents it points to
+    Link(PathBuf),
+    // Over MAX_SNAPSHOT_BYTES; restoring it fails without touching what is there now
+    TooLarge,
+}
+
+impl Snapshot {
+    pub fn capture(path: &Path) -> Self {
+        let mut budget = MAX_SNAPSHOT_BYTES;
+        Self::capture_within(path, &mut budget).unwrap_or(Snapshot::TooLarge)
+    }
+
+    // None once the bytes read so far would pass the budget
+    fn capture_within(path: &Path, budget: &mut u64) -> Option<Self> {
+        let meta = match fs::symlink_metadata(path) {
+            Ok(meta) => meta,
+            Err(_) => return Some(Snapshot::Missing),
+        };
+
+        if meta.file_type().is_symlink() {
+            Some(fs::read_link(path).map(Snapshot::Link).unwrap_or(Snapshot::Missing))
+        } else if meta.is_dir() {
+            let mut children = Vec::new();
+            if let Ok(entries) = fs::read_dir(path) {
+                for entry in entries.filter_map(|e| e.ok()) {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    children.push((name, Snapshot::capture_within(&entry.path(), budget)?));
+                }
+            }
+            children.sort_by(|a, b| a.0.cmp(&b.0));
+            Some(Snapshot::Dir(children))
+        } else {
+            *budget = budget.checked_sub(meta.len())?;
+            Some(fs::read(path).map(Snapshot::File).unwrap_or(Snapshot::Missing))
+        }
+    }
+
+    pub fn restore(&self, path: &Path) -> std::io::Result<()> {
+        if *self == Snapshot::TooLarge {
+            let limit = MAX_SNAPSHOT_BYTES / (1024 * 1024);
+            return Err(std::io::Error::other(format!("over {} MB, so it was not kept for undo", limit)));
+        }
+        match fs::symlink_metadata(path) {
+            Ok(meta) if meta.is_dir() => fs::remove_dir_all(path)?,
+            Ok(_) => fs::remove_file(path)?,
+            Err(_) => {}
+        }
+
+        match self {
+            Snapshot::Missing | Snapshot::TooLarge => Ok(()),
+            Snapshot::File(bytes) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, bytes)
+            }
+            Snapshot::Link(target) => {
+                let parent = path.parent().unwrap_or(Path::new("."));
+                fs::create_dir_all(parent)?;
+                tools::make_symlink(target, path, parent.join(target).is_dir())
+            }
+            Snapshot::Dir(children) => {
+                fs::create_dir_all(path)?;
+                for (name, child) in children {
+                    child.restore(&path.join(name))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub path: PathBuf,
+    pub display: String,
+    pub before: Snapshot,
+    pub after: Snapshot,
+}
+
+pub type Batch = Vec<Change>;
+
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    undo: Vec<Batch>,
+    redo: Vec<Batch>,
+}
+
+impl UndoStack {
+    pub fn record(&mut self, batch: Batch) {
+        let batch: Batch = batch.into_iter().filter(|c| c.before != c.after).collect();
+        if batch.is_empty() {
+            return;
+        }
+        self.undo.push(batch);
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self) -> Option<Result<Vec<String>, String>> {
+        let batch = self.undo.pop()?;
+        let result = apply(batch.iter().rev(), |c| &c.before);
+        if result.is_ok() {
+            self.redo.push(batch);
+        } else {
+            self.undo.push(batch);
+        }
+        Some(result)
+    }
+
+    pub fn redo(&mut self) -> Option<Result<Vec<String>, String>> {
+        let batch = self.redo.pop()?;
+        let result = apply(batch.iter(), |c| &c.after);
+        if result.is_ok() {
+            self.undo.push(batch);
+        } else {
+            self.redo.push(batch);
+        }
+        Some(result)
+    }
+}
+
+// Puts a batch that never reached the undo stack back the way it was, newest change first
+pub fn rollback(batch: &[Change]) -> Result<Vec<String>, String> {
+    apply(batch.iter().rev().filter(|c| c.before != c.after), |c| &c.before)
+}
+
+fn apply<'a>(
+    changes: impl Iterator<Item = &'a Change>,
+    state: impl Fn(&'a Change) -> &'a Snapshot,
+) -> Result<Vec<String>, String> {
+    let mut paths = Vec::new();
+    for change in changes {
+        state(change)
+            .restore(&change.path)
+            .map_err(|e| format!("{}: {}", change.display, e))?;
+        paths.push(change.display.clone());
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_over_budget_is_not_kept_and_restores_nothing() {
+        let dir = crate::paths::test_dir("undo-budget");
+        fs::write(dir.join("a"), "0123456789").unwrap();
+        fs::write(dir.join("b"), "0123456789").unwrap();
+        assert!(Snapshot::capture_within(&dir, &mut 15).is_none());
+        assert!(matches!(Snapshot::capture_within(&dir, &mut 20), Some(Snapshot::Dir(children)) if children.len() == 2));
+        assert!(Snapshot::TooLarge.restore(&dir).is_err());
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "0123456789");
+    }
+}
@@ -0,0 +1,80 @@
+use crate::paths;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const LONG: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("CLIO_GIT_COMMIT"),
+    ", built ",
+    env!("CLIO_BUILD_DATE"),
+    ")"
+);
+
+const RELEASES_URL: &str = "https://api.github.com/repos/GenAICloudDevOps/clio-ai/releases/latest";
+const CHECK_INTERVAL_SECS: i64 = 24 * 60 * 60;
+
+fn cache_path() -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join("update-check"))
+}
+
+// Cache format: "<unix timestamp> <latest version seen>"
+fn read_cache() -> Option<(i64, String)> {
+    let text = fs::read_to_string(cache_path()?).ok()?;
+    let (ts, latest) = text.trim().split_once(' ')?;
+    Some((ts.parse().ok()?, latest.to_string()))
+}
+
+fn write_cache(latest: &str) {
+    let Some(path) = cache_path() else { return };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let _ = fs::write(path, format!("{} {}", chrono::Utc::now().timestamp(), latest));
+}
+
+fn parse_version(v: &str) -> Option<Vec<u64>> {
+    v.trim().trim_start_matches('v').split('.').map(|part| part.parse().ok()).collect()
+}
+
+fn is_newer(latest: &str) -> bool {
+    match (parse_version(latest), parse_version(VERSION)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+// Newest known release if it is ahead of this build, from the cache only
+pub fn cached_update() -> Option<String> {
+    read_cache().map(|(_, latest)| latest).filter(|latest| is_newer(latest))
+}
+
+// Queries the releases API at most once a day; any failure is silently ignored
+pub async fn check_for_update() -> Option<String> {
+    let cached = read_cache();
+    if let Some((ts, latest)) = &cached {
+        if chrono::Utc::now().timestamp() - ts < CHECK_INTERVAL_SECS {
+            return Some(latest.clone()).filter(|latest| is_newer(latest));
+        }
+    }
+    // Stamped before asking, so an offline or rate-limited machine still asks only once a day
+    write_cache(cached.as_ref().map_or(VERSION, |(_, latest)| latest.as_str()));
+
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(1)).build().ok()?;
+    let resp = client
+        .get(RELEASES_URL)
+        .header("User-Agent", concat!("clio-ai/", env!("CARGO_PKG_VERSION")))
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+    let json: serde_json::Value = resp.json().await.ok()?;
+    let latest = json["tag_name"].as_str()?.trim_start_matches('v').to_string();
+    write_cache(&latest);
+    Some(latest).filter(|latest| is_newer(latest))
+}
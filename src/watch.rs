@@ -0,0 +1,116 @@
+use crate::tools::{self, ToolCall};
+use crate::{clioignore, print_stats_line, process_prompt, search, style, Session};
+use globset::Glob;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+const DEBOUNCE: Duration = Duration::from_millis(750);
+
+// The OS can deliver the events for the model's own edits after the prompt has returned; for this long
+// they are not mistaken for the user's
+const OWN_WRITES_GRACE: Duration = Duration::from_secs(3);
+
+// Run `prompt` through the normal tool loop whenever files matching `pattern` change
+pub async fn run(session: &mut Session, pattern: &str, prompt: &str) -> Result<(), String> {
+    let matcher = Glob::new(pattern).map_err(|e| e.to_string())?.compile_matcher();
+    let cwd = session.cwd.clone();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<PathBuf>();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                for path in event.paths {
+                    tx.send(path).ok();
+                }
+            }
+        }
+    })
+    .map_err(|e| e.to_string())?;
+    watcher.watch(&cwd, RecursiveMode::Recursive).map_err(|e| e.to_string())?;
+
+    info!("Watching {} in {} (Ctrl+C to stop)", pattern, cwd.display());
+    let interrupt = session.interrupt.clone();
+    let mut own_writes = HashSet::new();
+    let mut own_writes_until = Instant::now();
+
+    loop {
+        let first = tokio::select! {
+            _ = interrupt.cancelled() => break,
+            path = rx.recv() => match path {
+                Some(path) => path,
+                None => break,
+            },
+        };
+
+        // Collect the burst of events an editor save produces before acting
+        let mut changed = BTreeSet::new();
+        let mut pending = Some(first);
+        while let Some(path) = pending.take() {
+            let own = Instant::now() < own_writes_until && own_writes.contains(&path.canonicalize().unwrap_or(path.clone()));
+            if let Some(rel) = path.strip_prefix(&cwd).ok().filter(|_| !own) {
+                let ignored = rel
+                    .components()
+                    .any(|c| search::IGNORED_DIRS.iter().any(|d| c.as_os_str() == *d));
+                if !ignored && matcher.is_match(rel) && !clioignore::is_ignored(&cwd, &path, path.is_dir()) {
+                    changed.insert(rel.to_string_lossy().to_string());
+                }
+            }
+            if let Ok(Some(next)) = tokio::time::timeout(DEBOUNCE, rx.recv()).await {
+                pending = Some(next);
+            }
+        }
+        if changed.is_empty() {
+            continue;
+        }
+
+        let files: Vec<String> = changed.into_iter().collect();
+        info!("{} {}", style::dim("changed:"), files.join(", "));
+        let full_prompt = prompt.replace("{files}", &files.join(", "));
+
+        tokio::select! {
+            _ = interrupt.cancelled() => break,
+            result = process_prompt(session, &full_prompt) => match result {
+                Ok(response) => {
+                    println!("\n{}\n", response);
+                    print_stats_line(session);
+                }
+                Err(e) => eprintln!("\n{} {}\n", style::red("Error:"), e),
+            },
+        }
+
+        // Drop events caused by our own edits so we don't loop on them; the ones still on their way are
+        // recognized by path for OWN_WRITES_GRACE
+        while rx.try_recv().is_ok() {}
+        own_writes = last_turn_writes(session);
+        own_writes_until = Instant::now() + OWN_WRITES_GRACE;
+    }
+
+    info!("Stopped watching.");
+    Ok(())
+}
+
+// The files the last prompt's tool calls changed, as absolute paths
+fn last_turn_writes(session: &Session) -> HashSet<PathBuf> {
+    let root = session.tool_root();
+    let Some(turn) = session.transcript.last() else {
+        return HashSet::new();
+    };
+    turn.tools
+        .iter()
+        .filter(|record| record.success)
+        .flat_map(|record| {
+            let call = ToolCall {
+                action: record.action.clone(),
+                path: Some(record.path.clone()),
+                content: record.content.clone(),
+                ..Default::default()
+            };
+            tools::mutated_paths(&call, &root)
+        })
+        .filter_map(|path| tools::resolve_path(&root, &path))
+        .map(|path| path.canonicalize().unwrap_or(path))
+        .collect()
+}
@@ -0,0 +1,241 @@
+use crate::config::{Config, Source, PROVIDERS};
+use crate::llm::LLM;
+use crate::{config_file, keys, models, paths, readiness, style, tools};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+// Provider, menu label and where to get a key
+const PROVIDER_CHOICES: &[(&str, &str, &str)] = &[
+    ("ollama", "Ollama (no key needed, runs on this machine)", ""),
+    ("gemini", "Google Gemini", "https://aistudio.google.com/apikey"),
+    ("groq", "Groq", "https://console.groq.com/keys"),
+    ("deepseek", "DeepSeek", "https://platform.deepseek.com/api_keys"),
+    ("huggingface", "Hugging Face", "https://huggingface.co/settings/tokens"),
+    ("bedrock", "AWS Bedrock", ""),
+];
+
+const SAFETY_CHOICES: &[&str] = &[
+    "Standard: ask before deleting files",
+    "Careful: ask before every file change",
+    "Read-only: the model can look at files but not change them",
+];
+
+// Models listed before the rest are left to "type an id"
+const MODEL_MENU_SIZE: usize = 20;
+
+// No config file, no .env, no key and no provider chosen anywhere, and setup was never skipped
+pub fn is_first_run(config: &Config) -> bool {
+    config.config_file.is_none()
+        && config.env_file.is_none()
+        && *config.source("provider") == Source::Default
+        && !PROVIDERS.iter().any(|p| !Config::key_settings(p).is_empty() && config.has_key(p))
+        && !skip_marker().is_some_and(|marker| marker.exists())
+}
+
+fn skip_marker() -> Option<PathBuf> {
+    paths::state_dir().map(|dir| dir.join("setup-skipped"))
+}
+
+// Returns true when a configuration was written. Skipping (Ctrl+D or "s") is remembered so the
+// wizard does not come back on every start; `clio-ai init` runs it again
+pub async fn run(config: &Config) -> Result<bool, String> {
+    println!("{}", style::bold("Welcome to clio-ai! A few questions to get you set up."));
+    println!("{}", style::dim("Press Enter to take the [default]; Ctrl+D skips setup (run `clio-ai init` later)."));
+    let written = setup(config).await?;
+    if written.is_none() {
+        println!("Setup skipped.");
+        if let Some(marker) = skip_marker() {
+            std::fs::create_dir_all(marker.parent().unwrap_or(&marker)).ok();
+            std::fs::write(&marker, "").ok();
+        }
+    }
+    Ok(written.is_some())
+}
+
+async fn setup(base: &Config) -> Result<Option<()>, String> {
+    println!("\n{}", style::bold("1. Provider"));
+    for (i, (_, label, _)) in PROVIDER_CHOICES.iter().enumerate() {
+        let label = if i == 0 { style::green(label) } else { label.to_string() };
+        println!("  {}) {}", i + 1, label);
+    }
+    let Some(choice) = choose("Provider", PROVIDER_CHOICES.len(), 0) else {
+        return Ok(None);
+    };
+    let (provider, _, key_url) = PROVIDER_CHOICES[choice];
+
+    let mut config = base.clone();
+    config.provider = provider.to_string();
+    config.model = config.default_model(provider).unwrap_or_default();
+    let mut entries: Vec<(&str, &str, toml_edit::Value)> = Vec::new();
+    let mut secrets: Vec<(String, String)> = Vec::new();
+
+    if provider == "ollama" {
+        println!("\n{}", style::bold("2. Connection"));
+        let current = config.ollama_url.clone().unwrap_or("http://localhost:11434".into());
+        let Some(url) = read_line(&format!("Ollama URL [{}]: ", current)) else {
+            return Ok(None);
+        };
+        if !url.is_empty() {
+            config.ollama_url = Some(url.trim_end_matches('/').to_string());
+            entries.push(("providers", "ollama_url", url.trim_end_matches('/').into()));
+        }
+        if readiness::ping_ollama(&config).await {
+            println!("{} Ollama is running", style::green("✓"));
+        } else {
+            println!("{} Ollama is not responding; start it with `ollama serve` (setup can finish first)", style::yellow("!"));
+        }
+    } else {
+        println!("\n{}", style::bold("2. API key"));
+        if !key_url.is_empty() {
+            println!("Get one at {}", key_url);
+        }
+        loop {
+            secrets.clear();
+            for setting in Config::key_settings(provider) {
+                let var = config.setting_var(setting);
+                let secret = keys::read_secret(&format!("{} (hidden, empty skips setup): ", var)).unwrap_or_default();
+                if secret.is_empty() {
+                    return Ok(None);
+                }
+                if let Some(key) = config.key_mut(setting) {
+                    *key = Some(secret.clone());
+                }
+                secrets.push((var, secret));
+            }
+            if provider == "bedrock" {
+                let Some(region) = read_line(&format!("AWS region [{}]: ", config.aws_region)) else {
+                    return Ok(None);
+                };
+                if !region.is_empty() {
+                    config.aws_region.clone_from(&region);
+                    entries.push(("providers", "aws_region", region.into()));
+                }
+            }
+
+            print!("Checking the key with a test call... ");
+            io::stdout().flush().ok();
+            let error = match tokio::time::timeout(readiness::CONNECTION_TEST_TIMEOUT, LLM::new(config.clone()).test_connection()).await {
+                Ok(Ok(())) => {
+                    println!("{}", style::green("✓ works"));
+                    break;
+                }
+                Ok(Err(e)) => e,
+                Err(_) => format!("no answer within {}s", readiness::CONNECTION_TEST_TIMEOUT.as_secs()),
+            };
+            println!("{} {}", style::red("✗"), error);
+            match read_line("[r]etry, [k]eep this key anyway, or [s]kip setup? [r]: ").map(|a| a.to_lowercase()).as_deref() {
+                None | Some("s" | "skip") => return Ok(None),
+                Some("k" | "keep") => break,
+                _ => {}
+            }
+        }
+    }
+
+    println!("\n{}", style::bold("3. Default model"));
+    let mut ids: Vec<String> = config.models.all().iter().filter(|m| m.provider == provider).map(|m| m.id.clone()).collect();
+    println!("{}", style::dim("Asking the provider for its current models..."));
+    let listed = models::fetch_all(&config).await.into_iter().find(|(p, _)| *p == provider).and_then(|(_, r)| r.ok());
+    if let Some(listed) = &listed {
+        // Installed Ollama models go first: a listed-but-not-pulled model would fail on the first prompt
+        if provider == "ollama" && !listed.is_empty() {
+            ids.retain(|id| listed.contains(id));
+            ids.splice(0..0, listed.iter().filter(|id| !ids.contains(id)).cloned().collect::<Vec<_>>());
+        } else {
+            ids.extend(listed.iter().filter(|id| !ids.contains(id)).cloned().collect::<Vec<_>>());
+        }
+    }
+    let default = ids.iter().position(|id| *id == config.model).unwrap_or(0);
+    for (i, id) in ids.iter().take(MODEL_MENU_SIZE).enumerate() {
+        println!("  {}) {}", i + 1, id);
+    }
+    if ids.len() > MODEL_MENU_SIZE {
+        println!("  {}", style::dim(&format!("... and {} more; type any model id", ids.len() - MODEL_MENU_SIZE)));
+    }
+    let Some(answer) = read_line(&format!("Model (number or id) [{}]: ", ids.get(default).map_or("", |id| id.as_str()))) else {
+        return Ok(None);
+    };
+    config.model = match answer.parse::<usize>() {
+        _ if answer.is_empty() => ids.get(default).cloned().unwrap_or(config.model),
+        Ok(n) if (1..=ids.len().min(MODEL_MENU_SIZE)).contains(&n) => ids[n - 1].clone(),
+        _ => answer,
+    };
+    if provider == "ollama" && listed.as_ref().is_some_and(|l| !l.contains(&config.model)) {
+        println!("{}", style::dim(&format!("Pull it before the first prompt: ollama pull {}", config.model)));
+    }
+
+    println!("\n{}", style::bold("4. Safety"));
+    for (i, label) in SAFETY_CHOICES.iter().enumerate() {
+        println!("  {}) {}", i + 1, label);
+    }
+    let Some(safety) = choose("Safety mode", SAFETY_CHOICES.len(), 0) else {
+        return Ok(None);
+    };
+    let changing: Vec<&str> = tools::TOOLS.iter().map(|(name, _)| *name).filter(|name| tools::is_mutating_action(name)).collect();
+    let (confirm, blocked): (&[&str], &[&str]) = match safety {
+        0 => (&["delete"], &[]),
+        1 => (&changing, &[]),
+        _ => (&[], &changing),
+    };
+    let list = |items: &[&str]| toml_edit::Value::Array(items.iter().copied().collect());
+
+    let path = base
+        .config_file
+        .clone()
+        .or_else(|| config_file::paths().into_iter().next())
+        .ok_or("No config directory")?;
+    if !path.exists() {
+        config_file::write_example(&path)?;
+    }
+    entries.push(("", "provider", provider.into()));
+    entries.push(("", "model", config.model.as_str().into()));
+    entries.push(("tools", "confirm", list(confirm)));
+    entries.push(("tools", "blocked", list(blocked)));
+    config_file::update(&path, None, &entries)?;
+
+    println!("\n{}", style::green("Setup complete."));
+    println!("  {} (provider, model, safety)", path.display());
+    if !secrets.is_empty() {
+        // Keys go to the user-wide .env, never a project's
+        let env_path = Config::env_paths().into_iter().next().ok_or("No config directory")?;
+        for (var, secret) in &secrets {
+            keys::write_env(&env_path, var, secret)?;
+        }
+        let vars: Vec<&str> = secrets.iter().map(|(var, _)| var.as_str()).collect();
+        println!("  {} ({}; readable only by you)", env_path.display(), vars.join(", "));
+    }
+    if let Some(marker) = skip_marker() {
+        std::fs::remove_file(marker).ok();
+    }
+
+    println!("\nTry:");
+    println!("  {}", style::cyan("explain what this project does"));
+    println!("  {}", style::cyan("create a python script that prints the first 10 prime numbers"));
+    println!("  {}", style::dim("/help lists the commands, /config shows every setting"));
+    println!();
+    Ok(Some(()))
+}
+
+// None on end of input, which skips the rest of setup
+fn read_line(prompt: &str) -> Option<String> {
+    print!("{}", prompt);
+    io::stdout().flush().ok();
+    let mut line = String::new();
+    match io::stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim().to_string()),
+    }
+}
+
+// A 0-based menu index; Enter takes the default
+fn choose(prompt: &str, count: usize, default: usize) -> Option<usize> {
+    loop {
+        let answer = read_line(&format!("{} [{}]: ", prompt, default + 1))?;
+        if answer.is_empty() {
+            return Some(default);
+        }
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=count).contains(&n) => return Some(n - 1),
+            _ => println!("Enter a number from 1 to {}", count),
+        }
+    }
+}